@@ -1,12 +1,14 @@
 import * as path from 'path';
-import { workspace, ExtensionContext, IndentAction, languages, window, Uri, TextDocument } from 'vscode';
+import { workspace, ExtensionContext, IndentAction, languages, window, Uri, TextDocument, commands } from 'vscode';
 
 import {
 	LanguageClient,
 	LanguageClientOptions,
 	ServerOptions,
 	TransportKind,
-	Executable
+	Executable,
+	Location,
+	Position
 } from 'vscode-languageclient/node';
 
 let client: LanguageClient;
@@ -79,8 +81,13 @@ export function activate(context: ExtensionContext) {
 	let clientOptions: LanguageClientOptions = {
 		documentSelector: [{ scheme: 'file', language: 'php' }],
 		synchronize: {
+			configurationSection: 'phplsrs',
 			fileEvents: workspace.createFileSystemWatcher('**/*.php')
 		},
+		initializationOptions: {
+			inlayHints: workspace.getConfiguration('phplsrs').get('inlayHints'),
+			diagnostics: workspace.getConfiguration('phplsrs').get('diagnostics')
+		},
 	};
 
 	client = new LanguageClient(
@@ -90,6 +97,17 @@ export function activate(context: ExtensionContext) {
 		clientOptions
 	);
 
+	// Clicking a code lens shows the locations it counted
+	context.subscriptions.push(commands.registerCommand(
+		'phplsrs.showReferences',
+		(uri: string, position: Position, locations: Location[]) => commands.executeCommand(
+			'editor.action.showReferences',
+			Uri.parse(uri),
+			client.protocol2CodeConverter.asPosition(position),
+			locations.map(client.protocol2CodeConverter.asLocation)
+		)
+	));
+
 	client.start();
 }
 
@@ -0,0 +1,541 @@
+use super::{reference_index, Backend, BackendState};
+use crate::environment::symbol::{FunctionParameter, PhpSymbolKind, Symbol};
+use crate::parser::node::{Node as AstNode, NodeRange};
+use indextree::NodeId;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashSet;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+use tokio::io;
+use tower_lsp::lsp_types::{Diagnostic, Range};
+
+/// Version of the layout of the cached entries. Bump it whenever `Symbol` or any other cached
+/// type changes, as entries written before would silently load with default values otherwise.
+const CACHE_SCHEMA_VERSION: u32 = 2;
+
+/// Describes what the cached entries were created with. If anything differs, the cache is invalid.
+#[derive(Serialize, Deserialize, PartialEq)]
+struct Manifest {
+    version: String,
+    schema: u32,
+    stubs: String,
+}
+
+/// Modification time and content hash of the file an entry was created from
+#[derive(Serialize, Deserialize, PartialEq)]
+struct Stamp {
+    mtime: u64,
+    hash: u64,
+}
+
+impl Stamp {
+    fn of(path: &Path, content: &str) -> Self {
+        Stamp {
+            mtime: mtime(path).unwrap_or_default(),
+            hash: hash(content),
+        }
+    }
+}
+
+/// A symbol and its children, detached from the arena
+#[derive(Serialize, Deserialize)]
+struct CachedSymbol {
+    symbol: Symbol,
+
+    /// Parameters as the index of the child symbol and the ast of the parameter
+    parameters: Vec<(usize, AstNode)>,
+
+    children: Vec<CachedSymbol>,
+}
+
+/// The symbols and diagnostics of a single file
+#[derive(Serialize, Deserialize)]
+pub(crate) struct CachedFile {
+    pub path: String,
+    stamp: Stamp,
+    range: Range,
+    diagnostics: Vec<Diagnostic>,
+    symbols: Vec<CachedSymbol>,
+}
+
+/// The references of a single file by the keys of the referenced symbols, and the names it
+/// mentions. Unlike the symbols, they depend on other files and are only used if those did not
+/// change either.
+#[derive(Serialize, Deserialize)]
+pub(crate) struct CachedReferences {
+    pub path: String,
+    stamp: Stamp,
+    references: Vec<(String, Vec<NodeRange>)>,
+    mentioned_names: HashSet<String>,
+}
+
+/// Only the path of a cached entry, to find entries of files that no longer exist
+#[derive(Deserialize)]
+struct CachedPath {
+    path: String,
+}
+
+/// On-disk cache of the symbols of every indexed file. Each file is stored as a separate entry
+/// keyed by its path and only used if modification time and content hash did not change.
+#[derive(Clone)]
+pub(crate) struct IndexCache {
+    dir: PathBuf,
+}
+
+impl IndexCache {
+    /// Open the cache in `dir` and clear it if it was created by another version of the server,
+    /// with another schema or with different stubs
+    pub fn open(dir: &Path, stubs: &str) -> io::Result<Self> {
+        let manifest = Manifest {
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            schema: CACHE_SCHEMA_VERSION,
+            stubs: stubs.to_owned(),
+        };
+        let manifest_path = dir.join("manifest.json");
+
+        let existing = fs::read_to_string(&manifest_path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<Manifest>(&content).ok());
+
+        if existing.as_ref() != Some(&manifest) {
+            if dir.exists() {
+                fs::remove_dir_all(dir)?;
+            }
+            fs::create_dir_all(dir)?;
+            fs::write(&manifest_path, serde_json::to_string(&manifest)?)?;
+        } else {
+            prune(dir)?;
+        }
+
+        Ok(IndexCache {
+            dir: dir.to_path_buf(),
+        })
+    }
+
+    /// The default location of the cache of a workspace
+    pub fn default_dir(root: &Path) -> Option<PathBuf> {
+        let base = std::env::var_os("XDG_CACHE_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))?;
+
+        Some(
+            base.join("phpls-rs")
+                .join(format!("{:x}", hash(&root.to_string_lossy()))),
+        )
+    }
+
+    /// Return the cached entry of a file if it is still up to date
+    pub fn load(&self, path: &Path, content: &str) -> Option<CachedFile> {
+        let cached: CachedFile = read(&self.entry(path))?;
+
+        if mtime(path).is_some() && cached.stamp == Stamp::of(path, content) {
+            Some(cached)
+        } else {
+            None
+        }
+    }
+
+    /// Write the entry of a freshly indexed file
+    pub fn store(&self, cached: &CachedFile) -> io::Result<()> {
+        fs::write(
+            self.entry(Path::new(&cached.path)),
+            serde_json::to_string(cached)?,
+        )
+    }
+
+    /// Return the cached references of a file if the file is still up to date
+    pub fn load_references(&self, path: &Path, content: &str) -> Option<CachedReferences> {
+        let cached: CachedReferences = read(&self.references_entry(path))?;
+
+        if mtime(path).is_some() && cached.stamp == Stamp::of(path, content) {
+            Some(cached)
+        } else {
+            None
+        }
+    }
+
+    /// Write the references of a freshly indexed file
+    pub fn store_references(&self, cached: &CachedReferences) -> io::Result<()> {
+        fs::write(
+            self.references_entry(Path::new(&cached.path)),
+            serde_json::to_string(cached)?,
+        )
+    }
+
+    fn entry(&self, path: &Path) -> PathBuf {
+        self.dir
+            .join(format!("{:x}.json", hash(&path.to_string_lossy())))
+    }
+
+    fn references_entry(&self, path: &Path) -> PathBuf {
+        self.dir.join(format!(
+            "{:x}.references.json",
+            hash(&path.to_string_lossy())
+        ))
+    }
+}
+
+impl CachedFile {
+    /// Detach the symbols and diagnostics of an indexed file from the state
+    pub fn new(path: &Path, content: &str, state: &BackendState) -> Option<Self> {
+        let key = path.to_string_lossy().to_string();
+        let file = *state.files.get(&key)?;
+
+        Some(CachedFile {
+            stamp: Stamp::of(path, content),
+            range: state.arena[file].get().range,
+            diagnostics: state
+                .diagnostics
+                .get(&key)
+                .map(|d| d.to_vec())
+                .unwrap_or_default(),
+            symbols: file
+                .children(&state.arena)
+                .map(|child| detach(child, state))
+                .collect(),
+            path: key,
+        })
+    }
+
+    /// Put the cached symbols back into the arena, register the file and return its path
+    pub fn restore(self, state: &mut BackendState) -> String {
+        let enclosing_file = state.arena.new_node(Symbol {
+            kind: PhpSymbolKind::File,
+            name: self.path.clone(),
+            range: self.range,
+            selection_range: self.range,
+            ..Symbol::default()
+        });
+
+        for symbol in self.symbols {
+            attach(symbol, enclosing_file, state);
+        }
+
+        Backend::register_file(&self.path, enclosing_file, state);
+        state
+            .diagnostics
+            .insert(self.path.clone(), self.diagnostics.into());
+
+        self.path
+    }
+}
+
+impl CachedReferences {
+    /// Detach the references of an indexed file from the state. References to variables are left
+    /// out, as only the references of opened files to them are ever looked at.
+    pub fn new(path: &Path, content: &str, state: &BackendState) -> Option<Self> {
+        let key = path.to_string_lossy().to_string();
+
+        Some(CachedReferences {
+            stamp: Stamp::of(path, content),
+            references: state
+                .symbol_references
+                .get(&key)?
+                .iter()
+                .filter_map(|(node, ranges)| {
+                    Some((reference_index::symbol_key(state, *node)?, ranges.clone()))
+                })
+                .collect(),
+            mentioned_names: state
+                .mentioned_names
+                .get(&key)
+                .map(|names| names.iter().cloned().collect())
+                .unwrap_or_default(),
+            path: key,
+        })
+    }
+
+    /// Put the references back into the index. The symbols of all files must be restored first.
+    pub fn restore(self, state: &mut BackendState) {
+        reference_index::restore(state, &self.path, self.references, self.mentioned_names);
+    }
+}
+
+fn detach(node: NodeId, state: &BackendState) -> CachedSymbol {
+    let symbol = state.arena[node].get();
+    let children = node.children(&state.arena).collect::<Vec<NodeId>>();
+
+    CachedSymbol {
+        symbol: symbol.clone(),
+        parameters: symbol
+            .parameters
+            .iter()
+            .filter_map(|parameter| {
+                let index = children.iter().position(|c| *c == parameter.symbol)?;
+
+                Some((index, parameter.ast.clone()))
+            })
+            .collect(),
+        children: children.iter().map(|child| detach(*child, state)).collect(),
+    }
+}
+
+fn attach(cached: CachedSymbol, parent: NodeId, state: &mut BackendState) {
+    let node = state.arena.new_node(cached.symbol);
+    parent.append(node, &mut state.arena);
+
+    let children = cached
+        .children
+        .into_iter()
+        .map(|child| {
+            attach(child, node, state);
+
+            node.children(&state.arena).last().unwrap()
+        })
+        .collect::<Vec<NodeId>>();
+
+    state.arena[node].get_mut().parameters = cached
+        .parameters
+        .iter()
+        .map(|(index, ast)| FunctionParameter::new(&children[*index], ast))
+        .collect();
+}
+
+/// Remove the entries of files that were deleted or renamed since they were cached
+fn prune(dir: &Path) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?.path();
+
+        if entry.file_name().is_none_or(|name| name == "manifest.json") {
+            continue;
+        }
+
+        let exists = fs::read_to_string(&entry)
+            .ok()
+            .and_then(|content| serde_json::from_str::<CachedPath>(&content).ok())
+            .is_some_and(|cached| Path::new(&cached.path).exists());
+
+        if !exists {
+            fs::remove_file(&entry)?;
+        }
+    }
+
+    Ok(())
+}
+
+fn read<T: DeserializeOwned>(entry: &Path) -> Option<T> {
+    serde_json::from_str(&fs::read_to_string(entry).ok()?).ok()
+}
+
+fn mtime(path: &Path) -> Option<u64> {
+    let modified = fs::metadata(path).ok()?.modified().ok()?;
+
+    Some(modified.duration_since(UNIX_EPOCH).ok()?.as_nanos() as u64)
+}
+
+/// Hash used for cache keys and contents. The default hasher is stable for a given
+/// build, and the cache is invalidated with every new version anyway.
+fn hash<T: Hash + ?Sized>(value: &T) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+
+    hasher.finish()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::tests::populate_state;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("phpls-rs-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+
+        dir
+    }
+
+    #[test]
+    fn test_restores_symbols_of_unchanged_files() {
+        let dir = temp_dir("cache-restore");
+        let file = dir.join("user.php");
+        let source = "<?php namespace App; class User { public function rename(string $name) {} }";
+        fs::write(&file, source).unwrap();
+
+        let path = file.to_string_lossy().to_string();
+        let mut state = BackendState::default();
+        populate_state(&mut state, &[(&path, source)]);
+
+        let cache = IndexCache::open(&dir.join("cache"), "/stubs").unwrap();
+        cache
+            .store(&CachedFile::new(&file, source, &state).unwrap())
+            .unwrap();
+
+        let mut restored = BackendState::default();
+        cache.load(&file, source).unwrap().restore(&mut restored);
+
+        let class = *restored.global_symbols.get("app\\user").unwrap();
+        let method = class
+            .children(&restored.arena)
+            .find(|child| restored.arena[*child].get().name == "rename")
+            .unwrap();
+        let parameters = &restored.arena[method].get().parameters;
+
+        assert_eq!(1, parameters.len());
+        assert_eq!(
+            Some(&parameters[0].symbol),
+            method.children(&restored.arena).next().as_ref()
+        );
+        assert_eq!(
+            Some(&file.to_string_lossy().to_string()),
+            restored.files.keys().next()
+        );
+
+        // Changed content is not served from the cache
+        assert!(cache.load(&file, "<?php class Other {}").is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_restores_references_of_unchanged_files() {
+        let dir = temp_dir("cache-references");
+        let declaration = dir.join("a.php");
+        let caller = dir.join("b.php");
+        let sources = ["<?php function greet() {}", "<?php\ngreet();\n$unused = 1;"];
+        fs::write(&declaration, sources[0]).unwrap();
+        fs::write(&caller, sources[1]).unwrap();
+
+        let paths = [
+            declaration.to_string_lossy().to_string(),
+            caller.to_string_lossy().to_string(),
+        ];
+        let mut state = BackendState::default();
+        populate_state(
+            &mut state,
+            &[(&paths[0], sources[0]), (&paths[1], sources[1])],
+        );
+
+        let cache = IndexCache::open(&dir.join("cache"), "/stubs").unwrap();
+        cache
+            .store_references(&CachedReferences::new(&caller, sources[1], &state).unwrap())
+            .unwrap();
+
+        // Only the symbols are known before the references are restored
+        let mut restored = BackendState::default();
+        for (path, source) in paths.iter().zip(sources.iter()) {
+            let (ast, range, _) = Backend::source_to_ast(source).unwrap();
+            Backend::collect_symbols(path, &ast, &range, &mut restored).unwrap();
+        }
+        cache
+            .load_references(&caller, sources[1])
+            .unwrap()
+            .restore(&mut restored);
+
+        let function = restored.global_symbols["greet"];
+        let references = reference_index::references_of(&restored, function);
+        assert_eq!(1, references[&paths[1]][&function].len());
+
+        // Variables are local to the file and left out
+        assert_eq!(1, restored.symbol_references[&paths[1]].len());
+        assert!(restored.mentioned_names[&paths[1]].contains("greet"));
+        assert!(restored.stale_references.is_empty());
+
+        // Changed content is not served from the cache
+        assert!(cache.load_references(&caller, "<?php\n").is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_prunes_entries_of_deleted_files() {
+        let dir = temp_dir("cache-prune");
+        let kept = dir.join("kept.php");
+        let deleted = dir.join("deleted.php");
+        let source = "<?php function foo() {}";
+        fs::write(&kept, source).unwrap();
+        fs::write(&deleted, source).unwrap();
+
+        let mut state = BackendState::default();
+        populate_state(
+            &mut state,
+            &[
+                (&kept.to_string_lossy(), source),
+                (&deleted.to_string_lossy(), source),
+            ],
+        );
+
+        let cache_dir = dir.join("cache");
+        let cache = IndexCache::open(&cache_dir, "/stubs").unwrap();
+        cache
+            .store(&CachedFile::new(&kept, source, &state).unwrap())
+            .unwrap();
+        cache
+            .store(&CachedFile::new(&deleted, source, &state).unwrap())
+            .unwrap();
+
+        fs::remove_file(&deleted).unwrap();
+
+        let cache = IndexCache::open(&cache_dir, "/stubs").unwrap();
+        assert!(cache.load(&kept, source).is_some());
+        assert!(!cache.entry(&deleted).exists());
+        assert!(cache_dir.join("manifest.json").exists());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalidates_cache_when_stubs_change() {
+        let dir = temp_dir("cache-invalidate");
+        let file = dir.join("index.php");
+        let source = "<?php function foo() {}";
+        fs::write(&file, source).unwrap();
+
+        let path = file.to_string_lossy().to_string();
+        let mut state = BackendState::default();
+        populate_state(&mut state, &[(&path, source)]);
+
+        let cache_dir = dir.join("cache");
+        IndexCache::open(&cache_dir, "/stubs")
+            .unwrap()
+            .store(&CachedFile::new(&file, source, &state).unwrap())
+            .unwrap();
+
+        assert!(IndexCache::open(&cache_dir, "/stubs")
+            .unwrap()
+            .load(&file, source)
+            .is_some());
+        assert!(IndexCache::open(&cache_dir, "/other/stubs")
+            .unwrap()
+            .load(&file, source)
+            .is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_invalidates_cache_of_other_schema() {
+        let dir = temp_dir("cache-schema");
+        let file = dir.join("index.php");
+        let source = "<?php function foo() {}";
+        fs::write(&file, source).unwrap();
+
+        let path = file.to_string_lossy().to_string();
+        let mut state = BackendState::default();
+        populate_state(&mut state, &[(&path, source)]);
+
+        let cache_dir = dir.join("cache");
+        IndexCache::open(&cache_dir, "/stubs")
+            .unwrap()
+            .store(&CachedFile::new(&file, source, &state).unwrap())
+            .unwrap();
+
+        // A manifest written before the schema was recorded
+        let manifest = serde_json::json!({
+            "version": env!("CARGO_PKG_VERSION"),
+            "stubs": "/stubs",
+        });
+        fs::write(cache_dir.join("manifest.json"), manifest.to_string()).unwrap();
+
+        assert!(IndexCache::open(&cache_dir, "/stubs")
+            .unwrap()
+            .load(&file, source)
+            .is_none());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
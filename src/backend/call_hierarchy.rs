@@ -0,0 +1,375 @@
+use super::signature_help::callees;
+use super::{Backend, BackendState, ReferenceMap};
+use crate::environment::symbol::PhpSymbolKind;
+use crate::environment::{fs as EnvFs, get_range, in_range};
+use crate::parser::node::Node as AstNode;
+use crate::parser::token::TokenType;
+use indextree::NodeId;
+use lsp_types::{
+    CallHierarchyIncomingCall, CallHierarchyItem, CallHierarchyOutgoingCall,
+    CallHierarchyPrepareParams, Position, Range, SymbolKind, SymbolTag, Url,
+};
+use tower_lsp::jsonrpc::Result;
+
+/// Gateway function that accepts the LSP parameters and returns the function or method at the
+/// cursor, either where it is declared or where it is called
+pub(crate) fn prepare_call_hierarchy(
+    state: &BackendState,
+    params: CallHierarchyPrepareParams,
+) -> Result<Option<Vec<CallHierarchyItem>>> {
+    let position = &params.text_document_position_params.position;
+    let file = EnvFs::normalize_path(
+        &params
+            .text_document_position_params
+            .text_document
+            .uri
+            .to_file_path()
+            .unwrap(),
+    );
+
+    let referenced = state
+        .symbol_references
+        .get(&file)
+        .and_then(|references| Backend::referenced_symbol_under_cursor(position, references))
+        .copied();
+    let node = referenced
+        .or_else(|| Backend::symbol_under_cursor(state, position, &file).map(|(node, _)| node));
+
+    Ok(node
+        .filter(|node| is_callable(state, *node))
+        .and_then(|node| hierarchy_item(state, node))
+        .map(|item| vec![item]))
+}
+
+/// Find the function or method an item of the call hierarchy was created for
+pub(crate) fn callable_of_item(state: &BackendState, item: &CallHierarchyItem) -> Option<NodeId> {
+    let file = EnvFs::normalize_path(&item.uri.to_file_path().ok()?);
+    let (node, name) = Backend::symbol_under_cursor(state, &item.selection_range.start, &file)?;
+
+    if is_callable(state, node) && name == item.name {
+        Some(node)
+    } else {
+        None
+    }
+}
+
+/// All functions and methods calling the item, with the references to it grouped by the
+/// caller. Calls outside of any function are attributed to the file.
+pub(crate) fn incoming_calls(
+    state: &BackendState,
+    item: &CallHierarchyItem,
+    references: &ReferenceMap,
+) -> Option<Vec<CallHierarchyIncomingCall>> {
+    let callable = callable_of_item(state, item)?;
+    let declaration = state.arena[callable].get().selection_range;
+    let declared_in = enclosing_file(state, callable)?;
+
+    let mut callers: Vec<(NodeId, Vec<Range>)> = Vec::new();
+    let mut files = references.keys().collect::<Vec<&String>>();
+    files.sort();
+
+    for file in files {
+        let mut ranges: Vec<Range> = if let Some(ranges) = references[file].get(&callable) {
+            ranges.iter().map(|range| get_range(*range)).collect()
+        } else {
+            continue;
+        };
+        ranges.sort_by_key(|range| range.start);
+
+        for range in ranges {
+            // The name in the declaration is a reference as well
+            if file == &state.arena[declared_in].get().name && range.start == declaration.start {
+                continue;
+            }
+
+            let caller = if let Some(caller) = caller_at(state, file, &range.start) {
+                caller
+            } else {
+                continue;
+            };
+
+            if let Some((_, ranges)) = callers.iter_mut().find(|(node, _)| *node == caller) {
+                ranges.push(range);
+            } else {
+                callers.push((caller, vec![range]));
+            }
+        }
+    }
+
+    Some(
+        callers
+            .drain(..)
+            .filter_map(|(caller, from_ranges)| {
+                Some(CallHierarchyIncomingCall {
+                    from: hierarchy_item(state, caller)?,
+                    from_ranges,
+                })
+            })
+            .collect(),
+    )
+}
+
+/// All functions and methods called within the body of the item, including constructors
+pub(crate) fn outgoing_calls(
+    state: &BackendState,
+    item: &CallHierarchyItem,
+) -> Option<Vec<CallHierarchyOutgoingCall>> {
+    let callable = callable_of_item(state, item)?;
+    let body = state.arena[callable].get().range;
+    let file = state.arena[enclosing_file(state, callable)?]
+        .get()
+        .name
+        .clone();
+
+    let source = if let Some(source) = state.latest_version_of_file.get(&file) {
+        source.to_string()
+    } else {
+        std::fs::read_to_string(&file).ok()?
+    };
+    let (ast, _, _) = Backend::source_to_ast(&source).ok()?;
+
+    let mut resolved = callees(state, &file)?;
+
+    let mut calls = Vec::new();
+    for node in ast.iter() {
+        collect_calls(node, &body, false, &mut calls);
+    }
+
+    let mut callees: Vec<(NodeId, Vec<Range>)> = Vec::new();
+    for (callee, is_new) in calls {
+        let target = if let Some((target, _)) = resolved.callable(callee, is_new) {
+            target
+        } else {
+            continue;
+        };
+
+        // Point to the name of the function or method, not the object it is called on
+        let range = match callee {
+            AstNode::Member { member, .. } | AstNode::StaticMember { member, .. } => {
+                get_range(member.range())
+            }
+            _ => get_range(callee.range()),
+        };
+
+        if let Some((_, ranges)) = callees.iter_mut().find(|(node, _)| *node == target) {
+            ranges.push(range);
+        } else {
+            callees.push((target, vec![range]));
+        }
+    }
+
+    Some(
+        callees
+            .drain(..)
+            .filter_map(|(callee, from_ranges)| {
+                Some(CallHierarchyOutgoingCall {
+                    to: hierarchy_item(state, callee)?,
+                    from_ranges,
+                })
+            })
+            .collect(),
+    )
+}
+
+/// Collect the callees of all calls within the range. The flag tells if the call is the
+/// constructor call of a `new` expression.
+fn collect_calls<'a>(
+    node: &'a AstNode,
+    range: &Range,
+    is_new: bool,
+    calls: &mut Vec<(&'a AstNode, bool)>,
+) {
+    let node_range = get_range(node.range());
+    if node_range.end < range.start || node_range.start > range.end {
+        return;
+    }
+
+    // Depending on the context, `new` is parsed as unary operator or as a node of its own
+    match node {
+        AstNode::New { class, .. } => return collect_calls(class, range, true, calls),
+        AstNode::Unary { token, expr } if token.t == TokenType::New => {
+            return collect_calls(expr, range, true, calls)
+        }
+        AstNode::Call { callee, .. }
+            if node_range.start >= range.start && node_range.end <= range.end =>
+        {
+            calls.push((callee, is_new))
+        }
+        _ => (),
+    }
+
+    for child in node.children() {
+        collect_calls(child, range, false, calls);
+    }
+}
+
+/// The innermost function or method containing the position, or the file itself
+fn caller_at(state: &BackendState, file: &str, position: &Position) -> Option<NodeId> {
+    let file_node = *state.files.get(file)?;
+    let innermost = state.arena[file_node]
+        .get()
+        .symbol_at(position, file_node, &state.arena);
+
+    innermost
+        .ancestors(&state.arena)
+        .find(|node| {
+            is_callable(state, *node) && in_range(position, &state.arena[*node].get().range)
+        })
+        .or(Some(file_node))
+}
+
+fn enclosing_file(state: &BackendState, node: NodeId) -> Option<NodeId> {
+    node.ancestors(&state.arena)
+        .find(|a| state.arena[*a].get().kind == PhpSymbolKind::File)
+}
+
+fn is_callable(state: &BackendState, node: NodeId) -> bool {
+    matches!(
+        state.arena[node].get().kind,
+        PhpSymbolKind::Function | PhpSymbolKind::Method | PhpSymbolKind::Constructor
+    )
+}
+
+/// Represent a function, method or file in the call hierarchy. Methods are detailed with the
+/// name of their class.
+fn hierarchy_item(state: &BackendState, node: NodeId) -> Option<CallHierarchyItem> {
+    let symbol = state.arena[node].get();
+    let file = state.arena[enclosing_file(state, node)?].get();
+
+    let detail = match symbol.kind {
+        PhpSymbolKind::Method | PhpSymbolKind::Constructor => state.arena[node]
+            .parent()
+            .map(|parent| state.arena[parent].get().fqdn()),
+        _ => symbol.namespace.clone(),
+    };
+
+    Some(CallHierarchyItem {
+        name: symbol.name().to_owned(),
+        kind: symbol
+            .kind
+            .get_symbol_kind()
+            .unwrap_or(SymbolKind::FUNCTION),
+        tags: if symbol.deprecated == Some(true) {
+            Some(vec![SymbolTag::DEPRECATED])
+        } else {
+            None
+        },
+        detail,
+        uri: Url::from_file_path(&file.name).ok()?,
+        range: symbol.range,
+        selection_range: symbol.selection_range,
+        data: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::tests::populate_state;
+
+    fn state(sources: &[(&str, &str)]) -> BackendState {
+        let mut state = BackendState::default();
+        for (file, source) in sources {
+            state
+                .latest_version_of_file
+                .insert(file.to_string(), source.to_string().into());
+        }
+        populate_state(&mut state, sources);
+
+        state
+    }
+
+    fn prepare(state: &BackendState, file: &str, line: u32, character: u32) -> CallHierarchyItem {
+        let params = CallHierarchyPrepareParams {
+            text_document_position_params: lsp_types::TextDocumentPositionParams {
+                text_document: lsp_types::TextDocumentIdentifier {
+                    uri: Url::from_file_path(file).unwrap(),
+                },
+                position: Position { line, character },
+            },
+            work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+        };
+
+        prepare_call_hierarchy(state, params)
+            .unwrap()
+            .unwrap()
+            .remove(0)
+    }
+
+    #[test]
+    fn test_groups_incoming_calls_by_caller() {
+        let state = state(&[
+            (
+                "/a.php",
+                "<?php
+function target() {}
+function caller() { target(); target(); }
+target();
+",
+            ),
+            (
+                "/b.php",
+                "<?php
+class B { public function run() { target(); } }
+",
+            ),
+        ]);
+
+        // Prepared at a call site, it resolves to the declaration
+        let item = prepare(&state, "/a.php", 3, 2);
+        assert_eq!("target", item.name);
+        assert_eq!(1, item.selection_range.start.line);
+
+        let incoming = incoming_calls(&state, &item, &state.symbol_references).unwrap();
+        assert_eq!(
+            vec![
+                (String::from("caller"), 2),
+                (String::from("/a.php"), 1),
+                (String::from("run"), 1)
+            ],
+            incoming
+                .iter()
+                .map(|call| (call.from.name.clone(), call.from_ranges.len()))
+                .collect::<Vec<(String, usize)>>()
+        );
+        assert_eq!(Some(String::from("B")), incoming[2].from.detail);
+    }
+
+    #[test]
+    fn test_lists_outgoing_calls_including_inherited_methods() {
+        let state = state(&[(
+            "/a.php",
+            "<?php
+function helper() {}
+class Base { public function inherited() {} }
+class Child extends Base {
+    public function __construct() {}
+    public function run() {
+        helper();
+        $this->inherited();
+        new Child();
+        unknown();
+        helper();
+    }
+}
+",
+        )]);
+
+        let item = prepare(&state, "/a.php", 5, 21);
+        assert_eq!("run", item.name);
+
+        let outgoing = outgoing_calls(&state, &item).unwrap();
+        assert_eq!(
+            vec![
+                (String::from("helper"), 2),
+                (String::from("inherited"), 1),
+                (String::from("__construct"), 1)
+            ],
+            outgoing
+                .iter()
+                .map(|call| (call.to.name.clone(), call.from_ranges.len()))
+                .collect::<Vec<(String, usize)>>()
+        );
+        assert_eq!(Some(String::from("Base")), outgoing[1].to.detail);
+    }
+}
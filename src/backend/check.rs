@@ -0,0 +1,424 @@
+use super::{document_link, Backend, BackendState};
+use crate::environment::composer::Composer;
+use crate::environment::fs as EnvFs;
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use tower_lsp::lsp_types::{Diagnostic, DiagnosticSeverity};
+
+/// Diagnostics of all checked files, ordered by path
+pub type CheckResult = BTreeMap<String, Vec<Diagnostic>>;
+
+/// Output formats of the check command
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Checkstyle,
+    Sarif,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "text" => Some(Format::Text),
+            "json" => Some(Format::Json),
+            "checkstyle" => Some(Format::Checkstyle),
+            "sarif" => Some(Format::Sarif),
+            _ => None,
+        }
+    }
+}
+
+/// Parse a severity name as accepted by `--fail-on`
+pub fn severity_from_name(name: &str) -> Option<DiagnosticSeverity> {
+    match name {
+        "error" => Some(DiagnosticSeverity::ERROR),
+        "warning" => Some(DiagnosticSeverity::WARNING),
+        "information" => Some(DiagnosticSeverity::INFORMATION),
+        "hint" => Some(DiagnosticSeverity::HINT),
+        _ => None,
+    }
+}
+
+/// Run the same pipeline as the language server on all files in `root` and return the
+/// diagnostics of every file. The stubs are indexed so that built in symbols resolve, but
+/// they are not checked themselves. The autoload rules are taken from the nearest composer.json
+/// in `root` or above.
+pub fn check(
+    root: &Path,
+    stubs: &Path,
+    ignore_patterns: &[PathBuf],
+    autoload_dev: bool,
+) -> CheckResult {
+    let root = EnvFs::normalize_lexically(&std::env::current_dir().unwrap_or_default().join(root));
+    let mut state = BackendState {
+        composer: project_root(&root).and_then(|project| Composer::load(project, autoload_dev)),
+        ..BackendState::default()
+    };
+
+    let files = EnvFs::reindex_folder(&root, ignore_patterns).unwrap_or_default();
+    let stub_files = EnvFs::reindex_folder(stubs, &[]).unwrap_or_default();
+
+    for path in stub_files.iter() {
+        if let Some((p, ast, range, _)) = parse(path) {
+            if let Err(e) = Backend::collect_symbols(&p, &ast, &range, &mut state) {
+                eprintln!("{}", e);
+            }
+        }
+    }
+
+    let mut asts = Vec::with_capacity(files.len());
+    for path in files.iter() {
+        if let Some((p, ast, range, errors)) = parse(path) {
+            if let Err(e) = Backend::collect_symbols(&p, &ast, &range, &mut state) {
+                eprintln!("{}", e);
+            }
+
+            let mut diagnostics: Vec<Diagnostic> = errors.iter().map(Diagnostic::from).collect();
+            diagnostics.extend(document_link::include_diagnostics(&ast, &p));
+            diagnostics.extend(Backend::autoload_diagnostics(&state, &p));
+            state.diagnostics.insert(p.clone(), diagnostics.into());
+
+            asts.push((p, ast));
+        }
+    }
+
+    for (p, ast) in asts.iter() {
+        if let Err(e) = Backend::collect_references(p, ast, &mut state, None) {
+            eprintln!("{}", e);
+        }
+    }
+
+    asts.drain(..)
+        .map(|(p, _)| {
+            let mut diagnostics = state
+                .diagnostics
+                .remove(&p)
+                .map(|d| d.to_vec())
+                .unwrap_or_default();
+            diagnostics.sort_by_key(|d| (d.range.start.line, d.range.start.character));
+
+            (p, diagnostics)
+        })
+        .collect()
+}
+
+/// The closest directory containing a composer.json, starting at `dir` itself
+fn project_root(dir: &Path) -> Option<&Path> {
+    dir.ancestors()
+        .find(|ancestor| ancestor.join("composer.json").is_file())
+}
+
+fn parse(path: &Path) -> Option<super::ParseResult> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) => {
+            eprintln!("Error reading file {:?}: {}", path, e);
+
+            return None;
+        }
+    };
+
+    match Backend::source_to_ast(&content) {
+        Ok((ast, range, errors)) => Some((EnvFs::normalize_path(path), ast, range, errors)),
+        Err(e) => {
+            eprintln!("Error parsing file {:?}: {}", path, e);
+
+            None
+        }
+    }
+}
+
+/// Return true if any diagnostic is at least as severe as `threshold`
+pub fn fails(result: &CheckResult, threshold: DiagnosticSeverity) -> bool {
+    result
+        .values()
+        .flatten()
+        .any(|diagnostic| severity(diagnostic) <= threshold)
+}
+
+/// Render the diagnostics in the given format
+pub fn report(result: &CheckResult, format: Format) -> String {
+    match format {
+        Format::Text => text(result),
+        Format::Json => serde_json::to_string_pretty(&json(result)).unwrap(),
+        Format::Checkstyle => checkstyle(result),
+        Format::Sarif => serde_json::to_string_pretty(&sarif(result)).unwrap(),
+    }
+}
+
+/// Diagnostics of the parser do not carry a severity, they are errors
+fn severity(diagnostic: &Diagnostic) -> DiagnosticSeverity {
+    diagnostic.severity.unwrap_or(DiagnosticSeverity::ERROR)
+}
+
+fn severity_name(diagnostic: &Diagnostic) -> &'static str {
+    match severity(diagnostic) {
+        DiagnosticSeverity::WARNING => "warning",
+        DiagnosticSeverity::INFORMATION => "information",
+        DiagnosticSeverity::HINT => "hint",
+        _ => "error",
+    }
+}
+
+/// Iterate over all diagnostics with their file, line and column. Lines and columns start at 1.
+fn entries(result: &CheckResult) -> impl Iterator<Item = (&String, u32, u32, &Diagnostic)> {
+    result.iter().flat_map(|(file, diagnostics)| {
+        diagnostics.iter().map(move |diagnostic| {
+            (
+                file,
+                diagnostic.range.start.line + 1,
+                diagnostic.range.start.character + 1,
+                diagnostic,
+            )
+        })
+    })
+}
+
+fn text(result: &CheckResult) -> String {
+    entries(result)
+        .map(|(file, line, col, diagnostic)| {
+            format!(
+                "{}:{}:{}: {}: {}\n",
+                file,
+                line,
+                col,
+                severity_name(diagnostic),
+                diagnostic.message
+            )
+        })
+        .collect()
+}
+
+fn json(result: &CheckResult) -> serde_json::Value {
+    entries(result)
+        .map(|(file, line, col, diagnostic)| {
+            json!({
+                "file": file,
+                "line": line,
+                "column": col,
+                "severity": severity_name(diagnostic),
+                "message": diagnostic.message,
+            })
+        })
+        .collect()
+}
+
+fn checkstyle(result: &CheckResult) -> String {
+    let mut xml =
+        String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<checkstyle version=\"4.3\">\n");
+
+    for (file, diagnostics) in result.iter() {
+        xml.push_str(&format!("  <file name=\"{}\">\n", escape_xml(file)));
+
+        for diagnostic in diagnostics {
+            // Checkstyle only knows error, warning and info
+            let severity = match severity(diagnostic) {
+                DiagnosticSeverity::ERROR => "error",
+                DiagnosticSeverity::WARNING => "warning",
+                _ => "info",
+            };
+
+            xml.push_str(&format!(
+                "    <error line=\"{}\" column=\"{}\" severity=\"{}\" message=\"{}\" source=\"phpls-rs\"/>\n",
+                diagnostic.range.start.line + 1,
+                diagnostic.range.start.character + 1,
+                severity,
+                escape_xml(&diagnostic.message)
+            ));
+        }
+
+        xml.push_str("  </file>\n");
+    }
+
+    xml.push_str("</checkstyle>\n");
+    xml
+}
+
+fn sarif(result: &CheckResult) -> serde_json::Value {
+    let results = entries(result)
+        .map(|(file, line, col, diagnostic)| {
+            // SARIF only knows error, warning and note
+            let level = match severity(diagnostic) {
+                DiagnosticSeverity::ERROR => "error",
+                DiagnosticSeverity::WARNING => "warning",
+                _ => "note",
+            };
+
+            json!({
+                "level": level,
+                "message": { "text": diagnostic.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": file },
+                        "region": {
+                            "startLine": line,
+                            "startColumn": col,
+                            "endLine": diagnostic.range.end.line + 1,
+                            "endColumn": diagnostic.range.end.character + 1,
+                        }
+                    }
+                }]
+            })
+        })
+        .collect::<Vec<serde_json::Value>>();
+
+    json!({
+        "$schema": "https://json.schemastore.org/sarif-2.1.0.json",
+        "version": "2.1.0",
+        "runs": [{
+            "tool": {
+                "driver": {
+                    "name": "phpls-rs",
+                    "version": env!("CARGO_PKG_VERSION"),
+                }
+            },
+            "results": results,
+        }]
+    })
+}
+
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tower_lsp::lsp_types::{Position, Range};
+
+    fn result() -> CheckResult {
+        let mut result = CheckResult::new();
+        result.insert(
+            String::from("/project/a.php"),
+            vec![
+                Diagnostic {
+                    range: Range {
+                        start: Position {
+                            line: 2,
+                            character: 4,
+                        },
+                        end: Position {
+                            line: 2,
+                            character: 7,
+                        },
+                    },
+                    message: String::from("Unresolvable type 'Foo<Bar>'"),
+                    ..Diagnostic::default()
+                },
+                Diagnostic {
+                    range: Range::default(),
+                    message: String::from("Case mismatch"),
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    ..Diagnostic::default()
+                },
+            ],
+        );
+
+        result
+    }
+
+    #[test]
+    fn test_reports_diagnostics_in_all_formats() {
+        assert_eq!(
+            "/project/a.php:3:5: error: Unresolvable type 'Foo<Bar>'\n/project/a.php:1:1: warning: Case mismatch\n",
+            report(&result(), Format::Text)
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&report(&result(), Format::Json)).unwrap();
+        assert_eq!("warning", json[1]["severity"]);
+        assert_eq!(3, json[0]["line"]);
+
+        let checkstyle = report(&result(), Format::Checkstyle);
+        assert!(checkstyle.contains(
+            "<error line=\"3\" column=\"5\" severity=\"error\" message=\"Unresolvable type &apos;Foo&lt;Bar&gt;&apos;\" source=\"phpls-rs\"/>"
+        ));
+
+        let sarif: serde_json::Value =
+            serde_json::from_str(&report(&result(), Format::Sarif)).unwrap();
+        let results = &sarif["runs"][0]["results"];
+        assert_eq!("error", results[0]["level"]);
+        assert_eq!(
+            3,
+            results[0]["locations"][0]["physicalLocation"]["region"]["startLine"]
+        );
+    }
+
+    #[test]
+    fn test_fails_above_threshold() {
+        assert!(fails(&result(), DiagnosticSeverity::ERROR));
+        assert!(fails(&result(), DiagnosticSeverity::HINT));
+
+        let mut result = result();
+        result.values_mut().for_each(|d| {
+            d.remove(0);
+        });
+        assert!(!fails(&result, DiagnosticSeverity::ERROR));
+        assert!(fails(&result, DiagnosticSeverity::WARNING));
+    }
+
+    #[test]
+    fn test_checks_project_against_stubs() {
+        let dir = std::env::temp_dir().join(format!("phpls-rs-check-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        fs::create_dir_all(dir.join("stubs")).unwrap();
+
+        fs::write(dir.join("stubs/core.php"), "<?php class Exception {}").unwrap();
+        fs::write(
+            dir.join("src/index.php"),
+            "<?php\nthrow new Exception();\nnew Missing();\n",
+        )
+        .unwrap();
+
+        let result = check(&dir.join("src"), &dir.join("stubs"), &[], false);
+
+        assert_eq!(
+            format!(
+                "{}:3:5: error: Unresolvable type (false) 'Missing'\n",
+                dir.join("src/index.php").to_string_lossy()
+            ),
+            report(&result, Format::Text)
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_loads_composer_json_above_checked_directory() {
+        let dir = std::env::temp_dir().join(format!("phpls-rs-project-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src/Models")).unwrap();
+
+        fs::write(
+            dir.join("composer.json"),
+            r#"{ "autoload": { "psr-4": { "App\\": "src/" } } }"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("src/Models/User.php"),
+            "<?php namespace App; class User {}",
+        )
+        .unwrap();
+
+        let result = check(&dir.join("src/Models"), &dir.join("stubs"), &[], false);
+
+        assert_eq!(
+            vec!["App\\User does not match its PSR-4 path, expected App\\Models\\User"],
+            result[&EnvFs::normalize_path(&dir.join("src/Models/User.php"))]
+                .iter()
+                .map(|diagnostic| diagnostic.message.as_str())
+                .collect::<Vec<&str>>()
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,736 @@
+use super::BackendState;
+use crate::environment::import::collect_uses;
+use crate::environment::inheritance::{InheritanceChecker, MISSING_IMPLEMENTATION};
+use crate::environment::symbol::PhpSymbolKind;
+use crate::environment::{fs as EnvFs, get_range, in_range};
+use crate::parser::node::{ClassStatement, Node as AstNode, TypeRef};
+use crate::parser::token::Token;
+use indextree::NodeId;
+use lsp_types::{
+    CodeAction, CodeActionKind, CodeActionOrCommand, CodeActionParams, CodeActionResponse,
+    Diagnostic, NumberOrString, Position, Range, TextEdit, Url, WorkspaceEdit,
+};
+use std::collections::HashMap;
+use tower_lsp::jsonrpc::Result;
+
+/// Gateway function that accepts the LSP parameters and calls the exec method
+pub(crate) fn code_action(
+    state: &BackendState,
+    params: CodeActionParams,
+) -> Result<Option<CodeActionResponse>> {
+    let file = EnvFs::normalize_path(&params.text_document.uri.to_file_path().unwrap());
+
+    if let Some((ast, _)) = state.opened_files.get(&file) {
+        return Ok(Some(exec(
+            state,
+            ast,
+            &params.range.start,
+            &file,
+            &params.text_document.uri,
+            &params.context.diagnostics,
+        )));
+    }
+
+    Ok(None)
+}
+
+#[inline]
+fn exec(
+    state: &BackendState,
+    ast: &[AstNode],
+    position: &Position,
+    file: &str,
+    uri: &Url,
+    diagnostics: &[Diagnostic],
+) -> CodeActionResponse {
+    let file_node = if let Some(file_node) = state.files.get(file) {
+        *file_node
+    } else {
+        return Vec::new();
+    };
+
+    let mut actions = import_actions(state, ast, position, file_node, uri, diagnostics);
+    actions.extend(implement_actions(
+        state,
+        ast,
+        position,
+        file_node,
+        uri,
+        diagnostics,
+    ));
+
+    actions
+}
+
+/// Offer to import the symbols a simple, unresolvable name under the cursor may refer to
+fn import_actions(
+    state: &BackendState,
+    ast: &[AstNode],
+    position: &Position,
+    file_node: NodeId,
+    uri: &Url,
+    diagnostics: &[Diagnostic],
+) -> CodeActionResponse {
+    let type_ref = if let Some(type_ref) = ast.iter().find_map(|node| type_ref_at(node, position)) {
+        type_ref
+    } else {
+        return Vec::new();
+    };
+
+    // Only a simple name can be fixed by importing the symbol it refers to
+    if type_ref.len() != 1 || type_ref.is_builtin() {
+        return Vec::new();
+    }
+
+    let name = if let Some(name) = type_ref.tip() {
+        name.to_lowercase()
+    } else {
+        return Vec::new();
+    };
+
+    let file_symbol = state.arena[file_node].get();
+
+    // Do not offer anything if the name already refers to an imported symbol or to a
+    // symbol within the current namespace
+    if let Some(imports) = file_symbol.imports.as_ref() {
+        if imports
+            .all()
+            .any(|import| import.name().to_lowercase() == name)
+        {
+            return Vec::new();
+        }
+    }
+
+    let namespace = ast.iter().find_map(|node| match node {
+        AstNode::NamespaceStatement { type_ref, .. }
+        | AstNode::NamespaceBlock {
+            type_ref: Some(type_ref),
+            ..
+        } => match type_ref.as_ref() {
+            AstNode::TypeRef(type_ref) => Some(type_ref.to_fqdn()),
+            _ => None,
+        },
+        _ => None,
+    });
+
+    let local_name = if let Some(namespace) = namespace {
+        format!("{}\\{}", namespace, name).to_lowercase()
+    } else {
+        name.clone()
+    };
+
+    if state.global_symbols.contains_key(&local_name) {
+        return Vec::new();
+    }
+
+    let diagnostics = diagnostics
+        .iter()
+        .filter(|diagnostic| {
+            diagnostic.message.starts_with("Unresolvable type")
+                && in_range(position, &diagnostic.range)
+        })
+        .cloned()
+        .collect::<Vec<Diagnostic>>();
+
+    let mut candidates = state
+        .global_symbols
+        .values()
+        .filter_map(|node| {
+            let symbol = state.arena[*node].get();
+
+            if symbol.normalized_name() == name
+                && matches!(
+                    symbol.kind,
+                    PhpSymbolKind::Class
+                        | PhpSymbolKind::Interface
+                        | PhpSymbolKind::Trait
+                        | PhpSymbolKind::Enum
+                )
+            {
+                Some(symbol.fqdn())
+            } else {
+                None
+            }
+        })
+        .collect::<Vec<String>>();
+    candidates.sort();
+    candidates.dedup();
+
+    let source = state
+        .latest_version_of_file
+        .get(&file_symbol.name)
+        .map(|source| source.as_str())
+        .unwrap_or_default();
+
+    candidates
+        .drain(..)
+        .map(|fqdn| {
+            let mut changes = HashMap::new();
+            changes.insert(uri.clone(), vec![use_statement_edit(ast, source, &fqdn)]);
+
+            CodeActionOrCommand::CodeAction(CodeAction {
+                title: format!("Import `{}`", fqdn),
+                kind: Some(CodeActionKind::QUICKFIX),
+                diagnostics: Some(diagnostics.clone()),
+                edit: Some(WorkspaceEdit {
+                    changes: Some(changes),
+                    ..WorkspaceEdit::default()
+                }),
+                ..CodeAction::default()
+            })
+        })
+        .collect()
+}
+
+/// Offer to generate stubs for the methods a class under the cursor must implement
+fn implement_actions(
+    state: &BackendState,
+    ast: &[AstNode],
+    position: &Position,
+    file_node: NodeId,
+    uri: &Url,
+    diagnostics: &[Diagnostic],
+) -> CodeActionResponse {
+    let (name, body) = if let Some(class) = ast.iter().find_map(|node| class_at(node, position)) {
+        class
+    } else {
+        return Vec::new();
+    };
+
+    let (oc, statements, cc) = if let AstNode::Block { oc, statements, cc } = body {
+        (oc, statements, cc)
+    } else {
+        return Vec::new();
+    };
+
+    let selection_range = get_range(name.range());
+    let class = file_node.descendants(&state.arena).find(|node| {
+        let symbol = state.arena[*node].get();
+
+        matches!(symbol.kind, PhpSymbolKind::Class | PhpSymbolKind::Enum)
+            && symbol.selection_range == selection_range
+    });
+    let class = if let Some(class) = class {
+        class
+    } else {
+        return Vec::new();
+    };
+
+    let mut checker = InheritanceChecker::new(
+        &state.arena,
+        &state.global_symbols,
+        file_node,
+        &state.diagnostic_options,
+        state.stubs.as_deref(),
+    );
+
+    let missing = checker.missing_methods(class);
+    if missing.is_empty() {
+        return Vec::new();
+    }
+
+    let indent = " ".repeat(state.arena[class].get().range.start.character as usize + 4);
+    let stubs = missing
+        .iter()
+        .map(|method| format!("\n{}", checker.stub(*method, &indent)))
+        .collect::<String>();
+
+    // Put the stubs right before the closing curly brace, which keeps its own line if it has one
+    let last_line = statements
+        .last()
+        .map(|statement| statement.range().end_line)
+        .unwrap_or(oc.line);
+    let edit = if last_line < cc.line {
+        insert_at(cc.line, stubs)
+    } else {
+        let position = Position {
+            line: cc.line,
+            character: cc.col,
+        };
+
+        TextEdit {
+            range: Range {
+                start: position,
+                end: position,
+            },
+            new_text: stubs,
+        }
+    };
+
+    let diagnostics = diagnostics
+        .iter()
+        .filter(|diagnostic| {
+            diagnostic.code == Some(NumberOrString::String(MISSING_IMPLEMENTATION.to_owned()))
+                && in_range(position, &diagnostic.range)
+        })
+        .cloned()
+        .collect::<Vec<Diagnostic>>();
+
+    let mut changes = HashMap::new();
+    changes.insert(uri.clone(), vec![edit]);
+
+    vec![CodeActionOrCommand::CodeAction(CodeAction {
+        title: "Implement missing methods".to_owned(),
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: Some(diagnostics),
+        edit: Some(WorkspaceEdit {
+            changes: Some(changes),
+            ..WorkspaceEdit::default()
+        }),
+        ..CodeAction::default()
+    })]
+}
+
+/// Find the name and the body of the class or enum whose name is under the cursor
+fn class_at<'a>(node: &'a AstNode, position: &Position) -> Option<(&'a Token, &'a AstNode)> {
+    let (name, body) = match node {
+        AstNode::ClassStatement(ClassStatement { name, body, .. })
+        | AstNode::EnumStatement { name, body, .. } => (name, body.as_ref()),
+        _ => {
+            return node
+                .children()
+                .iter()
+                .find_map(|child| class_at(child, position))
+        }
+    };
+
+    if in_range(position, &get_range(name.range())) {
+        Some((name, body))
+    } else {
+        None
+    }
+}
+
+/// Create the edit that adds a `use` statement for `fqdn` to a file. The statement is
+/// inserted in sorted position among the existing use statements or, if there are none, right
+/// after the namespace declaration. Grouped imports are never split up.
+pub(crate) fn use_statement_edit(ast: &[AstNode], source: &str, fqdn: &str) -> TextEdit {
+    let statement = format!("use {};\n", fqdn);
+    let normalized_fqdn = fqdn.to_lowercase();
+    let mut line = None;
+
+    for node in use_statements(ast) {
+        let range = node.range();
+        let first_import = collect_uses(node, &TypeRef::default())
+            .first()
+            .map(|import| import.full_name().to_lowercase())
+            .unwrap_or_default();
+
+        if first_import > normalized_fqdn {
+            line = Some(range.start_line);
+
+            break;
+        }
+
+        line = Some(range.end_line + 1);
+    }
+
+    if let Some(line) = line {
+        return insert_at(line, statement);
+    }
+
+    let namespace_line = ast.iter().find_map(|node| match node {
+        AstNode::NamespaceStatement { .. } => Some(node.range().end_line + 1),
+        AstNode::NamespaceBlock { token, .. } => Some(token.line + 1),
+        _ => None,
+    });
+
+    // Without a namespace right after the declare statements or the opening tag, a use
+    // statement must not come before declare(strict_types=1)
+    let declare_line = ast
+        .iter()
+        .take_while(|node| matches!(node, AstNode::DeclareStatement { .. }))
+        .last()
+        .map(|node| node.range().end_line + 1);
+
+    insert_at(
+        namespace_line
+            .or(declare_line)
+            .unwrap_or_else(|| header_end_line(source)),
+        format!("\n{}", statement),
+    )
+}
+
+/// The line after the opening tag and the comments following it, like a license header. A
+/// comment right above a statement documents the statement, so it is not part of the header.
+fn header_end_line(source: &str) -> u32 {
+    let mut rest = match source.find("<?php") {
+        Some(start) => &source[start + "<?php".len()..],
+        None => return 1,
+    };
+
+    loop {
+        let comment = rest.trim_start();
+        let length = if comment.starts_with("/*") {
+            comment.find("*/").map(|end| end + 2)
+        } else if comment.starts_with("//")
+            || comment.starts_with('#') && !comment.starts_with("#[")
+        {
+            Some(comment.find('\n').unwrap_or(comment.len()))
+        } else {
+            None
+        };
+
+        let after = match length {
+            Some(length) => &comment[length..],
+            None => break,
+        };
+
+        let gap = &after[..after.len() - after.trim_start().len()];
+        if gap.matches('\n').count() < 2 {
+            break;
+        }
+
+        rest = after;
+    }
+
+    source[..source.len() - rest.len()].matches('\n').count() as u32 + 1
+}
+
+/// The statements importing classes, functions or constants, including those of a namespace
+/// block
+fn use_statements(ast: &[AstNode]) -> Vec<&AstNode> {
+    ast.iter()
+        .flat_map(|node| match node {
+            AstNode::NamespaceBlock { block, .. } => block.children(),
+            _ => vec![node],
+        })
+        .filter(|node| {
+            matches!(
+                node,
+                AstNode::UseStatement { .. }
+                    | AstNode::UseFunctionStatement { .. }
+                    | AstNode::UseConstStatement { .. }
+            )
+        })
+        .collect()
+}
+
+fn insert_at(line: u32, new_text: String) -> TextEdit {
+    let position = Position { line, character: 0 };
+
+    TextEdit {
+        range: Range {
+            start: position,
+            end: position,
+        },
+        new_text,
+    }
+}
+
+/// Find the type reference under the cursor
+fn type_ref_at<'a>(node: &'a AstNode, position: &Position) -> Option<&'a TypeRef> {
+    if let AstNode::TypeRef(type_ref) = node {
+        if in_range(position, &get_range(type_ref.range())) {
+            return Some(type_ref);
+        }
+
+        return None;
+    }
+
+    node.children()
+        .iter()
+        .find_map(|child| type_ref_at(child, position))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::tests::populate_state;
+    use crate::parser::{scanner::Scanner, Parser};
+
+    fn actions_at(sources: &[(&str, &str)], line: u32, character: u32) -> Vec<(String, TextEdit)> {
+        let mut state = BackendState::default();
+        state
+            .latest_version_of_file
+            .insert(sources[0].0.to_owned(), sources[0].1.to_owned().into());
+        populate_state(&mut state, sources);
+
+        let mut scanner = Scanner::new(sources[0].1);
+        scanner.scan().unwrap();
+        let (ast, _) = Parser::ast(scanner.tokens).unwrap();
+
+        let uri = Url::from_file_path("/index.php").unwrap();
+
+        exec(
+            &state,
+            &ast,
+            &Position { line, character },
+            sources[0].0,
+            &uri,
+            &[],
+        )
+        .drain(..)
+        .map(|action| match action {
+            CodeActionOrCommand::CodeAction(action) => (
+                action.title,
+                action.edit.unwrap().changes.unwrap()[&uri][0].clone(),
+            ),
+            _ => unreachable!(),
+        })
+        .collect()
+    }
+
+    #[test]
+    fn offers_imports_in_sorted_position() {
+        let actions = actions_at(
+            &[
+                (
+                    "index.php",
+                    "<?php
+namespace App;
+
+use App\\Alpha\\First;
+use App\\Omega\\Last;
+
+new Bar();",
+                ),
+                ("bar.php", "<?php namespace App\\Models; class Bar {}"),
+                ("other.php", "<?php namespace Vendor; class Bar {}"),
+            ],
+            6,
+            5,
+        );
+
+        assert_eq!(
+            vec![
+                (
+                    "Import `App\\Models\\Bar`".to_owned(),
+                    insert_at(4, "use App\\Models\\Bar;\n".to_owned())
+                ),
+                (
+                    "Import `Vendor\\Bar`".to_owned(),
+                    insert_at(5, "use Vendor\\Bar;\n".to_owned())
+                )
+            ],
+            actions
+        );
+    }
+
+    #[test]
+    fn inserts_import_after_namespace() {
+        let actions = actions_at(
+            &[
+                (
+                    "index.php",
+                    "<?php
+namespace App;
+
+class Foo extends Bar {}",
+                ),
+                ("bar.php", "<?php namespace Vendor; class Bar {}"),
+            ],
+            3,
+            19,
+        );
+
+        assert_eq!(
+            vec![(
+                "Import `Vendor\\Bar`".to_owned(),
+                insert_at(2, "\nuse Vendor\\Bar;\n".to_owned())
+            )],
+            actions
+        );
+    }
+
+    #[test]
+    fn keeps_grouped_imports_intact() {
+        let actions = actions_at(
+            &[
+                (
+                    "index.php",
+                    "<?php
+namespace App;
+
+use App\\Alpha\\{First, Second};
+use App\\Omega\\{
+    Last,
+    Other
+};
+
+new Bar();",
+                ),
+                ("bar.php", "<?php namespace App\\Models; class Bar {}"),
+                ("other.php", "<?php namespace Vendor; class Bar {}"),
+            ],
+            9,
+            5,
+        );
+
+        assert_eq!(
+            vec![
+                (
+                    "Import `App\\Models\\Bar`".to_owned(),
+                    insert_at(4, "use App\\Models\\Bar;\n".to_owned())
+                ),
+                (
+                    "Import `Vendor\\Bar`".to_owned(),
+                    insert_at(8, "use Vendor\\Bar;\n".to_owned())
+                )
+            ],
+            actions
+        );
+    }
+
+    #[test]
+    fn inserts_import_after_license_header() {
+        let actions = actions_at(
+            &[
+                (
+                    "index.php",
+                    "<?php
+/**
+ * This file is part of the project.
+ */
+
+new Bar();",
+                ),
+                ("bar.php", "<?php namespace Vendor; class Bar {}"),
+            ],
+            5,
+            5,
+        );
+
+        assert_eq!(
+            vec![(
+                "Import `Vendor\\Bar`".to_owned(),
+                insert_at(4, "\nuse Vendor\\Bar;\n".to_owned())
+            )],
+            actions
+        );
+    }
+
+    #[test]
+    fn keeps_doc_comments_with_their_statements() {
+        assert_eq!(1, header_end_line("<?php\n$a = 1;"));
+        assert_eq!(
+            2,
+            header_end_line("<?php\n// Copyright\n\n/** Doc */\nfunction a() {}")
+        );
+        assert_eq!(1, header_end_line("<?php\n/** Doc */\nfunction a() {}"));
+    }
+
+    #[test]
+    fn inserts_import_after_declare_statement() {
+        let actions = actions_at(
+            &[
+                (
+                    "index.php",
+                    "<?php
+declare(strict_types=1);
+
+new Bar();",
+                ),
+                ("bar.php", "<?php namespace Vendor; class Bar {}"),
+            ],
+            3,
+            5,
+        );
+
+        assert_eq!(
+            vec![(
+                "Import `Vendor\\Bar`".to_owned(),
+                insert_at(2, "\nuse Vendor\\Bar;\n".to_owned())
+            )],
+            actions
+        );
+    }
+
+    #[test]
+    fn offers_nothing_for_resolvable_names() {
+        let actions = actions_at(
+            &[
+                ("index.php", "<?php namespace Vendor; new Bar();"),
+                ("bar.php", "<?php namespace Vendor; class Bar {}"),
+            ],
+            0,
+            29,
+        );
+
+        assert!(actions.is_empty());
+    }
+
+    #[test]
+    fn implements_missing_methods() {
+        let actions = actions_at(
+            &[
+                (
+                    "index.php",
+                    "<?php
+namespace App;
+
+use Vendor\\Shape;
+
+class Square implements Shape {
+}",
+                ),
+                (
+                    "shape.php",
+                    "<?php namespace Vendor; interface Shape {
+    public function scale(Shape $other, int &$factor = 2, ...$rest): ?Shape;
+    public static function create();
+}",
+                ),
+            ],
+            5,
+            8,
+        );
+
+        assert_eq!(
+            vec![(
+                "Implement missing methods".to_owned(),
+                insert_at(
+                    6,
+                    "
+    public static function create()
+    {
+        // TODO: Implement create()
+    }
+
+    public function scale(\\Vendor\\Shape $other, int &$factor = 2, ...$rest): ?\\Vendor\\Shape
+    {
+        // TODO: Implement scale()
+    }
+"
+                    .to_owned()
+                )
+            )],
+            actions
+        );
+    }
+
+    #[test]
+    fn attaches_missing_implementation_diagnostics() {
+        let sources = [(
+            "/index.php",
+            "<?php
+interface Shape { public function area(); }
+class Square implements Shape {
+}",
+        )];
+        let mut state = BackendState::default();
+        populate_state(&mut state, &sources);
+
+        let mut scanner = Scanner::new(sources[0].1);
+        scanner.scan().unwrap();
+        let (ast, _) = Parser::ast(scanner.tokens).unwrap();
+
+        let diagnostics = state.diagnostics["/index.php"].to_vec();
+        let position = diagnostics[0].range.start;
+        let actions = exec(
+            &state,
+            &ast,
+            &position,
+            sources[0].0,
+            &Url::from_file_path("/index.php").unwrap(),
+            &diagnostics,
+        );
+
+        match &actions[..] {
+            [CodeActionOrCommand::CodeAction(action)] => {
+                assert_eq!(Some(diagnostics), action.diagnostics)
+            }
+            _ => panic!("Expected a single code action"),
+        }
+    }
+}
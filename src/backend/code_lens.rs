@@ -0,0 +1,260 @@
+use super::goto_implementation::implementations;
+use super::{Backend, BackendState, ReferenceMap};
+use crate::environment::{fs as EnvFs, get_range, symbol::PhpSymbolKind};
+use indextree::NodeId;
+use lsp_types::{CodeLens, CodeLensParams, Command, Location, Url};
+use serde_json::{json, Value};
+use tower_lsp::jsonrpc::Result;
+
+/// What a code lens counts, stored in its data until it is resolved
+const REFERENCES: &str = "references";
+const IMPLEMENTATIONS: &str = "implementations";
+
+/// Gateway function that accepts the LSP parameters and returns unresolved code lenses above
+/// all classes, interfaces, traits, enums, methods and functions of the file
+pub(crate) fn code_lens(
+    state: &BackendState,
+    params: CodeLensParams,
+) -> Result<Option<Vec<CodeLens>>> {
+    let uri = params.text_document.uri;
+    let file = EnvFs::normalize_path(&uri.to_file_path().unwrap());
+
+    let file_node = if let Some(file_node) = state.files.get(&file) {
+        *file_node
+    } else {
+        return Ok(None);
+    };
+
+    let mut lenses = Vec::new();
+    for child in file_node.children(&state.arena) {
+        let symbol = state.arena[child].get();
+
+        match symbol.kind {
+            PhpSymbolKind::Function => lenses.push(lens(state, child, &uri, REFERENCES)),
+            PhpSymbolKind::Class
+            | PhpSymbolKind::Interface
+            | PhpSymbolKind::Trait
+            | PhpSymbolKind::Enum => {
+                lenses.push(lens(state, child, &uri, REFERENCES));
+                if symbol.kind == PhpSymbolKind::Interface {
+                    lenses.push(lens(state, child, &uri, IMPLEMENTATIONS));
+                }
+
+                for member in child.children(&state.arena) {
+                    let method = state.arena[member].get();
+
+                    if !matches!(
+                        method.kind,
+                        PhpSymbolKind::Method | PhpSymbolKind::Constructor
+                    ) || method.is_implicit
+                    {
+                        continue;
+                    }
+
+                    lenses.push(lens(state, member, &uri, REFERENCES));
+                    if method.is_abstract || symbol.kind == PhpSymbolKind::Interface {
+                        lenses.push(lens(state, member, &uri, IMPLEMENTATIONS));
+                    }
+                }
+            }
+            _ => (),
+        }
+    }
+
+    Ok(Some(lenses))
+}
+
+/// Find the symbol a code lens was created for, along with its name
+pub(crate) fn symbol_of_lens(state: &BackendState, lens: &CodeLens) -> Option<(NodeId, String)> {
+    let uri = lens.data.as_ref()?.get("uri")?.as_str()?;
+    let file = EnvFs::normalize_path(&Url::parse(uri).ok()?.to_file_path().ok()?);
+
+    Backend::symbol_under_cursor(state, &lens.range.start, &file)
+}
+
+/// Tells if the lens counts the implementations rather than the references
+pub(crate) fn counts_implementations(lens: &CodeLens) -> bool {
+    lens.data
+        .as_ref()
+        .and_then(|data| data.get("kind"))
+        .and_then(Value::as_str)
+        == Some(IMPLEMENTATIONS)
+}
+
+/// Count the references to the symbol of the lens, not counting its declaration
+pub(crate) fn resolve_references(
+    state: &BackendState,
+    mut lens: CodeLens,
+    node: NodeId,
+    references: &ReferenceMap,
+) -> CodeLens {
+    let declaration = state.arena[node].get().selection_range;
+    let declared_in = node
+        .ancestors(&state.arena)
+        .find(|a| state.arena[*a].get().kind == PhpSymbolKind::File)
+        .map(|file| state.arena[file].get().name.clone());
+
+    let mut files = references.keys().collect::<Vec<&String>>();
+    files.sort();
+
+    let mut locations = Vec::new();
+    for file in files {
+        let mut ranges = if let Some(ranges) = references[file].get(&node) {
+            ranges.iter().map(|range| get_range(*range)).collect()
+        } else {
+            Vec::new()
+        };
+        ranges.sort_by_key(|range| range.start);
+
+        for range in ranges {
+            if Some(file) == declared_in.as_ref() && range.start == declaration.start {
+                continue;
+            }
+
+            if let Ok(uri) = Url::from_file_path(file) {
+                locations.push(Location { uri, range });
+            }
+        }
+    }
+
+    let title = match locations.len() {
+        1 => String::from("1 reference"),
+        n => format!("{} references", n),
+    };
+    lens.command = Some(command(&lens, title, locations));
+
+    lens
+}
+
+/// Count the implementations of the interface or abstract method of the lens
+pub(crate) fn resolve_implementations(
+    state: &BackendState,
+    mut lens: CodeLens,
+    node: NodeId,
+) -> CodeLens {
+    let locations = implementations(state, node).unwrap_or_default();
+
+    let title = match locations.len() {
+        1 => String::from("1 implementation"),
+        n => format!("{} implementations", n),
+    };
+    lens.command = Some(command(&lens, title, locations));
+
+    lens
+}
+
+fn lens(state: &BackendState, node: NodeId, uri: &Url, kind: &str) -> CodeLens {
+    CodeLens {
+        range: state.arena[node].get().selection_range,
+        command: None,
+        data: Some(json!({ "uri": uri, "kind": kind })),
+    }
+}
+
+/// The client shows the locations in a peek view when the lens is clicked
+fn command(lens: &CodeLens, title: String, locations: Vec<Location>) -> Command {
+    let uri = lens
+        .data
+        .as_ref()
+        .and_then(|data| data.get("uri"))
+        .cloned()
+        .unwrap_or(Value::Null);
+
+    Command {
+        title,
+        command: String::from("phplsrs.showReferences"),
+        arguments: Some(vec![uri, json!(lens.range.start), json!(locations)]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::tests::populate_state;
+    use lsp_types::TextDocumentIdentifier;
+
+    fn lenses(state: &BackendState, file: &str) -> Vec<CodeLens> {
+        let params = CodeLensParams {
+            text_document: TextDocumentIdentifier {
+                uri: Url::from_file_path(file).unwrap(),
+            },
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        };
+
+        code_lens(state, params).unwrap().unwrap()
+    }
+
+    fn resolve(state: &BackendState, lens: CodeLens) -> String {
+        let (node, _) = symbol_of_lens(state, &lens).unwrap();
+
+        let lens = if counts_implementations(&lens) {
+            resolve_implementations(state, lens, node)
+        } else {
+            resolve_references(state, lens, node, &state.symbol_references)
+        };
+
+        lens.command.unwrap().title
+    }
+
+    #[test]
+    fn test_counts_references_and_implementations() {
+        let mut state = BackendState::default();
+        populate_state(
+            &mut state,
+            &[
+                (
+                    "/a.php",
+                    "<?php
+interface Shape { public function area(); }
+abstract class Base implements Shape {
+    abstract public function name();
+    public function area() { return $this->name(); }
+}
+",
+                ),
+                (
+                    "/b.php",
+                    "<?php
+class Circle extends Base { public function name() {} }
+function make(): Shape { return new Circle(); }
+make();
+",
+                ),
+            ],
+        );
+
+        let titles = lenses(&state, "/a.php")
+            .drain(..)
+            .map(|lens| (lens.range.start.line, resolve(&state, lens)))
+            .collect::<Vec<(u32, String)>>();
+
+        assert_eq!(
+            vec![
+                (1, String::from("2 references")),
+                (1, String::from("1 implementation")),
+                (1, String::from("0 references")),
+                (1, String::from("1 implementation")),
+                // $this refers to the class as well
+                (2, String::from("2 references")),
+                (3, String::from("1 reference")),
+                (3, String::from("1 implementation")),
+                (4, String::from("0 references")),
+            ],
+            titles
+        );
+
+        let titles = lenses(&state, "/b.php")
+            .drain(..)
+            .map(|lens| (lens.range.start.line, resolve(&state, lens)))
+            .collect::<Vec<(u32, String)>>();
+        assert_eq!(
+            vec![
+                (1, String::from("1 reference")),
+                (1, String::from("0 references")),
+                (2, String::from("1 reference")),
+            ],
+            titles
+        );
+    }
+}
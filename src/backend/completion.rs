@@ -1,3 +1,4 @@
+use super::code_action::use_statement_edit;
 use super::BackendState;
 use crate::environment::fs as EnvFs;
 use crate::environment::get_range;
@@ -40,6 +41,11 @@ pub(crate) fn completion(
 
     if let Some((file_ast, _range)) = file_ast {
         let ast = file_ast;
+        let source = state
+            .latest_version_of_file
+            .get(opened_file)
+            .map(|source| source.as_str())
+            .unwrap_or_default();
 
         let current_file_symbol = if let Some(current_file_symbol) = state.files.get(opened_file) {
             current_file_symbol
@@ -59,6 +65,7 @@ pub(crate) fn completion(
                 &state.arena,
                 &state.global_symbols,
                 references,
+                state.inferred_types.get(opened_file).map(|types| &**types),
             );
 
             return Ok(Some(CompletionResponse::Array(
@@ -82,6 +89,7 @@ pub(crate) fn completion(
 
                         if symbol.kind == PhpSymbolKind::Class
                             || symbol.kind == PhpSymbolKind::Trait
+                            || symbol.kind == PhpSymbolKind::Enum
                         {
                             if sug.context == SuggestionContext::Import {
                                 return CompletionItem {
@@ -106,28 +114,17 @@ pub(crate) fn completion(
 
                             let fqdn = symbol.fqdn();
                             // Check if the current file already has that import. if yes we are good
-                            let line = if let Some(imports) = current_file.imports.as_ref() {
+                            if let Some(imports) = current_file.imports.as_ref() {
                                 if imports.all().any(|import| import.full_name() == fqdn) {
                                     return symbol.completion_item(sn, &state.arena);
-                                } else {
-                                    // add use to the end of the imports
-                                    if let Some(first_import) = imports.all().next() {
-                                        first_import.path.range().start_line
-                                    } else {
-                                        3
-                                    }
                                 }
-                            } else {
-                                // add use right after the namespace or the opening <?php
-                                3
-                            };
+                            }
 
                             // if not, we add it as a text edit
                             return CompletionItem {
-                                additional_text_edits: Some(vec![TextEdit {
-                                    range: get_range(((0, line), (0, line)).into()),
-                                    new_text: format!("use {};\n", fqdn),
-                                }]),
+                                additional_text_edits: Some(vec![use_statement_edit(
+                                    ast, source, &fqdn,
+                                )]),
                                 ..symbol.completion_item(sn, &state.arena)
                             };
                         }
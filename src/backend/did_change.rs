@@ -1,17 +1,193 @@
 use super::{Backend, BackendState};
 use crate::environment::fs as EnvFs;
-use lsp_types::DidChangeTextDocumentParams;
+use lsp_types::{
+    Diagnostic, DidChangeTextDocumentParams, Position, TextDocumentContentChangeEvent, Url,
+};
+use std::time::Duration;
 
+/// Time to wait for further edits before the changed file and the other opened files are
+/// analyzed again
+pub(crate) const REANALYSIS_DELAY: Duration = Duration::from_millis(250);
+
+/// Apply the changes to the latest version of the file and remember the version of the edit.
+/// The file is analyzed later on by `reanalyze`, once no further edits followed.
 pub(crate) fn did_change(state: &mut BackendState, params: &DidChangeTextDocumentParams) {
-    let uri = params.text_document.uri.clone();
+    let file_path = params.text_document.uri.to_file_path().unwrap();
+    let path = EnvFs::normalize_path(&file_path);
+
+    let source = state
+        .latest_version_of_file
+        .entry(path.clone())
+        .or_default();
+
+    for change in params.content_changes.iter() {
+        apply_change(source, change);
+    }
+
+    state
+        .document_versions
+        .insert(path, params.text_document.version);
+}
+
+/// Analyze a changed file and resolve the references of the other opened files to it again,
+/// unless it was edited after `version`. Returns the diagnostics of the changed file if it was
+/// the latest edit.
+pub(crate) fn reanalyze(
+    state: &mut BackendState,
+    uri: Url,
+    version: i32,
+) -> Option<Vec<Diagnostic>> {
     let file_path = uri.to_file_path().unwrap();
     let path = EnvFs::normalize_path(&file_path);
 
-    if let Some(changes) = params.content_changes.first() {
+    if state.document_versions.get(&path) != Some(&version) {
+        return None;
+    }
+
+    let source = state.latest_version_of_file.get(&path)?.clone();
+    Backend::analyze_file(state, &uri, &source);
+    Backend::refresh_opened_files(state, &path);
+
+    Some(
+        state
+            .diagnostics
+            .get(&path)
+            .map(|d| d.to_vec())
+            .unwrap_or_default(),
+    )
+}
+
+/// Apply a single change event, which either replaces a range or the whole document
+fn apply_change(source: &mut String, change: &TextDocumentContentChangeEvent) {
+    if let Some(range) = change.range {
+        let start = offset_at(source, &range.start);
+        let end = offset_at(source, &range.end).max(start);
+
+        source.replace_range(start..end, &change.text);
+    } else {
+        *source = change.text.clone();
+    }
+}
+
+/// Convert a position to a byte offset in the source. The character of an LSP position counts
+/// UTF-16 code units, positions beyond the end of a line or the document are clamped.
+fn offset_at(source: &str, position: &Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match source[line_start..].find('\n') {
+            Some(newline) => line_start += newline + 1,
+            None => return source.len(),
+        }
+    }
+
+    let line = &source[line_start..];
+    let line = &line[..line.find('\n').unwrap_or(line.len())];
+    let line = line.strip_suffix('\r').unwrap_or(line);
+
+    let mut units = 0;
+    for (offset, c) in line.char_indices() {
+        if units >= position.character {
+            return line_start + offset;
+        }
+
+        units += c.len_utf16() as u32;
+    }
+
+    line_start + line.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_types::{Range, VersionedTextDocumentIdentifier};
+
+    fn change(start: (u32, u32), end: (u32, u32), text: &str) -> TextDocumentContentChangeEvent {
+        TextDocumentContentChangeEvent {
+            range: Some(Range {
+                start: Position {
+                    line: start.0,
+                    character: start.1,
+                },
+                end: Position {
+                    line: end.0,
+                    character: end.1,
+                },
+            }),
+            range_length: None,
+            text: text.to_owned(),
+        }
+    }
+
+    fn params(
+        version: i32,
+        changes: Vec<TextDocumentContentChangeEvent>,
+    ) -> DidChangeTextDocumentParams {
+        DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier {
+                uri: Url::from_file_path("/index.php").unwrap(),
+                version,
+            },
+            content_changes: changes,
+        }
+    }
+
+    #[test]
+    fn test_applies_ranged_changes_in_order() {
+        let mut source = String::from("<?php\n$a = 1;\r\n$b = 2;\n");
+
+        apply_change(&mut source, &change((1, 5), (1, 6), "10"));
+        apply_change(&mut source, &change((2, 0), (2, 2), "$c"));
+        apply_change(&mut source, &change((1, 8), (2, 0), "\n"));
+
+        assert_eq!("<?php\n$a = 10;\n$c = 2;\n", source);
+    }
+
+    #[test]
+    fn test_counts_characters_in_utf16_code_units() {
+        // The emoji takes two UTF-16 code units and four bytes
+        let mut source = String::from("<?php\n$a = '😀ä';\n");
+
+        apply_change(&mut source, &change((1, 8), (1, 9), "ö"));
+        assert_eq!("<?php\n$a = '😀ö';\n", source);
+
+        apply_change(&mut source, &change((1, 6), (1, 8), ""));
+        assert_eq!("<?php\n$a = 'ö';\n", source);
+    }
+
+    #[test]
+    fn test_clamps_positions_beyond_the_end() {
+        let mut source = String::from("<?php\n$a;");
+
+        apply_change(&mut source, &change((1, 20), (5, 0), "\n$b;"));
+
+        assert_eq!("<?php\n$a;\n$b;", source);
+    }
+
+    #[test]
+    fn test_analyzes_only_the_latest_edit() {
+        let mut state = BackendState::default();
+        let uri = Url::from_file_path("/index.php").unwrap();
         state
             .latest_version_of_file
-            .insert(path, changes.text.clone());
+            .insert(String::from("/index.php"), String::from("<?php\n").into());
+
+        did_change(
+            &mut state,
+            &params(1, vec![change((1, 0), (1, 0), "class A {}")]),
+        );
+        did_change(
+            &mut state,
+            &params(2, vec![change((1, 10), (1, 10), "\nclass B {}")]),
+        );
+
+        assert!(state.global_symbols.is_empty());
+        assert!(!state.opened_files.contains_key("/index.php"));
+
+        assert!(reanalyze(&mut state, uri.clone(), 1).is_none());
+        assert!(state.global_symbols.is_empty());
 
-        Backend::refresh_file(state, uri, &changes.text);
+        assert!(reanalyze(&mut state, uri, 2).is_some());
+        assert_eq!(2, state.global_symbols.len());
+        assert!(state.opened_files.contains_key("/index.php"));
     }
 }
@@ -1,6 +1,6 @@
 use lsp_types::{Diagnostic, DidChangeWatchedFilesParams};
 
-use super::{Backend, BackendState};
+use super::{document_link, reference_index, Backend, BackendState};
 use crate::environment::fs as EnvFs;
 
 pub(crate) fn did_change_watched_files(
@@ -12,6 +12,13 @@ pub(crate) fn did_change_watched_files(
 
         let path = EnvFs::normalize_path(&file_path);
 
+        // Files that can not be autoloaded were never indexed in the first place
+        if let Some(composer) = state.composer.as_ref() {
+            if !composer.is_autoloadable(&file_path) {
+                continue;
+            }
+        }
+
         // If the file is currently opened we don't have to refresh
         if state.opened_files.contains_key(&path) {
             return;
@@ -24,12 +31,21 @@ pub(crate) fn did_change_watched_files(
                 eprintln!("Error collecting symbols: {}", e);
             }
 
-            let diagnostics = state
-                .diagnostics
-                .entry(path.to_string())
-                .or_insert_with(Vec::new);
+            if reference_index::is_indexed(state, &path) {
+                reference_index::index_file(state, &path, &ast);
+            }
+
+            let diagnostics = state.diagnostics.entry(path.to_string()).or_default();
             diagnostics.clear();
             diagnostics.extend(errors.iter().map(Diagnostic::from));
+            diagnostics.extend(document_link::include_diagnostics(&ast, &path));
+
+            let autoload_diagnostics = Backend::autoload_diagnostics(state, &path);
+            state
+                .diagnostics
+                .entry(path.to_string())
+                .or_default()
+                .extend(autoload_diagnostics);
         }
     }
 }
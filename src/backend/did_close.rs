@@ -5,6 +5,10 @@ use lsp_types::DidCloseTextDocumentParams;
 pub(crate) fn did_close(state: &mut BackendState, params: DidCloseTextDocumentParams) {
     let p = EnvFs::normalize_path(&params.text_document.uri.to_file_path().unwrap());
     state.latest_version_of_file.remove(&p);
+    state.document_versions.remove(&p);
     state.opened_files.remove(&p);
-    state.symbol_references.remove(&p);
+    // Unsaved changes are discarded, so the references are indexed again from the saved file
+    state.stale_references.insert(p.clone());
+    state.inferred_types.remove(&p);
+    state.semantic_tokens.lock().unwrap().remove(&p);
 }
@@ -1,4 +1,4 @@
-use super::{Backend, BackendState};
+use super::{document_link, Backend, BackendState};
 use crate::environment::fs as EnvFs;
 use lsp_types::{Diagnostic, DidOpenTextDocumentParams};
 
@@ -6,16 +6,22 @@ pub(crate) fn did_open(state: &mut BackendState, params: &DidOpenTextDocumentPar
     let file_path = params.text_document.uri.to_file_path().unwrap();
 
     let path = EnvFs::normalize_path(&file_path);
-    let source = std::fs::read_to_string(file_path).unwrap();
+    let source = params.text_document.text.clone();
     state
         .latest_version_of_file
-        .insert(path.clone(), source.clone());
+        .insert(path.clone(), source.clone().into());
+    state
+        .document_versions
+        .insert(path.clone(), params.text_document.version);
 
     if !state.opened_files.contains_key(&path) {
         if let Ok((ast, range, errors)) = Backend::source_to_ast(&source) {
-            let diags = errors.iter().map(Diagnostic::from).collect();
-            state.diagnostics.insert(path.to_owned(), diags);
-            state.opened_files.insert(path.to_string(), (ast, range));
+            let mut diags: Vec<Diagnostic> = errors.iter().map(Diagnostic::from).collect();
+            diags.extend(document_link::include_diagnostics(&ast, &path));
+            state.diagnostics.insert(path.to_owned(), diags.into());
+            state
+                .opened_files
+                .insert(path.to_string(), (ast.into(), range));
         } else {
             return;
         }
@@ -0,0 +1,318 @@
+use super::BackendState;
+use crate::environment::{fs as EnvFs, get_range};
+use crate::parser::node::Node as AstNode;
+use crate::parser::scanner::Scanner;
+use crate::parser::token::{Token, TokenType};
+use lsp_types::{
+    Diagnostic, DiagnosticSeverity, DocumentLink, DocumentLinkParams, Position, Range, Url,
+};
+use std::path::{Path, PathBuf};
+use tower_lsp::jsonrpc::Result;
+
+/// Gateway function that accepts the LSP parameters and calls the exec method
+pub(crate) fn document_link(
+    state: &BackendState,
+    params: DocumentLinkParams,
+) -> Result<Option<Vec<DocumentLink>>> {
+    let file = EnvFs::normalize_path(&params.text_document.uri.to_file_path().unwrap());
+
+    if let Some((ast, _)) = state.opened_files.get(&file) {
+        let source = state
+            .latest_version_of_file
+            .get(&file)
+            .map(|source| source.as_str());
+
+        return Ok(Some(exec(ast, source, &file)));
+    }
+
+    Ok(None)
+}
+
+#[inline]
+fn exec(ast: &[AstNode], source: Option<&str>, file: &str) -> Vec<DocumentLink> {
+    let mut links = includes(ast, file)
+        .drain(..)
+        .filter(|(_, path)| path.is_file())
+        .filter_map(|(range, path)| {
+            Some(DocumentLink {
+                range,
+                target: Some(Url::from_file_path(&path).ok()?),
+                tooltip: None,
+                data: None,
+            })
+        })
+        .collect::<Vec<DocumentLink>>();
+
+    // Doc comments are not kept in the AST, so they are taken from the token stream
+    if let Some(source) = source {
+        let mut scanner = Scanner::new(source);
+
+        if scanner.scan().is_ok() {
+            for token in scanner.tokens.iter() {
+                if token.t == TokenType::MultilineComment {
+                    links.extend(doc_comment_links(token));
+                }
+            }
+        }
+    }
+
+    links.sort_by_key(|link| link.range.start);
+    links
+}
+
+/// Warn about includes of files that do not exist. Includes with paths that can not be
+/// determined statically are ignored.
+pub(crate) fn include_diagnostics(ast: &[AstNode], file: &str) -> Vec<Diagnostic> {
+    includes(ast, file)
+        .drain(..)
+        .filter(|(_, path)| !path.is_file())
+        .map(|(range, path)| Diagnostic {
+            range,
+            message: format!("Included file {} does not exist", path.display()),
+            severity: Some(DiagnosticSeverity::WARNING),
+            ..Diagnostic::default()
+        })
+        .collect()
+}
+
+/// All include and require statements with the range of their path and the path itself
+fn includes(ast: &[AstNode], file: &str) -> Vec<(Range, PathBuf)> {
+    let mut includes = Vec::new();
+
+    for node in ast {
+        collect_includes(node, file, &mut includes);
+    }
+
+    includes
+}
+
+fn collect_includes(node: &AstNode, file: &str, includes: &mut Vec<(Range, PathBuf)>) {
+    if let AstNode::FileInclude { resource, .. } = node {
+        if let Some(path) = evaluate(resource, file) {
+            let path = Path::new(&path);
+            let path = if path.is_relative() {
+                Path::new(file)
+                    .parent()
+                    .unwrap_or_else(|| Path::new(""))
+                    .join(path)
+            } else {
+                path.to_path_buf()
+            };
+
+            includes.push((
+                get_range(resource.range()),
+                EnvFs::normalize_lexically(&path),
+            ));
+        }
+    }
+
+    for child in node.children() {
+        collect_includes(child, file, includes);
+    }
+}
+
+/// Fold the constant parts of a path expression into a string, namely string literals,
+/// `__DIR__`, `__FILE__`, `dirname()` and concatenations of them
+fn evaluate(node: &AstNode, file: &str) -> Option<String> {
+    match node {
+        AstNode::Literal(token) => match token.t {
+            TokenType::ConstantEncapsedString | TokenType::EncapsedAndWhitespaceString => {
+                token.label.clone()
+            }
+            TokenType::ConstDir => Some(dirname(file, 1)),
+            TokenType::ConstFile => Some(file.to_owned()),
+            _ => None,
+        },
+        AstNode::Binary { left, token, right } if token.t == TokenType::Concat => Some(format!(
+            "{}{}",
+            evaluate(left, file)?,
+            evaluate(right, file)?
+        )),
+        AstNode::Grouping(expr) => evaluate(expr, file),
+        AstNode::Call {
+            callee, parameters, ..
+        } if callee.normalized_name().trim_start_matches('\\') == "dirname" => {
+            let levels = match parameters.get(1) {
+                Some(AstNode::Literal(token)) if token.t == TokenType::LongNumber => {
+                    token.label.as_ref()?.parse().ok()?
+                }
+                Some(_) => return None,
+                None => 1,
+            };
+
+            Some(dirname(&evaluate(parameters.first()?, file)?, levels))
+        }
+        _ => None,
+    }
+}
+
+/// Same as PHP's `dirname`, go up the given number of levels
+fn dirname(path: &str, levels: usize) -> String {
+    let mut path = Path::new(path);
+
+    for _ in 0..levels {
+        path = path.parent().unwrap_or(path);
+    }
+
+    EnvFs::normalize_path(path)
+}
+
+/// Link the URLs following `@see` and `@link` tags
+fn doc_comment_links(comment: &Token) -> Vec<DocumentLink> {
+    let mut links = Vec::new();
+    let text = comment.label.as_deref().unwrap_or_default();
+
+    for (i, line) in text.lines().enumerate() {
+        let mut words = line.split_whitespace();
+
+        while let Some(word) = words.next() {
+            if word != "@see" && word != "@link" {
+                continue;
+            }
+
+            let url = match words.next() {
+                Some(url) if url.starts_with("http://") || url.starts_with("https://") => url,
+                _ => continue,
+            };
+            let target = if let Ok(target) = Url::parse(url) {
+                target
+            } else {
+                continue;
+            };
+
+            // The label starts after the opening `/*` of the comment. Columns count UTF-16
+            // code units.
+            let offset = line[..line.find(url).unwrap_or_default()]
+                .encode_utf16()
+                .count() as u32;
+            let (line, col) = if i == 0 {
+                (comment.line, comment.col + 2 + offset)
+            } else {
+                (comment.line + i as u32, offset)
+            };
+
+            links.push(DocumentLink {
+                range: Range::new(
+                    Position::new(line, col),
+                    Position::new(line, col + url.encode_utf16().count() as u32),
+                ),
+                target: Some(target),
+                tooltip: None,
+                data: None,
+            });
+        }
+    }
+
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn ast(source: &str) -> Vec<AstNode> {
+        let mut scanner = Scanner::new(source);
+        scanner.scan().unwrap();
+
+        Parser::ast(scanner.tokens).unwrap().0
+    }
+
+    #[test]
+    fn test_folds_constant_include_paths() {
+        let file = "/project/public/index.php";
+        let paths = includes(
+            &ast("<?php
+require __DIR__ . '/../config/app.php';
+require_once(dirname(__DIR__) . '/vendor/autoload.php');
+include dirname(__FILE__, 3) . \"/lib.php\";
+include 'partials/header.php';
+include $dynamic . '/file.php';
+"),
+            file,
+        );
+
+        assert_eq!(
+            vec![
+                (
+                    Range::new(Position::new(1, 8), Position::new(1, 38)),
+                    PathBuf::from("/project/config/app.php")
+                ),
+                (
+                    Range::new(Position::new(2, 13), Position::new(2, 54)),
+                    PathBuf::from("/project/vendor/autoload.php")
+                ),
+                (
+                    Range::new(Position::new(3, 8), Position::new(3, 41)),
+                    PathBuf::from("/lib.php")
+                ),
+                (
+                    Range::new(Position::new(4, 8), Position::new(4, 29)),
+                    PathBuf::from("/project/public/partials/header.php")
+                ),
+            ],
+            paths
+        );
+    }
+
+    #[test]
+    fn test_links_existing_includes_and_warns_about_missing_ones() {
+        let dir = std::env::temp_dir().join(format!("phpls-rs-links-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("app.php"), "<?php").unwrap();
+
+        let file = EnvFs::normalize_path(&dir.join("index.php"));
+        let source = "<?php
+/**
+ * @see https://example.com/docs Documentation
+ */
+require __DIR__ . '/app.php';
+require __DIR__ . '/missing.php';
+";
+        let ast = ast(source);
+
+        let links = exec(&ast, Some(source), &file);
+        assert_eq!(
+            vec![
+                (
+                    Range::new(Position::new(2, 8), Position::new(2, 32)),
+                    String::from("https://example.com/docs")
+                ),
+                (
+                    Range::new(Position::new(4, 8), Position::new(4, 28)),
+                    Url::from_file_path(dir.join("app.php"))
+                        .unwrap()
+                        .to_string()
+                ),
+            ],
+            links
+                .iter()
+                .map(|link| (link.range, link.target.as_ref().unwrap().to_string()))
+                .collect::<Vec<(Range, String)>>()
+        );
+
+        let diagnostics = include_diagnostics(&ast, &file);
+        assert_eq!(1, diagnostics.len());
+        assert_eq!(5, diagnostics[0].range.start.line);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_counts_link_columns_in_utf16_code_units() {
+        let source = "<?php
+/**
+ * Größe 😀 @link https://example.com/größe
+ */
+function size() {}
+";
+
+        assert_eq!(
+            vec![Range::new(Position::new(2, 18), Position::new(2, 43))],
+            exec(&ast(source), Some(source), "/index.php")
+                .iter()
+                .map(|link| link.range)
+                .collect::<Vec<Range>>()
+        );
+    }
+}
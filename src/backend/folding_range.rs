@@ -0,0 +1,264 @@
+use super::BackendState;
+use crate::environment::fs as EnvFs;
+use crate::parser::node::Node as AstNode;
+use crate::parser::scanner::Scanner;
+use crate::parser::token::{Token, TokenType};
+use lsp_types::{FoldingRange, FoldingRangeKind, FoldingRangeParams};
+use tower_lsp::jsonrpc::Result;
+
+/// Gateway function that accepts the LSP parameters and calls the exec method
+pub(crate) fn folding_range(
+    state: &BackendState,
+    params: FoldingRangeParams,
+) -> Result<Option<Vec<FoldingRange>>> {
+    let file = EnvFs::normalize_path(&params.text_document.uri.to_file_path().unwrap());
+
+    if let Some((ast, _)) = state.opened_files.get(&file) {
+        let source = state
+            .latest_version_of_file
+            .get(&file)
+            .map(|source| source.as_str());
+
+        return Ok(Some(exec(ast, source)));
+    }
+
+    Ok(None)
+}
+
+#[inline]
+fn exec(ast: &[AstNode], source: Option<&str>) -> Vec<FoldingRange> {
+    let mut ranges = Vec::new();
+
+    fold_use_statements(&ast.iter().collect::<Vec<&AstNode>>(), &mut ranges);
+    for node in ast {
+        collect_ranges(node, &mut ranges);
+    }
+
+    // Comments are not part of the AST, so they are taken from the token stream
+    if let Some(source) = source {
+        let mut scanner = Scanner::new(source);
+
+        if scanner.scan().is_ok() {
+            token_ranges(&scanner.tokens, &mut ranges);
+        }
+    }
+
+    ranges.sort_by_key(|range| (range.start_line, range.end_line));
+    ranges.dedup_by_key(|range| (range.start_line, range.end_line));
+    ranges
+}
+
+/// Walk the AST and fold everything enclosed in brackets or keywords that spans multiple lines
+fn collect_ranges(node: &AstNode, ranges: &mut Vec<FoldingRange>) {
+    match node {
+        AstNode::Block { oc, cc, .. }
+        | AstNode::Match { oc, cc, .. }
+        | AstNode::Array { ob: oc, cb: cc, .. }
+        | AstNode::OldArray { op: oc, cp: cc, .. }
+        | AstNode::Call { op: oc, cp: cc, .. }
+        | AstNode::SwitchBody {
+            start: oc, end: cc, ..
+        }
+        | AstNode::AlternativeBlock {
+            colon: oc,
+            terminator: cc,
+            ..
+        } => bracket(oc, cc, None, ranges),
+        AstNode::GroupedUse { oc, cc, .. } => {
+            bracket(oc, cc, Some(FoldingRangeKind::Imports), ranges)
+        }
+        AstNode::ArrowFunction { token, body, .. } => {
+            push(token.line, body.range().end_line, None, ranges)
+        }
+        _ => (),
+    }
+
+    let children = node.children();
+    fold_use_statements(&children, ranges);
+
+    for child in children {
+        collect_ranges(child, ranges);
+    }
+}
+
+/// Fold consecutive use statements into a single block of imports
+fn fold_use_statements(statements: &[&AstNode], ranges: &mut Vec<FoldingRange>) {
+    let mut block: Option<(u32, u32)> = None;
+
+    for statement in statements {
+        match statement {
+            AstNode::UseStatement { .. }
+            | AstNode::UseFunctionStatement { .. }
+            | AstNode::UseConstStatement { .. } => {
+                let range = statement.range();
+
+                block = match block {
+                    Some((start, _)) => Some((start, range.end_line)),
+                    None => Some((range.start_line, range.end_line)),
+                };
+            }
+            _ => {
+                if let Some((start, end)) = block.take() {
+                    push(start, end, Some(FoldingRangeKind::Imports), ranges);
+                }
+            }
+        }
+    }
+
+    if let Some((start, end)) = block {
+        push(start, end, Some(FoldingRangeKind::Imports), ranges);
+    }
+}
+
+/// Fold multi-line comments, heredocs and the lines between `// region` and `// endregion`
+fn token_ranges(tokens: &[Token], ranges: &mut Vec<FoldingRange>) {
+    let mut regions = Vec::new();
+    let mut heredoc = None;
+
+    for token in tokens {
+        match token.t {
+            TokenType::MultilineComment => {
+                let range = token.range();
+
+                push(
+                    range.start_line,
+                    range.end_line,
+                    Some(FoldingRangeKind::Comment),
+                    ranges,
+                );
+            }
+            TokenType::LineComment => {
+                let comment = token.label.as_deref().unwrap_or_default().trim_start();
+
+                if comment.starts_with("region") {
+                    regions.push(token.line);
+                } else if comment.starts_with("endregion") {
+                    if let Some(start) = regions.pop() {
+                        push(start, token.line, Some(FoldingRangeKind::Region), ranges);
+                    }
+                }
+            }
+            TokenType::HereDocStart => heredoc = Some(token.line),
+            TokenType::HereDocEnd => {
+                if let Some(start) = heredoc.take() {
+                    push(start, token.line.saturating_sub(1), None, ranges);
+                }
+            }
+            _ => (),
+        }
+    }
+}
+
+/// Fold the lines between two brackets, keeping the line of the closing one visible
+fn bracket(
+    open: &Token,
+    close: &Token,
+    kind: Option<FoldingRangeKind>,
+    ranges: &mut Vec<FoldingRange>,
+) {
+    push(open.line, close.line.saturating_sub(1), kind, ranges);
+}
+
+fn push(start: u32, end: u32, kind: Option<FoldingRangeKind>, ranges: &mut Vec<FoldingRange>) {
+    if end <= start {
+        return;
+    }
+
+    ranges.push(FoldingRange {
+        start_line: start,
+        start_character: None,
+        end_line: end,
+        end_character: None,
+        kind,
+        collapsed_text: None,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::Parser;
+
+    fn folds(source: &str) -> Vec<(u32, u32, Option<FoldingRangeKind>)> {
+        let mut scanner = Scanner::new(source);
+        scanner.scan().unwrap();
+        let (ast, _) = Parser::ast(scanner.tokens).unwrap();
+
+        exec(&ast, Some(source))
+            .drain(..)
+            .map(|range| (range.start_line, range.end_line, range.kind))
+            .collect()
+    }
+
+    #[test]
+    fn test_folds_blocks_comments_and_regions() {
+        let source = "<?php
+use App\\A;
+use App\\B;
+
+// region Helpers
+/**
+ * Doc
+ */
+class Foo
+{
+    public function bar()
+    {
+        if ($a):
+            echo 1;
+        endif;
+        $x = [
+            1,
+        ];
+        call(
+            1
+        );
+        $f = fn() =>
+            1;
+        $s = <<<EOT
+text
+EOT;
+    }
+}
+// endregion
+";
+
+        assert_eq!(
+            vec![
+                (1, 2, Some(FoldingRangeKind::Imports)),
+                (4, 28, Some(FoldingRangeKind::Region)),
+                (5, 7, Some(FoldingRangeKind::Comment)),
+                (9, 26, None),
+                (11, 25, None),
+                (12, 13, None),
+                (15, 16, None),
+                (18, 19, None),
+                (21, 22, None),
+                (23, 24, None),
+            ],
+            folds(source)
+        );
+    }
+
+    #[test]
+    fn test_folds_nested_regions_and_skips_single_lines() {
+        let source = "<?php
+# region Outer
+function a() { return [1, 2]; }
+//region Inner
+function b()
+{
+}
+// endregion
+#endregion
+";
+
+        assert_eq!(
+            vec![
+                (1, 8, Some(FoldingRangeKind::Region)),
+                (3, 7, Some(FoldingRangeKind::Region)),
+            ],
+            folds(source)
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use super::{Backend, BackendState};
+use super::{type_hierarchy, Backend, BackendState};
 use crate::environment::{
     fs as EnvFs, symbol::PhpSymbolKind, visitor::name_resolver::NameResolver,
 };
@@ -10,7 +10,7 @@ use lsp_types::{
 use tower_lsp::jsonrpc::Result;
 
 pub(crate) fn goto_implementation(
-    state: &mut BackendState,
+    state: &BackendState,
     params: GotoImplementationParams,
 ) -> Result<Option<GotoImplementationResponse>> {
     let position = &params.text_document_position_params.position;
@@ -22,69 +22,87 @@ pub(crate) fn goto_implementation(
             .to_file_path()
             .unwrap(),
     );
-    let mut results = Vec::new();
 
-    if let Some((nuc, sym_name)) = Backend::symbol_under_cursor(&state, position, &file) {
-        let kind_of_suc = state.arena[nuc].get().kind;
+    if let Some((nuc, _)) = Backend::symbol_under_cursor(state, position, &file) {
+        return Ok(implementations(state, nuc).map(GotoDefinitionResponse::Array));
+    }
 
-        if kind_of_suc == PhpSymbolKind::Method {
-            let parent_node = if let Some(parent_node) = state.arena[nuc].parent() {
-                let parent_symbol = state.arena[parent_node].get();
-                if parent_symbol.kind == PhpSymbolKind::Interface {
-                    parent_node
-                } else {
-                    return Ok(None);
-                }
-            } else {
-                return Ok(None);
-            };
-
-            let method_name = sym_name.to_lowercase();
-
-            // We are searching for the implementations of a particular method
-            return Ok(Some(GotoDefinitionResponse::Array(
-                implementing_interfaces(parent_node, state)
-                    .iter()
-                    .filter_map(|(file, node)| {
-                        node.children(&state.arena).find_map(|child_of_interface| {
-                            let meth = state.arena[child_of_interface].get();
-
-                            if meth.normalized_name().eq(&method_name) {
-                                Some(Location {
-                                    uri: Url::from_file_path(file).unwrap(),
-                                    range: meth.selection_range,
-                                })
-                            } else {
-                                None
-                            }
-                        })
+    Ok(Some(GotoDefinitionResponse::Array(Vec::new())))
+}
+
+/// Find the implementations of an interface or of an abstract method. Returns `None` for
+/// methods that are neither declared in an interface nor abstract.
+pub(crate) fn implementations(state: &BackendState, node: NodeId) -> Option<Vec<Location>> {
+    let symbol = state.arena[node].get();
+
+    if symbol.kind == PhpSymbolKind::Method {
+        let parent_node = state.arena[node].parent()?;
+        let parent_symbol = state.arena[parent_node].get();
+        let method_name = symbol.normalized_name();
+
+        let implementors = if parent_symbol.kind == PhpSymbolKind::Interface {
+            implementing_interfaces(parent_node, state)
+        } else if symbol.is_abstract {
+            type_hierarchy::all_subtypes(state, parent_node)
+                .drain(..)
+                .filter_map(|subtype| {
+                    let file = subtype
+                        .ancestors(&state.arena)
+                        .find(|a| state.arena[*a].get().kind == PhpSymbolKind::File)?;
+
+                    Some((state.arena[file].get().name.clone(), subtype))
+                })
+                .collect()
+        } else {
+            return None;
+        };
+
+        // We are searching for the implementations of a particular method
+        return Some(
+            implementors
+                .iter()
+                .filter_map(|(file, node)| {
+                    node.children(&state.arena).find_map(|child_of_interface| {
+                        let meth = state.arena[child_of_interface].get();
+
+                        if meth.normalized_name().eq(&method_name) && !meth.is_abstract {
+                            Some(Location {
+                                uri: Url::from_file_path(file).unwrap(),
+                                range: meth.selection_range,
+                            })
+                        } else {
+                            None
+                        }
                     })
-                    .collect(),
-            )));
-        }
+                })
+                .collect(),
+        );
+    }
 
-        if kind_of_suc == PhpSymbolKind::Interface {
-            results.extend(
-                implementing_interfaces(nuc, state)
-                    .iter()
-                    .map(|(file, node)| Location {
-                        uri: Url::from_file_path(file).unwrap(),
-                        range: state.arena[*node].get().selection_range,
-                    }),
-            )
-        }
+    if symbol.kind == PhpSymbolKind::Interface {
+        return Some(
+            implementing_interfaces(node, state)
+                .iter()
+                .map(|(file, node)| Location {
+                    uri: Url::from_file_path(file).unwrap(),
+                    range: state.arena[*node].get().selection_range,
+                })
+                .collect(),
+        );
     }
 
-    Ok(Some(GotoDefinitionResponse::Array(results)))
+    Some(Vec::new())
 }
 
-fn implementing_interfaces(interface: NodeId, state: &mut BackendState) -> Vec<(String, NodeId)> {
+fn implementing_interfaces(interface: NodeId, state: &BackendState) -> Vec<(String, NodeId)> {
     let mut results = Vec::new();
     state.global_symbols.iter().find(|(_, node)| {
         let potential_symbol = state.arena[**node].get();
         let symbol_name = potential_symbol.normalized_name();
 
-        if potential_symbol.kind != PhpSymbolKind::Class {
+        if potential_symbol.kind != PhpSymbolKind::Class
+            && potential_symbol.kind != PhpSymbolKind::Enum
+        {
             return false;
         }
 
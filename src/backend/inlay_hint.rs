@@ -0,0 +1,336 @@
+use super::signature_help::callees;
+use super::BackendState;
+use crate::environment::arguments::Callees;
+use crate::environment::fs as EnvFs;
+use crate::parser::node::Node as AstNode;
+use crate::parser::token::{Token, TokenType};
+use indextree::NodeId;
+use lsp_types::{InlayHint, InlayHintKind, InlayHintLabel, InlayHintParams, Position};
+use serde::Deserialize;
+use serde_json::Value;
+use tower_lsp::jsonrpc::Result;
+
+/// Which inlay hints to show, configurable via the `inlayHints` section of the settings
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct InlayHintOptions {
+    /// Show the names of the parameters at positional arguments
+    pub parameter_names: bool,
+
+    /// Show the inferred types of assigned variables
+    pub variable_types: bool,
+
+    /// Omit the parameter name if the argument is a variable of the same name
+    pub skip_matching_names: bool,
+}
+
+impl Default for InlayHintOptions {
+    fn default() -> Self {
+        InlayHintOptions {
+            parameter_names: true,
+            variable_types: true,
+            skip_matching_names: true,
+        }
+    }
+}
+
+impl InlayHintOptions {
+    /// Read the options from the settings sent by the client, falling back to the defaults
+    pub fn from_settings(settings: Option<&Value>) -> Self {
+        settings
+            .and_then(|settings| settings.get("inlayHints"))
+            .and_then(|options| serde_json::from_value(options.clone()).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Gateway function that accepts the LSP parameters and calls the exec method
+pub(crate) fn inlay_hint(
+    state: &BackendState,
+    params: InlayHintParams,
+) -> Result<Option<Vec<InlayHint>>> {
+    let file = EnvFs::normalize_path(&params.text_document.uri.to_file_path().unwrap());
+
+    if let Some((ast, _)) = state.opened_files.get(&file) {
+        let range = params.range;
+        let hints = exec(state, ast, &file)
+            .drain(..)
+            .filter(|hint| hint.position >= range.start && hint.position <= range.end)
+            .collect();
+
+        return Ok(Some(hints));
+    }
+
+    Ok(None)
+}
+
+#[inline]
+fn exec(state: &BackendState, ast: &[AstNode], file: &str) -> Vec<InlayHint> {
+    let mut hints = Vec::new();
+    let mut callees = callees(state, file);
+
+    for node in ast {
+        collect_hints(state, &mut callees, node, false, file, &mut hints);
+    }
+
+    hints.sort_by_key(|hint| hint.position);
+    hints
+}
+
+/// Walk the AST and collect the hints of all calls and assignments. `is_new` is true if the node
+/// is the constructor call of a `new` expression.
+fn collect_hints(
+    state: &BackendState,
+    callees: &mut Option<Callees>,
+    node: &AstNode,
+    is_new: bool,
+    file: &str,
+    hints: &mut Vec<InlayHint>,
+) {
+    let options = &state.inlay_hint_options;
+
+    // Depending on the context, `new` is parsed as unary operator or as a node of its own
+    match node {
+        AstNode::New { class, .. } => {
+            return collect_hints(state, callees, class, true, file, hints)
+        }
+        AstNode::Unary { token, expr } if token.t == TokenType::New => {
+            return collect_hints(state, callees, expr, true, file, hints)
+        }
+        AstNode::Call {
+            callee, parameters, ..
+        } if options.parameter_names => {
+            if let Some((callable, _)) = callees
+                .as_mut()
+                .and_then(|callees| callees.callable(callee, is_new))
+            {
+                parameter_hints(state, callable, parameters, hints);
+            }
+        }
+        AstNode::Binary { left, token, .. }
+            if token.t == TokenType::Assignment && options.variable_types =>
+        {
+            if let AstNode::Variable(variable) = left.as_ref() {
+                if let Some(hint) = type_hint(state, variable, file) {
+                    hints.push(hint);
+                }
+            }
+        }
+        _ => (),
+    }
+
+    for child in node.children() {
+        collect_hints(state, callees, child, false, file, hints);
+    }
+}
+
+/// Hint the names of the parameters the positional arguments are passed to
+fn parameter_hints(
+    state: &BackendState,
+    callable: NodeId,
+    arguments: &[AstNode],
+    hints: &mut Vec<InlayHint>,
+) {
+    let parameters = &state.arena[callable].get().parameters;
+
+    for (i, argument) in arguments.iter().enumerate() {
+        // Positional arguments can not follow named ones, and a spread argument fills all
+        // remaining parameters
+        match argument {
+            AstNode::NamedParameter { .. } => return,
+            AstNode::Unary { token, .. } if token.t == TokenType::Elipsis => return,
+            _ => (),
+        }
+
+        // A variadic parameter takes all remaining arguments
+        let parameter = match parameters.get(i).or_else(|| parameters.last()) {
+            Some(parameter) => parameter,
+            None => return,
+        };
+        let is_variadic = matches!(
+            &parameter.ast,
+            AstNode::FunctionArgument {
+                spread: Some(_),
+                ..
+            }
+        );
+        if i >= parameters.len() && !is_variadic {
+            return;
+        }
+
+        let name = state.arena[parameter.symbol].get().name();
+        let name = name.trim_start_matches('$');
+
+        if state.inlay_hint_options.skip_matching_names {
+            if let AstNode::Variable(variable) = argument {
+                if variable.label.as_deref() == Some(name) {
+                    continue;
+                }
+            }
+        }
+
+        let start = argument.range();
+        let label = if is_variadic {
+            format!("...{}:", name)
+        } else {
+            format!("{}:", name)
+        };
+
+        hints.push(InlayHint {
+            position: Position::new(start.start_line, start.start_col),
+            label: InlayHintLabel::String(label),
+            kind: Some(InlayHintKind::PARAMETER),
+            text_edits: None,
+            tooltip: None,
+            padding_left: None,
+            padding_right: Some(true),
+            data: None,
+        });
+    }
+}
+
+/// Hint the type that was inferred for the assigned variable
+fn type_hint(state: &BackendState, variable: &Token, file: &str) -> Option<InlayHint> {
+    let range = variable.range();
+    let types = state.inferred_types.get(file)?.get(&range)?;
+
+    // Union types may list the same symbol more than once, keep the first occurrence
+    let mut seen = Vec::new();
+    let names = types
+        .iter()
+        .filter(|t| {
+            if seen.contains(*t) {
+                return false;
+            }
+            seen.push(**t);
+
+            true
+        })
+        .map(|t| state.arena[*t].get().name().to_owned())
+        .collect::<Vec<String>>();
+
+    if names.is_empty() {
+        return None;
+    }
+
+    Some(InlayHint {
+        position: Position::new(range.end_line, range.end_col),
+        label: InlayHintLabel::String(format!(": {}", names.join("|"))),
+        kind: Some(InlayHintKind::TYPE),
+        text_edits: None,
+        tooltip: None,
+        padding_left: None,
+        padding_right: None,
+        data: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::tests::populate_state;
+    use crate::parser::{scanner::Scanner, Parser};
+
+    fn hints(source: &str, options: InlayHintOptions) -> Vec<(u32, u32, String)> {
+        let mut state = BackendState {
+            inlay_hint_options: options,
+            ..BackendState::default()
+        };
+        populate_state(&mut state, &[("index.php", source)]);
+
+        let mut scanner = Scanner::new(source);
+        scanner.scan().unwrap();
+        let (ast, _) = Parser::ast(scanner.tokens).unwrap();
+
+        exec(&state, &ast, "index.php")
+            .drain(..)
+            .map(|hint| match hint.label {
+                InlayHintLabel::String(label) => {
+                    (hint.position.line, hint.position.character, label)
+                }
+                _ => unreachable!(),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_hints_parameter_names_of_positional_arguments() {
+        let source = "<?php
+function sendMail($to, $html, ...$headers) {}
+class Mailer { public function __construct($transport) {} }
+sendMail($to, true, 'a', 'b');
+sendMail('me', html: true);
+new Mailer(null);
+";
+
+        assert_eq!(
+            vec![
+                (3, 14, String::from("html:")),
+                (3, 20, String::from("...headers:")),
+                (3, 25, String::from("...headers:")),
+                (4, 9, String::from("to:")),
+                (5, 11, String::from("transport:")),
+            ],
+            hints(source, InlayHintOptions::default())
+        );
+
+        assert_eq!(
+            (3, 9, String::from("to:")),
+            hints(
+                source,
+                InlayHintOptions {
+                    skip_matching_names: false,
+                    ..InlayHintOptions::default()
+                }
+            )[0]
+        );
+    }
+
+    #[test]
+    fn test_hints_inferred_types_of_assigned_variables() {
+        let source = "<?php
+class Foo {}
+class Bar {}
+function make(): Foo {}
+/** @return Foo|Bar|Foo */
+function either() {}
+$a = new Foo();
+$b = make();
+$c = 1;
+$d = either();
+";
+
+        assert_eq!(
+            vec![
+                (6, 2, String::from(": Foo")),
+                (7, 2, String::from(": Foo")),
+                (9, 2, String::from(": Foo|Bar")),
+            ],
+            hints(
+                source,
+                InlayHintOptions {
+                    parameter_names: false,
+                    ..InlayHintOptions::default()
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_reads_options_from_settings() {
+        let settings = serde_json::json!({ "inlayHints": { "variableTypes": false } });
+
+        assert_eq!(
+            InlayHintOptions {
+                parameter_names: true,
+                variable_types: false,
+                skip_matching_names: true,
+            },
+            InlayHintOptions::from_settings(Some(&settings))
+        );
+        assert_eq!(
+            InlayHintOptions::default(),
+            InlayHintOptions::from_settings(None)
+        );
+    }
+}
@@ -1,18 +1,26 @@
+use crate::environment::arguments::ArgumentChecker;
+use crate::environment::composer::Composer;
+use crate::environment::diagnostics::DiagnosticOptions;
 use crate::environment::fs as EnvFs;
 use crate::environment::get_range;
 use crate::environment::in_range;
+use crate::environment::inheritance::InheritanceChecker;
 use crate::environment::symbol::{PhpSymbolKind, Symbol};
 use crate::environment::traverser::traverse;
+use crate::environment::variables::VariableChecker;
 use crate::environment::visitor::name_resolver::{NameResolveVisitor, NameResolver};
 use crate::environment::visitor::workspace_symbol::WorkspaceSymbolVisitor;
+use crate::environment::Notification;
 use crate::parser::node::Node as AstNode;
 use crate::parser::node::NodeRange;
 use crate::parser::scanner::Scanner;
 use crate::parser::token::{Token, TokenType};
 use crate::parser::Error as ParserError;
 use crate::parser::Parser;
+use cache::{CachedFile, CachedReferences, IndexCache};
 use ignore::{types::TypesBuilder, WalkBuilder};
 use indextree::{Arena, NodeId};
+use inlay_hint::InlayHintOptions;
 use lsp_types::request::GotoImplementationParams;
 use lsp_types::request::GotoImplementationResponse;
 use lsp_types::DefinitionOptions;
@@ -25,72 +33,165 @@ use lsp_types::RenameOptions;
 use lsp_types::WorkspaceFoldersServerCapabilities;
 use lsp_types::WorkspaceServerCapabilities;
 use lsp_types::WorkspaceSymbolOptions;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use reference_index::ReferencingFiles;
+use shared_state::{Shared, SharedState};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 use tokio::io;
 use tokio::runtime::Handle;
-use tokio::sync::Mutex;
 use tokio::task;
 use tower_lsp::lsp_types::{
-    CompletionItem, CompletionOptions, CompletionParams, CompletionResponse, Diagnostic,
-    DidChangeWatchedFilesParams, DidCloseTextDocumentParams, DidOpenTextDocumentParams,
-    DocumentFormattingParams, DocumentHighlight, DocumentHighlightParams, DocumentSymbolParams,
-    DocumentSymbolResponse, ExecuteCommandOptions, GotoDefinitionParams, GotoDefinitionResponse,
-    Hover, HoverParams, HoverProviderCapability, InitializeParams, InitializeResult,
-    InitializedParams, Location, Position, Range, ReferenceParams, RenameParams,
-    ServerCapabilities, SymbolInformation, TextDocumentSyncCapability, TextDocumentSyncKind,
-    TextEdit, Url, WorkspaceEdit, WorkspaceSymbolParams,
+    CallHierarchyIncomingCall, CallHierarchyIncomingCallsParams, CallHierarchyItem,
+    CallHierarchyOutgoingCall, CallHierarchyOutgoingCallsParams, CallHierarchyPrepareParams,
+    CallHierarchyServerCapability, CodeActionParams, CodeActionProviderCapability,
+    CodeActionResponse, CodeLens, CodeLensOptions, CodeLensParams, CompletionItem,
+    CompletionOptions, CompletionParams, CompletionResponse, Diagnostic, DiagnosticSeverity,
+    DidChangeConfigurationParams, DidChangeWatchedFilesParams, DidCloseTextDocumentParams,
+    DidOpenTextDocumentParams, DocumentFormattingParams, DocumentHighlight,
+    DocumentHighlightParams, DocumentLink, DocumentLinkOptions, DocumentLinkParams,
+    DocumentSymbolParams, DocumentSymbolResponse, ExecuteCommandOptions, FoldingRange,
+    FoldingRangeParams, FoldingRangeProviderCapability, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverParams, HoverProviderCapability, InitializeParams,
+    InitializeResult, InitializedParams, InlayHint, InlayHintParams, Location, NumberOrString,
+    Position, Range, ReferenceParams, Registration, RenameParams, SelectionRange,
+    SelectionRangeParams, SelectionRangeProviderCapability, SemanticTokens,
+    SemanticTokensDeltaParams, SemanticTokensFullDeltaResult, SemanticTokensFullOptions,
+    SemanticTokensOptions, SemanticTokensParams, SemanticTokensRangeParams,
+    SemanticTokensRangeResult, SemanticTokensResult, SemanticTokensServerCapabilities,
+    ServerCapabilities, SignatureHelp, SignatureHelpOptions, SignatureHelpParams,
+    SymbolInformation, TextDocumentSyncCapability, TextDocumentSyncKind, TextEdit,
+    TypeHierarchyItem, TypeHierarchyPrepareParams, TypeHierarchySubtypesParams,
+    TypeHierarchySupertypesParams, Url, WorkspaceEdit, WorkspaceSymbolParams,
+};
+use tower_lsp::{
+    jsonrpc::{Error, Result},
+    lsp_types::DidChangeTextDocumentParams,
 };
-use tower_lsp::{jsonrpc::Result, lsp_types::DidChangeTextDocumentParams};
 use tower_lsp::{Client, LanguageServer};
 
 extern crate crossbeam_channel as channel;
 extern crate ignore;
 extern crate walkdir;
 
+mod cache;
+mod call_hierarchy;
+pub mod check;
+mod code_action;
+mod code_lens;
 mod completion;
 mod did_change;
 mod did_change_watched_files;
 mod did_close;
 mod did_open;
 mod document_highlight;
+mod document_link;
 mod document_symbol;
+mod folding_range;
 mod formatting;
 mod goto_definition;
 mod goto_implementation;
 mod hover;
+mod inlay_hint;
+mod reference_index;
+mod references;
+mod rename;
+mod selection_range;
+mod semantic_tokens;
+mod shared_state;
+mod signature_help;
 mod symbol;
+mod type_hierarchy;
 
 pub(crate) type FileReferenceMap = HashMap<NodeId, Vec<NodeRange>>;
-pub(crate) type ReferenceMap = HashMap<String, FileReferenceMap>;
-pub(crate) type ReferenceMapMutex = Arc<Mutex<ReferenceMap>>;
+pub(crate) type ReferenceMap = HashMap<String, Shared<FileReferenceMap>>;
+pub(crate) type FileTypeMap = HashMap<NodeRange, Vec<NodeId>>;
 pub(crate) type ParseResult = (String, Vec<AstNode>, Range, Vec<ParserError>);
 
-#[derive(Default)]
+/// Number of files indexed before the next snapshot of the state is published
+const INDEX_BATCH_SIZE: usize = 100;
+
+/// Result of indexing a single file during the initialization of the workspace
+pub(crate) enum IndexResult {
+    /// The file was parsed, its content is kept to update the cache
+    Parsed(ParseResult, String),
+
+    /// The file did not change since it was cached, along with its references if they were cached
+    Cached(CachedFile, Option<CachedReferences>),
+}
+
+/// The state of the backend. Cloning it is cheap, as the large parts are shared with the clone
+/// until either of them changes them, and maps by file share the values of unchanged files.
+#[derive(Clone, Default)]
 pub struct BackendState {
     /// Storage arena for all symbols
-    pub arena: Arena<Symbol>,
+    pub arena: Shared<Arena<Symbol>>,
 
     /// NodeIds of entry points to files
-    pub files: HashMap<String, NodeId>,
+    pub files: Shared<HashMap<String, NodeId>>,
 
     /// FQDN to NodeId
-    pub global_symbols: HashMap<String, NodeId>,
+    pub global_symbols: Shared<HashMap<String, NodeId>>,
 
     /// Global list of all diagnostics
-    pub diagnostics: HashMap<String, Vec<Diagnostic>>,
+    pub diagnostics: Shared<HashMap<String, Shared<Vec<Diagnostic>>>>,
 
     /// References to symbols implemented as a HashMap of HashMaps. The
     /// other HashMap has the filename as key, the inner HashMap has the
     /// NodeId of the symbols whose references are stored, as key
-    pub symbol_references: ReferenceMap,
+    pub symbol_references: Shared<ReferenceMap>,
+
+    /// Files referencing a symbol, by the key of the symbol, to look up the references of a
+    /// symbol without going through all files
+    pub referencing_files: Shared<ReferencingFiles>,
+
+    /// Lowercase names of the identifiers and types mentioned in a file, by file
+    pub mentioned_names: Shared<HashMap<String, Shared<HashSet<String>>>>,
+
+    /// Files whose references must be collected again before the references are queried, as
+    /// symbols they referenced were removed or changed
+    pub stale_references: HashSet<String>,
+
+    /// Files whose references are missing from the cache, they are cached once they are indexed
+    pub uncached_references: HashSet<String>,
+
+    /// Lowercase names declared since the references were last queried, files mentioning them
+    /// might contain references that could not be resolved before
+    pub new_declarations: HashSet<String>,
+
+    /// Lowercase names of supertypes to the top level types extending, implementing or using them
+    pub inheritors: Shared<HashMap<String, Vec<NodeId>>>,
+
+    /// Inferred types of variables at the locations they are used at, by file
+    pub inferred_types: Shared<HashMap<String, Shared<FileTypeMap>>>,
 
     /// List of currently opened files and their AST
-    pub opened_files: HashMap<String, (Vec<AstNode>, Range)>,
+    pub opened_files: HashMap<String, (Shared<Vec<AstNode>>, Range)>,
 
     /// Map of latest edits on files
-    pub latest_version_of_file: HashMap<String, String>,
+    pub latest_version_of_file: HashMap<String, Shared<String>>,
+
+    /// Version of the latest edit of each opened file, used to debounce the analysis
+    pub document_versions: HashMap<String, i32>,
+
+    /// Autoload rules of the workspace, if it is a composer project
+    pub composer: Option<Composer>,
+
+    /// Path to the stubs of the standard library
+    pub stubs: Option<PathBuf>,
+
+    /// Cache of the index of the workspace, if it could be opened
+    pub(crate) cache: Option<IndexCache>,
+
+    /// Semantic tokens last sent for each opened file, the base of the next delta. They are
+    /// remembered while answering requests, so they are shared by all snapshots.
+    pub semantic_tokens: Arc<std::sync::Mutex<HashMap<String, SemanticTokens>>>,
+
+    /// Which inlay hints the client wants to see
+    pub inlay_hint_options: InlayHintOptions,
+
+    /// Severities of the diagnostics of the static analysis
+    pub diagnostic_options: DiagnosticOptions,
 }
 
 /// Represents the backend of the language server.
@@ -99,13 +200,38 @@ pub struct Backend {
     client: Client,
 
     /// The global state of the server
-    state: Arc<Mutex<BackendState>>,
+    state: Arc<SharedState>,
 
     /// Path to stubs
     stubs: String,
 
     /// Patterns of folders to be ignored during indexing
     ignore_patterns: Vec<PathBuf>,
+
+    /// Also index the autoload-dev section of composer.json
+    autoload_dev: bool,
+
+    /// Directory of the symbol cache, if not the default one
+    cache_dir: Option<PathBuf>,
+}
+
+impl From<&Notification> for Diagnostic {
+    fn from(notification: &Notification) -> Diagnostic {
+        Diagnostic {
+            range: get_range(notification.range),
+            message: notification.message.clone(),
+            severity: Some(notification.severity),
+            tags: if notification.tags.is_empty() {
+                None
+            } else {
+                Some(notification.tags.clone())
+            },
+            code: notification
+                .code
+                .map(|code| NumberOrString::String(code.to_owned())),
+            ..Diagnostic::default()
+        }
+    }
 }
 
 impl From<&ParserError> for Diagnostic {
@@ -177,12 +303,23 @@ impl From<&Token> for Symbol {
 }
 
 impl Backend {
-    pub fn new(client: Client, stubs: String, ignore_patterns: Vec<String>) -> Self {
+    pub fn new(
+        client: Client,
+        stubs: String,
+        ignore_patterns: Vec<String>,
+        autoload_dev: bool,
+        cache_dir: Option<String>,
+    ) -> Self {
         Backend {
             client,
-            state: Arc::new(Mutex::new(BackendState::default())),
+            state: Arc::new(SharedState::new(BackendState {
+                stubs: Some(PathBuf::from(&stubs)),
+                ..BackendState::default()
+            })),
             stubs,
             ignore_patterns: ignore_patterns.iter().map(PathBuf::from).collect(),
+            autoload_dev,
+            cache_dir: cache_dir.map(PathBuf::from),
         }
     }
 
@@ -218,96 +355,246 @@ impl Backend {
         None
     }
 
-    async fn references_of_symbol_under_cursor(&self, nuc: &str) -> Option<ReferenceMapMutex> {
-        let all_files = self
-            .state
-            .lock()
-            .await
-            .files
-            .iter()
-            .map(|(k, _)| k.clone())
-            .collect::<Vec<String>>();
-        let symbol_references = Arc::new(Mutex::new(ReferenceMap::new()));
-
-        let mut joins = Vec::new();
-        for p in all_files {
-            let state = self.state.clone();
-            let symbol_references = symbol_references.clone();
-            let nuc = nuc.to_owned();
-
-            joins.push(task::spawn(async move {
-                let content = match tokio::fs::read_to_string(&p).await {
-                    Ok(content) => content,
-                    Err(error) => {
-                        eprintln!("{}", error);
-                        return;
-                    }
-                };
+    /// Index the references of the files whose references were not restored from the cache, as
+    /// long as they are not part of the stubs. This runs in the background once the server is
+    /// initialized, requests bring the index up to date themselves in the meantime.
+    async fn index_references(state: Arc<SharedState>) {
+        // Requests might index some of the files first, they still need to be cached
+        let (mut stale, cache) = state
+            .update(|state| {
+                reference_index::mark_mentioning_files(state);
+                let uncached = std::mem::take(&mut state.uncached_references);
+
+                (
+                    state
+                        .stale_references
+                        .iter()
+                        .chain(uncached.iter())
+                        .filter(|file| {
+                            state.files.contains_key(*file)
+                                && !state.opened_files.contains_key(*file)
+                        })
+                        .cloned()
+                        .collect::<HashSet<String>>()
+                        .into_iter()
+                        .collect::<Vec<String>>(),
+                    state.cache.clone(),
+                )
+            })
+            .await;
+        stale.sort();
+
+        // Files are parsed side by side and indexed in batches, each published as one snapshot
+        for batch in stale.chunks(INDEX_BATCH_SIZE) {
+            let joins = batch
+                .iter()
+                .cloned()
+                .map(|p| {
+                    task::spawn(async move {
+                        let content = match tokio::fs::read_to_string(&p).await {
+                            Ok(content) => content,
+                            Err(error) => {
+                                eprintln!("{}", error);
+                                return None;
+                            }
+                        };
 
-                // Full text search ....
-                if !content.contains(&nuc) {
-                    return;
-                }
+                        if let Ok((ast, _, _)) = Backend::source_to_ast(&content) {
+                            Some((p, content, ast))
+                        } else {
+                            eprintln!("Could not index {} due to syntax errors", p);
 
-                let mut state = state.lock().await;
-
-                if let Ok((ast, _, _)) = Backend::source_to_ast(&content) {
-                    let mut symbol_references = symbol_references.lock().await;
-                    let reindex_result = Backend::collect_references(
-                        &p,
-                        &ast,
-                        &mut state,
-                        Some(&mut symbol_references),
-                    );
-                    match reindex_result {
-                        Ok(()) => (),
-                        Err(err) => {
-                            eprintln!("{}", err);
+                            None
                         }
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            let mut parsed = Vec::new();
+            for j in joins {
+                match j.await {
+                    Ok(result) => parsed.extend(result),
+                    Err(err) => {
+                        eprintln!("{}", err);
                     }
-                } else {
-                    // TODO: Publish errors as diagnostics
-                    eprintln!("Could not index {} due to syntax errors", p);
                 }
-            }));
+            }
+
+            let entries = state
+                .update(|state| {
+                    let mut entries = Vec::new();
+
+                    for (p, content, ast) in parsed.iter() {
+                        if !state.files.contains_key(p) {
+                            continue;
+                        }
+
+                        // Requests might have indexed the file in the meantime
+                        if state.stale_references.remove(p) {
+                            reference_index::index_file(state, p, ast);
+                        }
+
+                        if cache.is_some() {
+                            entries.extend(CachedReferences::new(Path::new(p), content, state));
+                        }
+                    }
+
+                    entries
+                })
+                .await;
+
+            if let Some(cache) = cache.clone() {
+                task::spawn_blocking(move || {
+                    for entry in entries {
+                        if let Err(err) = cache.store_references(&entry) {
+                            eprintln!("Could not cache references of {}: {}", entry.path, err);
+                        }
+                    }
+                });
+            }
         }
 
-        for j in joins {
-            match j.await {
-                Ok(()) => (),
-                Err(err) => {
-                    eprintln!("{}", err);
+        // What is left, like the opened files, is brought up to date right away
+        let indexed = state
+            .update(|state| {
+                reference_index::refresh(state);
+
+                state.symbol_references.len()
+            })
+            .await;
+        eprintln!("Indexed references of {} files", indexed);
+    }
+
+    /// Bring the reference index up to date, only waiting for the other writers if it is outdated
+    async fn refresh_references(&self) {
+        let state = self.state.get();
+        if state.stale_references.is_empty() && state.new_declarations.is_empty() {
+            return;
+        }
+        drop(state);
+
+        self.state.update(reference_index::refresh).await;
+    }
+
+    /// Answer a request on the latest snapshot of the state, away from the tasks of the async
+    /// runtime. If the document the request is about was edited before the work starts, the
+    /// answer would be outdated and the request is cancelled, so the client can ask again.
+    async fn read<R, F>(&self, uri: Option<&Url>, f: F) -> Result<R>
+    where
+        F: FnOnce(&BackendState) -> Result<R> + Send + 'static,
+        R: Send + 'static,
+    {
+        let snapshot = self.state.get();
+        let file = uri
+            .and_then(|uri| uri.to_file_path().ok())
+            .map(|path| EnvFs::normalize_path(&path));
+        let version = file
+            .as_ref()
+            .and_then(|file| snapshot.document_versions.get(file).copied());
+
+        let state = self.state.clone();
+        task::spawn_blocking(move || {
+            if let Some(file) = file {
+                if state.get().document_versions.get(&file).copied() != version {
+                    return Err(Error::content_modified());
                 }
             }
-        }
 
-        Some(symbol_references)
+            f(&snapshot)
+        })
+        .await
+        .map_err(|e| {
+            eprintln!("Request failed: {}", e);
+
+            Error::internal_error()
+        })?
     }
 
     async fn init_workspace(&self, url: &Url) -> io::Result<()> {
         let root_path = url.to_file_path().unwrap();
-        let (tx, rx) = channel::bounded::<ParseResult>(1000);
+        let (tx, rx) = channel::bounded::<IndexResult>(1000);
+
+        // Composer projects only need what is autoloadable, everything else is never used
+        let composer = Composer::load(&root_path, self.autoload_dev);
+        let mut roots = if let Some(composer) = composer.as_ref() {
+            composer
+                .autoloadable_paths()
+                .drain(..)
+                .filter(|path| path.exists())
+                .collect()
+        } else {
+            vec![root_path.clone()]
+        };
+        roots.push(PathBuf::from(&self.stubs));
+        self.state.update(|state| state.composer = composer).await;
+
+        // Unchanged files are restored from the cache instead of being parsed again
+        let cache = self
+            .cache_dir
+            .clone()
+            .or_else(|| IndexCache::default_dir(&root_path))
+            .and_then(|dir| match IndexCache::open(&dir, &self.stubs) {
+                Ok(cache) => Some(cache),
+                Err(e) => {
+                    eprintln!("Could not open symbol cache {:?}: {}", dir, e);
+
+                    None
+                }
+            });
 
         let state = self.state.clone();
+        let receiver_cache = cache.clone();
 
         let handle = Handle::current();
         let mt = std::thread::spawn(move || {
             handle.spawn(async move {
-                for (p, ast, range, errors) in rx {
-                    let mut state = state.lock().await;
-
-                    let reindex_result = Backend::collect_symbols(&p, &ast, &range, &mut state);
+                let mut entries = Vec::new();
+                let mut references = Vec::new();
+                let mut parsed = Vec::new();
+
+                // Files are added in batches, so a snapshot is published for many files at once
+                while let Ok(first) = rx.recv() {
+                    let batch = std::iter::once(first)
+                        .chain(rx.try_iter().take(INDEX_BATCH_SIZE - 1))
+                        .collect::<Vec<IndexResult>>();
+                    parsed.extend(batch.iter().filter_map(|result| match result {
+                        IndexResult::Parsed((p, ..), _) => Some(p.clone()),
+                        IndexResult::Cached(..) => None,
+                    }));
+
+                    let added = state
+                        .update(|state| {
+                            batch
+                                .into_iter()
+                                .map(|result| {
+                                    Backend::add_indexed_file(
+                                        state,
+                                        result,
+                                        receiver_cache.is_some(),
+                                    )
+                                })
+                                .collect::<Vec<_>>()
+                        })
+                        .await;
 
-                    match reindex_result {
-                        Ok(()) => (),
-                        Err(err) => {
-                            eprintln!("{}", err);
-                        }
+                    for (entry, cached_references) in added {
+                        entries.extend(entry);
+                        references.extend(cached_references);
                     }
+                }
 
-                    let diags = errors.iter().map(Diagnostic::from).collect();
-                    state.diagnostics.insert(p, diags);
+                // Writing the cache does not need the state, so it does not hold up the writers
+                if let Some(cache) = receiver_cache {
+                    task::spawn_blocking(move || {
+                        for entry in entries {
+                            if let Err(err) = cache.store(&entry) {
+                                eprintln!("Could not cache symbols of {}: {}", entry.path, err);
+                            }
+                        }
+                    });
                 }
+
+                (references, parsed)
             })
         });
 
@@ -315,15 +602,20 @@ impl Backend {
         type_builder.add_def("php:*.php").unwrap();
         let types = type_builder.select("php").build().unwrap();
 
-        let walker = WalkBuilder::new(PathBuf::from(&root_path))
+        let mut walk_builder = WalkBuilder::new(&roots[0]);
+        for root in roots.iter().skip(1) {
+            walk_builder.add(root);
+        }
+
+        let walker = walk_builder
             .standard_filters(false)
-            .add(PathBuf::from(&self.stubs))
             .types(types)
             .threads(6)
             .build_parallel();
 
         walker.run(|| {
             let tx = tx.clone();
+            let cache = cache.clone();
             Box::new(move |result| {
                 use ignore::WalkState::Continue;
 
@@ -339,10 +631,19 @@ impl Backend {
                             }
                         };
 
-                        if let Ok((ast, range, errors)) = Backend::source_to_ast(&content) {
-                            if let Err(e) =
-                                tx.send((EnvFs::normalize_path(&path), ast, range, errors))
-                            {
+                        if let Some(cached) = cache.as_ref().and_then(|c| c.load(&path, &content)) {
+                            let references = cache
+                                .as_ref()
+                                .and_then(|c| c.load_references(&path, &content));
+
+                            if let Err(e) = tx.send(IndexResult::Cached(cached, references)) {
+                                eprintln!("{:?}", e);
+                            };
+                        } else if let Ok((ast, range, errors)) = Backend::source_to_ast(&content) {
+                            if let Err(e) = tx.send(IndexResult::Parsed(
+                                (EnvFs::normalize_path(&path), ast, range, errors),
+                                content,
+                            )) {
                                 eprintln!("{:?}", e);
                             };
                         }
@@ -359,31 +660,164 @@ impl Backend {
 
         drop(tx);
 
-        mt.join().unwrap().await?;
+        let (references, parsed) = mt.join().unwrap().await?;
 
-        // This is in a block to release the locks asap
-        {
-            let mut state = self.state.lock().await;
-            eprintln!("Indexed {} files ", state.files.len());
-            let mut global_table: HashMap<String, NodeId> = HashMap::new();
-            for (_file, node_id) in state.files.iter() {
-                for symbol_id in node_id.children(&state.arena) {
-                    let symbol = state.arena[symbol_id].get();
+        self.state
+            .update(|state| {
+                eprintln!("Indexed {} files ", state.files.len());
+                let mut global_table: HashMap<String, NodeId> = HashMap::new();
+                for (_file, node_id) in state.files.iter() {
+                    for symbol_id in node_id.children(&state.arena) {
+                        let symbol = state.arena[symbol_id].get();
 
-                    if symbol.kind.register_global() {
-                        global_table.insert(symbol.fqdn().to_lowercase(), symbol_id);
+                        if symbol.kind.register_global() {
+                            global_table.insert(symbol.fqdn().to_lowercase(), symbol_id);
+                        }
                     }
                 }
-            }
 
-            state.global_symbols = global_table;
-        }
+                state.global_symbols = global_table.into();
+
+                // References of unchanged files are restored, they only resolve differently if
+                // they mention names declared by the files that were parsed again
+                for cached in references {
+                    cached.restore(state);
+                }
+
+                let parsed_files = parsed
+                    .iter()
+                    .filter_map(|p| state.files.get(p).copied())
+                    .collect::<Vec<NodeId>>();
+                state.new_declarations = parsed_files
+                    .into_iter()
+                    .flat_map(|file| reference_index::declared_names(state, file))
+                    .collect();
+
+                // The references of all other files are indexed in the background
+                let unindexed = state
+                    .files
+                    .keys()
+                    .filter(|file| {
+                        reference_index::is_indexed(state, file)
+                            && !state.symbol_references.contains_key(*file)
+                    })
+                    .cloned()
+                    .collect::<Vec<String>>();
+                if cache.is_some() {
+                    state.uncached_references = unindexed.iter().cloned().collect();
+                }
+                state.stale_references.extend(unindexed);
+                state.cache = cache;
+            })
+            .await;
 
         eprintln!("Done doing the stuff");
 
         Ok(())
     }
 
+    /// Add a file indexed during the initialization to the state. Returns the entry to cache if
+    /// the file was parsed and `cache` is set, and the cached references of an unchanged file,
+    /// which can only be restored once the symbols of all files are known.
+    fn add_indexed_file(
+        state: &mut BackendState,
+        result: IndexResult,
+        cache: bool,
+    ) -> (Option<CachedFile>, Option<CachedReferences>) {
+        let (p, entry, references) = match result {
+            IndexResult::Cached(cached, references) => (cached.restore(state), None, references),
+            IndexResult::Parsed((p, ast, range, errors), content) => {
+                let reindex_result = Backend::collect_symbols(&p, &ast, &range, state);
+
+                match reindex_result {
+                    Ok(()) => (),
+                    Err(err) => {
+                        eprintln!("{}", err);
+                    }
+                }
+
+                let mut diags: Vec<Diagnostic> = errors.iter().map(Diagnostic::from).collect();
+                diags.extend(document_link::include_diagnostics(&ast, &p));
+                state.diagnostics.insert(p.clone(), diags.into());
+
+                let entry = if cache {
+                    CachedFile::new(Path::new(&p), &content, state)
+                } else {
+                    None
+                };
+
+                (p, entry, None)
+            }
+        };
+
+        // Autoload rules can change independently of the file, so they are never cached
+        let autoload_diagnostics = Backend::autoload_diagnostics(state, &p);
+        state
+            .diagnostics
+            .entry(p)
+            .or_default()
+            .extend(autoload_diagnostics);
+
+        (entry, references)
+    }
+
+    /// Warn about classes, interfaces, traits and enums whose FQDN does not match the PSR-4
+    /// path of the file they are declared in
+    fn autoload_diagnostics(state: &BackendState, path: &str) -> Vec<Diagnostic> {
+        let composer = if let Some(composer) = state.composer.as_ref() {
+            composer
+        } else {
+            return Vec::new();
+        };
+
+        let file = if let Some(file) = state.files.get(path) {
+            *file
+        } else {
+            return Vec::new();
+        };
+
+        let expected_fqdn = if let Some(expected_fqdn) = composer.expected_fqdn(Path::new(path)) {
+            expected_fqdn
+        } else {
+            return Vec::new();
+        };
+
+        file.children(&state.arena)
+            .filter_map(|child| {
+                let symbol = state.arena[child].get();
+
+                if !matches!(
+                    symbol.kind,
+                    PhpSymbolKind::Class
+                        | PhpSymbolKind::Interface
+                        | PhpSymbolKind::Trait
+                        | PhpSymbolKind::Enum
+                ) {
+                    return None;
+                }
+
+                let fqdn = symbol.fqdn();
+                if composer
+                    .expected_files(&fqdn)
+                    .iter()
+                    .any(|file| file == Path::new(path))
+                {
+                    return None;
+                }
+
+                Some(Diagnostic {
+                    range: symbol.selection_range,
+                    message: format!(
+                        "{} does not match its PSR-4 path, expected {}",
+                        fqdn, expected_fqdn
+                    ),
+                    severity: Some(DiagnosticSeverity::WARNING),
+                    ..Diagnostic::default()
+                })
+            })
+            .collect()
+    }
+
     /// Index a source string to an ast
     pub fn source_to_ast(
         source: &str,
@@ -422,27 +856,42 @@ impl Backend {
             traverse(node, &mut visitor, &mut state.arena, enclosing_file);
         }
 
+        Backend::register_file(path, enclosing_file, state);
+
+        Ok(())
+    }
+
+    /// Register a file and its top level symbols, replacing a previous version of the file
+    pub(crate) fn register_file(path: &str, enclosing_file: NodeId, state: &mut BackendState) {
+        let mut declared_before = HashSet::new();
+
         // Deregister old children from the global symbol table and the references
         if let Some(old_enclosing) = state.files.insert(path.to_owned(), enclosing_file) {
+            declared_before = reference_index::declared_names(state, old_enclosing);
+
             // Since only the top level symbols are in the global_symbols its okay
             // to shallowy travers the tree
-            for symbol_id in old_enclosing.children(&state.arena) {
+            let old_children = old_enclosing
+                .children(&state.arena)
+                .collect::<Vec<NodeId>>();
+            for symbol_id in old_children {
+                type_hierarchy::deregister_inheritor(state, symbol_id);
+
                 let symbol = state.arena[symbol_id].get();
 
                 state.global_symbols.remove(&symbol.fqdn().to_lowercase());
             }
 
-            // But references are a different story
-            for symbol_id in old_enclosing.descendants(&state.arena) {
-                for (_file, references_of_file) in state.symbol_references.iter_mut() {
-                    references_of_file.remove(&symbol_id);
-                }
-            }
+            // But references are a different story, they are moved over to the new symbols
+            reference_index::renew_symbols(state, path, old_enclosing, enclosing_file);
 
             old_enclosing.remove_subtree(&mut state.arena);
         }
 
-        for symbol_id in enclosing_file.children(&state.arena) {
+        let children = enclosing_file
+            .children(&state.arena)
+            .collect::<Vec<NodeId>>();
+        for symbol_id in children {
             let symbol = state.arena[symbol_id].get();
 
             if symbol.kind.register_global() {
@@ -450,9 +899,14 @@ impl Backend {
                     .global_symbols
                     .insert(symbol.fqdn().to_lowercase(), symbol_id);
             }
+
+            type_hierarchy::register_inheritor(state, symbol_id);
         }
 
-        Ok(())
+        let declared_now = reference_index::declared_names(state, enclosing_file);
+        state
+            .new_declarations
+            .extend(declared_now.difference(&declared_before).cloned());
     }
 
     /// Collect all references withing a given ast
@@ -492,17 +946,13 @@ impl Backend {
             traverse(node, &mut visitor, &mut state.arena, enclosing_file);
         }
 
-        for notification in visitor.diagnostics() {
+        // Files indexed in the background report their diagnostics when they are opened
+        for notification in visitor.diagnostics().filter(|_| container.is_none()) {
             state
                 .diagnostics
                 .entry(notification.file.clone())
-                .or_insert_with(Vec::new)
-                .push(Diagnostic {
-                    range: get_range(notification.range),
-                    message: notification.message.clone(),
-                    severity: Some(notification.severity),
-                    ..Diagnostic::default()
-                })
+                .or_default()
+                .push(notification.into())
         }
 
         // Extract all references of the current file only
@@ -520,39 +970,123 @@ impl Backend {
             });
 
         if let Some(container) = container {
-            container.insert(path.to_owned(), map);
+            container.insert(path.to_owned(), map.into());
         } else {
-            state.symbol_references.insert(path.to_owned(), map);
+            let inferred_types = visitor.inferred_types();
+
+            let mut notifications = ArgumentChecker::new(
+                &state.arena,
+                &state.global_symbols,
+                enclosing_file,
+                &map,
+                &inferred_types,
+                &state.diagnostic_options,
+            )
+            .check(ast);
+            notifications.extend(
+                VariableChecker::new(
+                    &state.arena,
+                    &state.global_symbols,
+                    enclosing_file,
+                    &map,
+                    &state.diagnostic_options,
+                )
+                .check(ast),
+            );
+            notifications.extend(
+                InheritanceChecker::new(
+                    &state.arena,
+                    &state.global_symbols,
+                    enclosing_file,
+                    &state.diagnostic_options,
+                    state.stubs.as_deref(),
+                )
+                .check(),
+            );
+
+            for notification in notifications.iter() {
+                state
+                    .diagnostics
+                    .entry(notification.file.clone())
+                    .or_default()
+                    .push(notification.into())
+            }
+
+            reference_index::record(state, path, map.into(), ast);
+            state
+                .inferred_types
+                .insert(path.to_owned(), inferred_types.into());
         }
 
         Ok(())
     }
 
-    pub(crate) fn refresh_file(state: &mut BackendState, uri: Url, src: &str) {
+    /// Parse a file and resolve its own references
+    pub(crate) fn analyze_file(state: &mut BackendState, uri: &Url, src: &str) {
         let file_path = uri.to_file_path().unwrap();
         let path = EnvFs::normalize_path(&file_path);
 
-        if let Ok((ast, range, errors)) = Backend::source_to_ast(&src) {
-            let reindex_result = Backend::collect_symbols(&path, &ast, &range, state);
+        let (ast, range, errors) = match Backend::source_to_ast(src) {
+            Ok(result) => result,
+            Err(_) => return,
+        };
 
-            let diagnostics = state
-                .diagnostics
-                .entry(path.to_string())
-                .or_insert_with(Vec::new);
-            diagnostics.clear();
-            diagnostics.extend(errors.iter().map(Diagnostic::from));
+        let reindex_result = Backend::collect_symbols(&path, &ast, &range, state);
 
-            state.opened_files.insert(path, (ast, range));
+        let diagnostics = state.diagnostics.entry(path.to_string()).or_default();
+        diagnostics.clear();
+        diagnostics.extend(errors.iter().map(Diagnostic::from));
+        diagnostics.extend(document_link::include_diagnostics(&ast, &path));
 
-            if reindex_result.is_err() {
-                return;
+        let autoload_diagnostics = Backend::autoload_diagnostics(state, &path);
+        state
+            .diagnostics
+            .entry(path.to_string())
+            .or_default()
+            .extend(autoload_diagnostics);
+
+        if reindex_result.is_ok() {
+            if let Err(e) = Backend::collect_references(&path, &ast, state, None) {
+                eprintln!("Error updating references of {}: {}", path, e);
             }
         }
 
-        // Now reindex all the other currently opened files to update references
+        state.opened_files.insert(path, (ast.into(), range));
+    }
+
+    /// Analyze all opened files again and return their diagnostics along with their versions
+    pub(crate) fn reanalyze_opened_files(
+        state: &mut BackendState,
+    ) -> Vec<(Url, Vec<Diagnostic>, Option<i32>)> {
+        let mut files = state.opened_files.keys().cloned().collect::<Vec<String>>();
+        files.sort();
+
+        files
+            .into_iter()
+            .filter_map(|file| {
+                let source = state.latest_version_of_file.get(&file)?.clone();
+                let uri = Url::from_file_path(&file).ok()?;
+
+                Backend::analyze_file(state, &uri, &source);
+
+                Some((
+                    uri,
+                    state
+                        .diagnostics
+                        .get(&file)
+                        .map(|d| d.to_vec())
+                        .unwrap_or_default(),
+                    state.document_versions.get(&file).copied(),
+                ))
+            })
+            .collect()
+    }
+
+    /// Reindex all the other currently opened files to update their references to a changed file
+    pub(crate) fn refresh_opened_files(state: &mut BackendState, changed: &str) {
         let tasks = state.opened_files.clone();
-        for (path, (ast, _)) in tasks {
-            if let Err(e) = Backend::collect_references(&path, &ast, state, None) {
+        for (path, (ast, _)) in tasks.iter().filter(|(path, _)| *path != changed) {
+            if let Err(e) = Backend::collect_references(path, ast, state, None) {
                 eprintln!("Error updating opened file after save of another: {}", e);
             }
         }
@@ -562,6 +1096,15 @@ impl Backend {
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
     async fn initialize(&self, params: InitializeParams) -> Result<InitializeResult> {
+        self.state
+            .update(|state| {
+                state.inlay_hint_options =
+                    InlayHintOptions::from_settings(params.initialization_options.as_ref());
+                state.diagnostic_options =
+                    DiagnosticOptions::from_settings(params.initialization_options.as_ref());
+            })
+            .await;
+
         if let Some(url) = params.root_uri {
             match self.init_workspace(&url).await {
                 Ok(()) => {
@@ -587,7 +1130,7 @@ impl LanguageServer for Backend {
             server_info: None,
             capabilities: ServerCapabilities {
                 text_document_sync: Some(TextDocumentSyncCapability::Kind(
-                    TextDocumentSyncKind::FULL,
+                    TextDocumentSyncKind::INCREMENTAL,
                 )),
                 hover_provider: Some(HoverProviderCapability::Simple(true)),
                 completion_provider: Some(CompletionOptions {
@@ -595,12 +1138,17 @@ impl LanguageServer for Backend {
                     resolve_provider: Some(true),
                     trigger_characters: Some(trigger_characters),
                     work_done_progress_options: Default::default(),
+                    completion_item: None,
                 }),
-                /*signature_help_provider: Some(SignatureHelpOptions {
-                    trigger_characters: None,
+                signature_help_provider: Some(SignatureHelpOptions {
+                    trigger_characters: Some(vec![String::from("("), String::from(",")]),
                     retrigger_characters: None,
                     work_done_progress_options: Default::default(),
-                }),*/
+                }),
+                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                code_lens_provider: Some(CodeLensOptions {
+                    resolve_provider: Some(true),
+                }),
                 implementation_provider: Some(ImplementationProviderCapability::Simple(true)),
                 references_provider: Some(OneOf::Right(ReferencesOptions {
                     work_done_progress_options: Default::default(),
@@ -621,12 +1169,31 @@ impl LanguageServer for Backend {
                 })),
                 workspace_symbol_provider: Some(OneOf::Right(WorkspaceSymbolOptions {
                     work_done_progress_options: Default::default(),
+                    resolve_provider: None,
                 })),
                 execute_command_provider: Some(ExecuteCommandOptions {
                     commands: vec!["dummy.do_something".to_string()],
                     work_done_progress_options: Default::default(),
                 }),
                 document_formatting_provider: Some(OneOf::Left(false)),
+                inlay_hint_provider: Some(OneOf::Left(true)),
+                folding_range_provider: Some(FoldingRangeProviderCapability::Simple(true)),
+                selection_range_provider: Some(SelectionRangeProviderCapability::Simple(true)),
+                document_link_provider: Some(DocumentLinkOptions {
+                    resolve_provider: Some(false),
+                    work_done_progress_options: Default::default(),
+                }),
+                call_hierarchy_provider: Some(CallHierarchyServerCapability::Simple(true)),
+                semantic_tokens_provider: Some(
+                    SemanticTokensServerCapabilities::SemanticTokensOptions(
+                        SemanticTokensOptions {
+                            work_done_progress_options: Default::default(),
+                            legend: semantic_tokens::legend(),
+                            range: Some(true),
+                            full: Some(SemanticTokensFullOptions::Delta { delta: Some(true) }),
+                        },
+                    ),
+                ),
                 workspace: Some(WorkspaceServerCapabilities {
                     file_operations: None,
                     workspace_folders: Some(WorkspaceFoldersServerCapabilities {
@@ -640,9 +1207,24 @@ impl LanguageServer for Backend {
     }
 
     async fn initialized(&self, _params: InitializedParams) {
-        let mut state = self.state.lock().await;
+        // The capability is missing in the static server capabilities of lsp-types
+        let type_hierarchy = Registration {
+            id: String::from("typeHierarchy"),
+            method: String::from("textDocument/prepareTypeHierarchy"),
+            register_options: None,
+        };
+        if let Err(e) = self.client.register_capability(vec![type_hierarchy]).await {
+            eprintln!("Could not register the type hierarchy: {}", e);
+        }
+
+        task::spawn(Backend::index_references(self.state.clone()));
+
+        let diagnostics = self
+            .state
+            .update(|state| std::mem::take(&mut state.diagnostics))
+            .await;
 
-        for (file, diagnostics) in state.diagnostics.iter() {
+        for (file, diagnostics) in diagnostics.iter() {
             if
             /*file.contains("/vendor/")
             || file.contains("/phpstorm-stubs/")
@@ -654,218 +1236,160 @@ impl LanguageServer for Backend {
             self.client
                 .publish_diagnostics(
                     Url::from_file_path(file).unwrap(),
-                    diagnostics.clone(),
+                    diagnostics.to_vec(),
                     None,
                 )
                 .await;
         }
-
-        state.diagnostics.clear();
     }
 
     async fn symbol(
         &self,
         params: WorkspaceSymbolParams,
     ) -> Result<Option<Vec<SymbolInformation>>> {
-        let state = self.state.lock().await;
-
-        symbol::symbol(&state, params)
+        self.read(None, move |state| symbol::symbol(state, params))
+            .await
     }
 
     async fn document_symbol(
         &self,
         params: DocumentSymbolParams,
     ) -> Result<Option<DocumentSymbolResponse>> {
-        let state = self.state.lock().await;
+        let uri = params.text_document.uri.clone();
 
-        document_symbol::document_symbol(&state, params)
+        self.read(Some(&uri), move |state| {
+            document_symbol::document_symbol(state, params)
+        })
+        .await
     }
 
     async fn document_highlight(
         &self,
         params: DocumentHighlightParams,
     ) -> Result<Option<Vec<DocumentHighlight>>> {
-        let state = self.state.lock().await;
-
-        document_highlight::document_highlight(&state, params)
+        let uri = params
+            .text_document_position_params
+            .text_document
+            .uri
+            .clone();
+
+        self.read(Some(&uri), move |state| {
+            document_highlight::document_highlight(state, params)
+        })
+        .await
     }
 
     async fn goto_implementation(
         &self,
         params: GotoImplementationParams,
     ) -> Result<Option<GotoImplementationResponse>> {
-        let mut state = self.state.lock().await;
-
-        goto_implementation::goto_implementation(&mut state, params)
+        let uri = params
+            .text_document_position_params
+            .text_document
+            .uri
+            .clone();
+
+        self.read(Some(&uri), move |state| {
+            goto_implementation::goto_implementation(state, params)
+        })
+        .await
     }
 
     async fn references(&self, params: ReferenceParams) -> Result<Option<Vec<Location>>> {
-        let position = &params.text_document_position.position;
-        let file = EnvFs::normalize_path(
-            &params
-                .text_document_position
-                .text_document
-                .uri
-                .to_file_path()
-                .unwrap(),
-        );
+        let uri = params.text_document_position.text_document.uri.clone();
+        self.refresh_references().await;
 
-        let (suc, nuc) = {
-            let state = self.state.lock().await;
-            if let Some((suc, nuc)) = Backend::symbol_under_cursor(&state, position, &file) {
-                (suc, nuc)
-            } else {
-                return Ok(None);
-            }
-        };
-
-        eprintln!("Finding refs for {:?} {}", suc, nuc);
-
-        let symbol_references =
-            if let Some(symbol_references) = self.references_of_symbol_under_cursor(&nuc).await {
-                symbol_references
-            } else {
-                return Ok(None);
-            };
-
-        let locations = symbol_references
-            .lock()
-            .await
-            .iter()
-            .filter_map(|(file, refs)| {
-                // Find all refs that point to our symbol, across all files
-                refs.get(&suc).map(|ranges| {
-                    ranges
-                        .iter()
-                        .map(|range| Location {
-                            uri: Url::from_file_path(file).unwrap(),
-                            range: get_range(*range),
-                        })
-                        .collect::<Vec<Location>>()
-                })
-            })
-            .fold(Vec::new(), |cur, mut tot: Vec<Location>| {
-                tot.extend(cur);
-
-                tot
-            });
-
-        Ok(Some(locations))
+        self.read(Some(&uri), move |state| {
+            references::references(state, params)
+        })
+        .await
     }
 
     async fn rename(&self, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
-        let position = &params.text_document_position.position;
-        let file = EnvFs::normalize_path(
-            &params
-                .text_document_position
-                .text_document
-                .uri
-                .to_file_path()
-                .unwrap(),
-        );
-        let nuc = {
-            let state = self.state.lock().await;
-            if let Some(references) = &state.symbol_references.get(&file) {
-                if let Some(nuc) = Backend::referenced_symbol_under_cursor(position, &references) {
-                    nuc.clone()
-                } else {
-                    return Ok(None);
-                }
-            } else {
-                return Ok(None);
-            }
-        };
-
-        let name = self.state.lock().await.arena[nuc].get().name.clone();
-
-        let symbol_references =
-            if let Some(symbol_references) = self.references_of_symbol_under_cursor(&name).await {
-                symbol_references
-            } else {
-                return Ok(None);
-            };
-
-        let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+        let uri = params.text_document_position.text_document.uri.clone();
+        self.refresh_references().await;
 
-        symbol_references
-            .lock()
+        self.read(Some(&uri), move |state| rename::rename(state, params))
             .await
-            .iter()
-            .for_each(|(file, refs)| {
-                let edits: Vec<TextEdit> = refs
-                    .iter()
-                    .filter_map(|(node, ranges)| {
-                        if node == &nuc {
-                            return Some(ranges.iter().map(|range| TextEdit {
-                                new_text: params.new_name.clone(),
-                                range: get_range(*range),
-                            }));
-                        }
-
-                        None
-                    })
-                    .flatten()
-                    .collect();
-                // Find all refs that point to our symbol, across all files
-                changes
-                    .entry(Url::from_file_path(file).unwrap())
-                    .or_insert_with(Vec::new)
-                    .extend(edits);
-            });
-
-        Ok(Some(WorkspaceEdit {
-            changes: Some(changes),
-            document_changes: None,
-            change_annotations: None,
-        }))
     }
 
     async fn goto_definition(
         &self,
         params: GotoDefinitionParams,
     ) -> Result<Option<GotoDefinitionResponse>> {
-        let state = self.state.lock().await;
-
-        goto_definition::goto_definition(&state, params)
+        let uri = params
+            .text_document_position_params
+            .text_document
+            .uri
+            .clone();
+
+        self.read(Some(&uri), move |state| {
+            goto_definition::goto_definition(state, params)
+        })
+        .await
     }
 
     async fn did_change_watched_files(&self, params: DidChangeWatchedFilesParams) {
-        let mut state = self.state.lock().await;
-
-        did_change_watched_files::did_change_watched_files(&mut state, params);
+        self.state
+            .update(|state| did_change_watched_files::did_change_watched_files(state, params))
+            .await;
     }
 
     async fn did_change(&self, params: DidChangeTextDocumentParams) {
-        let mut state = self.state.lock().await;
-        let file_path = params.text_document.uri.to_file_path().unwrap();
-        let path = EnvFs::normalize_path(&file_path);
+        self.state
+            .update(|state| did_change::did_change(state, &params))
+            .await;
+
+        // Only analyze the file and the other opened files once the user stopped typing for a moment
+        let state = self.state.clone();
+        let client = self.client.clone();
+        task::spawn(async move {
+            tokio::time::sleep(did_change::REANALYSIS_DELAY).await;
 
-        did_change::did_change(&mut state, &params);
+            let uri = params.text_document.uri;
+            let version = params.text_document.version;
+            let path = EnvFs::normalize_path(&uri.to_file_path().unwrap());
 
-        if let Some(diagnostics) = state.diagnostics.get(&path) {
-            self.client
-                .publish_diagnostics(params.text_document.uri, diagnostics.clone(), None)
+            // A newer edit is analyzed later on, so there is no need to take the write lock
+            if state.get().document_versions.get(&path) != Some(&version) {
+                return;
+            }
+
+            let diagnostics = state
+                .update(|state| did_change::reanalyze(state, uri.clone(), version))
                 .await;
-        }
+            if let Some(diagnostics) = diagnostics {
+                client
+                    .publish_diagnostics(uri, diagnostics, Some(version))
+                    .await;
+            }
+        });
     }
 
     async fn did_close(&self, params: DidCloseTextDocumentParams) {
-        let mut state = self.state.lock().await;
-        did_close::did_close(&mut state, params);
+        self.state
+            .update(|state| did_close::did_close(state, params))
+            .await;
     }
 
     async fn did_open(&self, params: DidOpenTextDocumentParams) {
-        let mut state = self.state.lock().await;
-
-        did_open::did_open(&mut state, &params);
-
         let file_path = params.text_document.uri.to_file_path().unwrap();
         let path = EnvFs::normalize_path(&file_path);
-        if let Some(diagnostics) = state.diagnostics.get(&path) {
+
+        let diagnostics = self
+            .state
+            .update(|state| {
+                did_open::did_open(state, &params);
+
+                state.diagnostics.get(&path).map(|d| d.to_vec())
+            })
+            .await;
+
+        if let Some(diagnostics) = diagnostics {
             self.client
                 .publish_diagnostics(
                     params.text_document.uri,
-                    diagnostics.clone(),
+                    diagnostics,
                     Some(params.text_document.version),
                 )
                 .await;
@@ -873,8 +1397,250 @@ impl LanguageServer for Backend {
     }
 
     async fn hover(&self, params: HoverParams) -> Result<Option<Hover>> {
-        let state = self.state.lock().await;
-        hover::hover(&state, params)
+        let uri = params
+            .text_document_position_params
+            .text_document
+            .uri
+            .clone();
+
+        self.read(Some(&uri), move |state| hover::hover(state, params))
+            .await
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> Result<Option<CodeActionResponse>> {
+        let uri = params.text_document.uri.clone();
+
+        self.read(Some(&uri), move |state| {
+            code_action::code_action(state, params)
+        })
+        .await
+    }
+
+    async fn signature_help(&self, params: SignatureHelpParams) -> Result<Option<SignatureHelp>> {
+        let uri = params
+            .text_document_position_params
+            .text_document
+            .uri
+            .clone();
+
+        self.read(Some(&uri), move |state| {
+            signature_help::signature_help(state, params)
+        })
+        .await
+    }
+
+    async fn semantic_tokens_full(
+        &self,
+        params: SemanticTokensParams,
+    ) -> Result<Option<SemanticTokensResult>> {
+        let uri = params.text_document.uri.clone();
+
+        self.read(Some(&uri), move |state| {
+            semantic_tokens::semantic_tokens_full(state, params)
+        })
+        .await
+    }
+
+    async fn semantic_tokens_full_delta(
+        &self,
+        params: SemanticTokensDeltaParams,
+    ) -> Result<Option<SemanticTokensFullDeltaResult>> {
+        let uri = params.text_document.uri.clone();
+
+        self.read(Some(&uri), move |state| {
+            semantic_tokens::semantic_tokens_full_delta(state, params)
+        })
+        .await
+    }
+
+    async fn semantic_tokens_range(
+        &self,
+        params: SemanticTokensRangeParams,
+    ) -> Result<Option<SemanticTokensRangeResult>> {
+        let uri = params.text_document.uri.clone();
+
+        self.read(Some(&uri), move |state| {
+            semantic_tokens::semantic_tokens_range(state, params)
+        })
+        .await
+    }
+
+    async fn prepare_call_hierarchy(
+        &self,
+        params: CallHierarchyPrepareParams,
+    ) -> Result<Option<Vec<CallHierarchyItem>>> {
+        let uri = params
+            .text_document_position_params
+            .text_document
+            .uri
+            .clone();
+
+        self.read(Some(&uri), move |state| {
+            call_hierarchy::prepare_call_hierarchy(state, params)
+        })
+        .await
+    }
+
+    async fn incoming_calls(
+        &self,
+        params: CallHierarchyIncomingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyIncomingCall>>> {
+        self.refresh_references().await;
+
+        self.read(None, move |state| {
+            let callable = match call_hierarchy::callable_of_item(state, &params.item) {
+                Some(callable) => callable,
+                None => return Ok(None),
+            };
+            let symbol_references = reference_index::references_of(state, callable);
+
+            Ok(call_hierarchy::incoming_calls(
+                state,
+                &params.item,
+                &symbol_references,
+            ))
+        })
+        .await
+    }
+
+    async fn outgoing_calls(
+        &self,
+        params: CallHierarchyOutgoingCallsParams,
+    ) -> Result<Option<Vec<CallHierarchyOutgoingCall>>> {
+        self.read(None, move |state| {
+            Ok(call_hierarchy::outgoing_calls(state, &params.item))
+        })
+        .await
+    }
+
+    async fn prepare_type_hierarchy(
+        &self,
+        params: TypeHierarchyPrepareParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        let uri = params
+            .text_document_position_params
+            .text_document
+            .uri
+            .clone();
+
+        self.read(Some(&uri), move |state| {
+            type_hierarchy::prepare_type_hierarchy(state, params)
+        })
+        .await
+    }
+
+    async fn supertypes(
+        &self,
+        params: TypeHierarchySupertypesParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        self.read(None, move |state| {
+            Ok(type_hierarchy::supertypes(state, &params.item))
+        })
+        .await
+    }
+
+    async fn subtypes(
+        &self,
+        params: TypeHierarchySubtypesParams,
+    ) -> Result<Option<Vec<TypeHierarchyItem>>> {
+        self.read(None, move |state| {
+            Ok(type_hierarchy::subtypes(state, &params.item))
+        })
+        .await
+    }
+
+    async fn folding_range(&self, params: FoldingRangeParams) -> Result<Option<Vec<FoldingRange>>> {
+        let uri = params.text_document.uri.clone();
+
+        self.read(Some(&uri), move |state| {
+            folding_range::folding_range(state, params)
+        })
+        .await
+    }
+
+    async fn selection_range(
+        &self,
+        params: SelectionRangeParams,
+    ) -> Result<Option<Vec<SelectionRange>>> {
+        let uri = params.text_document.uri.clone();
+
+        self.read(Some(&uri), move |state| {
+            selection_range::selection_range(state, params)
+        })
+        .await
+    }
+
+    async fn document_link(&self, params: DocumentLinkParams) -> Result<Option<Vec<DocumentLink>>> {
+        let uri = params.text_document.uri.clone();
+
+        self.read(Some(&uri), move |state| {
+            document_link::document_link(state, params)
+        })
+        .await
+    }
+
+    async fn code_lens(&self, params: CodeLensParams) -> Result<Option<Vec<CodeLens>>> {
+        let uri = params.text_document.uri.clone();
+
+        self.read(Some(&uri), move |state| code_lens::code_lens(state, params))
+            .await
+    }
+
+    async fn code_lens_resolve(&self, params: CodeLens) -> Result<CodeLens> {
+        if !code_lens::counts_implementations(&params) {
+            self.refresh_references().await;
+        }
+
+        self.read(None, move |state| {
+            let node = match code_lens::symbol_of_lens(state, &params) {
+                Some((node, _)) => node,
+                None => return Ok(params),
+            };
+
+            if code_lens::counts_implementations(&params) {
+                return Ok(code_lens::resolve_implementations(state, params, node));
+            }
+
+            let symbol_references = reference_index::references_of(state, node);
+
+            Ok(code_lens::resolve_references(
+                state,
+                params,
+                node,
+                &symbol_references,
+            ))
+        })
+        .await
+    }
+
+    async fn inlay_hint(&self, params: InlayHintParams) -> Result<Option<Vec<InlayHint>>> {
+        let uri = params.text_document.uri.clone();
+
+        self.read(Some(&uri), move |state| {
+            inlay_hint::inlay_hint(state, params)
+        })
+        .await
+    }
+
+    async fn did_change_configuration(&self, params: DidChangeConfigurationParams) {
+        let diagnostics = self
+            .state
+            .update(|state| {
+                state.inlay_hint_options =
+                    InlayHintOptions::from_settings(params.settings.get("phplsrs"));
+                state.diagnostic_options =
+                    DiagnosticOptions::from_settings(params.settings.get("phplsrs"));
+
+                // The severities might have changed
+                Backend::reanalyze_opened_files(state)
+            })
+            .await;
+
+        for (uri, diagnostics, version) in diagnostics {
+            self.client
+                .publish_diagnostics(uri, diagnostics, version)
+                .await;
+        }
     }
 
     async fn shutdown(&self) -> Result<()> {
@@ -882,8 +1648,12 @@ impl LanguageServer for Backend {
     }
 
     async fn completion(&self, params: CompletionParams) -> Result<Option<CompletionResponse>> {
-        let state = self.state.lock().await;
-        completion::completion(&state, params)
+        let uri = params.text_document_position.text_document.uri.clone();
+
+        self.read(Some(&uri), move |state| {
+            completion::completion(state, params)
+        })
+        .await
     }
 
     async fn completion_resolve(&self, params: CompletionItem) -> Result<CompletionItem> {
@@ -891,8 +1661,12 @@ impl LanguageServer for Backend {
     }
 
     async fn formatting(&self, params: DocumentFormattingParams) -> Result<Option<Vec<TextEdit>>> {
-        let state = self.state.lock().await;
-        formatting::formatting(&state, params)
+        let uri = params.text_document.uri.clone();
+
+        self.read(Some(&uri), move |state| {
+            formatting::formatting(state, params)
+        })
+        .await
     }
 }
 
@@ -922,6 +1696,62 @@ pub(crate) mod tests {
             Backend::collect_references(*file_name, &pr.0, state, None).unwrap();
         }
     }
+
+    #[test]
+    fn test_reanalyzes_opened_files_with_changed_severities() {
+        let mut state = BackendState::default();
+        let uri = Url::from_file_path("/index.php").unwrap();
+        let source = "<?php function a() { $unused = 1; }";
+        state
+            .latest_version_of_file
+            .insert(String::from("/index.php"), String::from(source).into());
+        Backend::analyze_file(&mut state, &uri, source);
+
+        assert_eq!(1, state.diagnostics["/index.php"].len());
+
+        state.diagnostic_options = DiagnosticOptions {
+            unused_variable: None,
+            ..DiagnosticOptions::default()
+        };
+        let diagnostics = Backend::reanalyze_opened_files(&mut state);
+
+        assert_eq!(vec![(uri, Vec::new(), None)], diagnostics);
+    }
+
+    #[test]
+    fn test_warns_about_classes_not_matching_their_psr4_path() {
+        let mut state = BackendState::default();
+        state.composer = Some(Composer::from_manifests(
+            Path::new("/project"),
+            &serde_json::json!({ "autoload": { "psr-4": { "App\\": "src/" } } }),
+            Path::new("/project/vendor"),
+            None,
+            false,
+        ));
+
+        populate_state(
+            &mut state,
+            &[
+                (
+                    "/project/src/Models/User.php",
+                    "<?php namespace App; class User {}",
+                ),
+                (
+                    "/project/src/Models/Post.php",
+                    "<?php namespace App\\Models; class Post {}",
+                ),
+            ],
+        );
+
+        assert_eq!(
+            vec!["App\\User does not match its PSR-4 path, expected App\\Models\\User".to_owned()],
+            Backend::autoload_diagnostics(&state, "/project/src/Models/User.php")
+                .drain(..)
+                .map(|d| d.message)
+                .collect::<Vec<String>>()
+        );
+        assert!(Backend::autoload_diagnostics(&state, "/project/src/Models/Post.php").is_empty());
+    }
 }
 
 /*
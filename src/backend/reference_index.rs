@@ -0,0 +1,522 @@
+use super::{Backend, BackendState, FileReferenceMap, ReferenceMap, Shared};
+use crate::environment::symbol::PhpSymbolKind;
+use crate::parser::node::{Node as AstNode, NodeRange};
+use indextree::NodeId;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// Files referencing symbols, by the key of the symbols. Unlike the ids of the symbols, the keys
+/// stay the same when the file declaring them is indexed anew.
+#[derive(Clone, Default)]
+pub struct ReferencingFiles {
+    by_symbol: HashMap<String, HashSet<String>>,
+    by_file: HashMap<String, HashSet<String>>,
+}
+
+impl ReferencingFiles {
+    /// Replace the keys of the symbols referenced by a file
+    fn insert(&mut self, file: &str, keys: HashSet<String>) {
+        self.remove(file);
+
+        for key in keys.iter() {
+            self.by_symbol
+                .entry(key.clone())
+                .or_default()
+                .insert(file.to_owned());
+        }
+
+        self.by_file.insert(file.to_owned(), keys);
+    }
+
+    fn remove(&mut self, file: &str) {
+        for key in self.by_file.remove(file).unwrap_or_default() {
+            if let Some(files) = self.by_symbol.get_mut(&key) {
+                files.remove(file);
+
+                if files.is_empty() {
+                    self.by_symbol.remove(&key);
+                }
+            }
+        }
+    }
+
+    fn files(&self, key: &str) -> impl Iterator<Item = &String> {
+        self.by_symbol.get(key).into_iter().flatten()
+    }
+}
+
+/// Collect the references of a file that is not opened into the reference index. Opened files
+/// are indexed by `Backend::collect_references` directly, as their inferred types are kept too.
+pub(crate) fn index_file(state: &mut BackendState, path: &str, ast: &[AstNode]) {
+    let mut container = ReferenceMap::new();
+
+    if let Err(e) = Backend::collect_references(path, ast, state, Some(&mut container)) {
+        eprintln!("Error indexing references of {}: {}", path, e);
+
+        return;
+    }
+
+    if let Some(references) = container.remove(path) {
+        record(state, path, references, ast);
+    }
+}
+
+/// Store the references of a file along with the keys of the symbols it references and the
+/// names it mentions
+pub(crate) fn record(
+    state: &mut BackendState,
+    path: &str,
+    references: Shared<FileReferenceMap>,
+    ast: &[AstNode],
+) {
+    let keys = references
+        .keys()
+        .filter_map(|node| symbol_key(state, *node))
+        .collect();
+
+    state.referencing_files.insert(path, keys);
+    state
+        .mentioned_names
+        .insert(path.to_owned(), mentioned_names(ast).into());
+    state.symbol_references.insert(path.to_owned(), references);
+}
+
+/// Put the references of a file restored from the cache back into the index, by the keys of the
+/// referenced symbols. If a symbol does not exist anymore, the file is marked as stale.
+pub(crate) fn restore(
+    state: &mut BackendState,
+    path: &str,
+    references: Vec<(String, Vec<NodeRange>)>,
+    mentioned_names: HashSet<String>,
+) {
+    let mut map = FileReferenceMap::new();
+    let mut keys = HashSet::new();
+
+    for (key, ranges) in references {
+        if let Some(node) = node_of_key(state, &key) {
+            map.entry(node).or_default().extend(ranges);
+            keys.insert(key);
+        } else {
+            state.stale_references.insert(path.to_owned());
+        }
+    }
+
+    state.referencing_files.insert(path, keys);
+    state
+        .mentioned_names
+        .insert(path.to_owned(), mentioned_names.into());
+    state.symbol_references.insert(path.to_owned(), map.into());
+}
+
+/// Remove the references of a file that was deleted or can not be parsed anymore
+fn forget(state: &mut BackendState, path: &str) {
+    state.referencing_files.remove(path);
+    state.mentioned_names.remove(path);
+    state.symbol_references.remove(path);
+}
+
+/// Tells if a file belongs to the workspace rather than to the stubs of the standard library,
+/// whose references are never indexed
+pub(crate) fn is_indexed(state: &BackendState, path: &str) -> bool {
+    state
+        .stubs
+        .as_ref()
+        .map(|stubs| !Path::new(path).starts_with(stubs))
+        .unwrap_or(true)
+}
+
+/// Names of all symbols declared in a file, variables excluded as they are local to the file
+pub(crate) fn declared_names(state: &BackendState, file: NodeId) -> HashSet<String> {
+    file.descendants(&state.arena)
+        .skip(1)
+        .map(|node| state.arena[node].get())
+        .filter(|symbol| symbol.kind != PhpSymbolKind::Variable)
+        .map(|symbol| symbol.normalized_name())
+        .collect()
+}
+
+/// Key of a symbol that does not change when its file is indexed anew. That is the fully
+/// qualified name of the top level symbol followed by the kinds and names of the nested ones.
+/// Variables have no key as they are local to their file.
+pub(crate) fn symbol_key(state: &BackendState, node: NodeId) -> Option<String> {
+    let mut key = String::new();
+
+    for ancestor in node.ancestors(&state.arena) {
+        let symbol = state.arena[ancestor].get();
+
+        let part = match symbol.kind {
+            PhpSymbolKind::File => break,
+            PhpSymbolKind::Variable => return None,
+            _ if is_top_level(state, ancestor) => symbol.fqdn().to_lowercase(),
+            _ => format!("::{:?}:{}", symbol.kind, symbol.normalized_name()),
+        };
+        key.insert_str(0, &part);
+    }
+
+    Some(key)
+}
+
+/// The symbol a key returned by `symbol_key` belongs to, if it still exists
+fn node_of_key(state: &BackendState, key: &str) -> Option<NodeId> {
+    let mut parts = key.split("::");
+    let mut node = *state.global_symbols.get(parts.next()?)?;
+
+    for part in parts {
+        let (kind, name) = part.split_once(':')?;
+
+        node = node.children(&state.arena).find(|child| {
+            let symbol = state.arena[*child].get();
+
+            format!("{:?}", symbol.kind) == kind && symbol.normalized_name() == name
+        })?;
+    }
+
+    Some(node)
+}
+
+fn is_top_level(state: &BackendState, node: NodeId) -> bool {
+    state.arena[node]
+        .parent()
+        .map(|parent| state.arena[parent].get().kind == PhpSymbolKind::File)
+        .unwrap_or(true)
+}
+
+/// What the resolution of references to a symbol depends on: its kind, types and parents. The
+/// parameters of functions are symbols of their own.
+fn signature(state: &BackendState, node: NodeId) -> Vec<String> {
+    let symbol = state.arena[node].get();
+
+    std::iter::once(format!("{:?}", symbol.kind))
+        .chain(
+            symbol
+                .data_types
+                .iter()
+                .chain(symbol.inherits_from.iter().flatten())
+                .chain(symbol.generic_parents.iter())
+                .map(|reference| reference.type_ref.to_fqdn().to_lowercase()),
+        )
+        .collect()
+}
+
+/// Move the references to the symbols of a file indexed anew from the old to the new symbols.
+/// Files referencing symbols that were removed or changed their signature are marked as stale.
+pub(crate) fn renew_symbols(state: &mut BackendState, path: &str, old: NodeId, new: NodeId) {
+    let renewed = new
+        .descendants(&state.arena)
+        .skip(1)
+        .filter_map(|node| Some((symbol_key(state, node)?, node)))
+        .collect::<HashMap<String, NodeId>>();
+
+    // Old symbols mapping to the new ones, if they still exist unchanged
+    let mut replacements = HashMap::new();
+    let mut files = HashSet::new();
+    for node in old.descendants(&state.arena).skip(1) {
+        let key = if let Some(key) = symbol_key(state, node) {
+            key
+        } else {
+            continue;
+        };
+
+        let replacement = renewed
+            .get(&key)
+            .copied()
+            .filter(|renewed| signature(state, node) == signature(state, *renewed));
+
+        replacements.insert(node, replacement);
+        files.extend(state.referencing_files.files(&key).cloned());
+    }
+
+    for file in files {
+        let mut stale = false;
+
+        if let Some(references) = state.symbol_references.get_mut(&file) {
+            for (old, new) in replacements.iter() {
+                if let Some(ranges) = references.remove(old) {
+                    match new {
+                        Some(new) => references.entry(*new).or_default().extend(ranges),
+                        None => stale = true,
+                    }
+                }
+            }
+        }
+
+        // Symbols of the file know the types they were resolved to
+        if let Some(enclosing) = state.files.get(&file).copied() {
+            let symbols = enclosing.descendants(&state.arena).collect::<Vec<NodeId>>();
+
+            for symbol in symbols {
+                let symbol = state.arena[symbol].get_mut();
+
+                for reference in symbol
+                    .data_types
+                    .iter_mut()
+                    .chain(symbol.inherits_from.iter_mut().flatten())
+                    .chain(symbol.generic_parents.iter_mut())
+                {
+                    if let Some(new) = reference.node.and_then(|node| replacements.get(&node)) {
+                        reference.node = *new;
+                    }
+                }
+            }
+        }
+
+        if stale && file != path {
+            state.stale_references.insert(file);
+        }
+    }
+
+    for types_of_file in state.inferred_types.values_mut() {
+        for types in types_of_file.values_mut() {
+            *types = types
+                .iter()
+                .filter_map(|node| replacements.get(node).copied().unwrap_or(Some(*node)))
+                .collect();
+        }
+    }
+}
+
+/// Lowercase names of the identifiers and types mentioned in an AST
+fn mentioned_names(ast: &[AstNode]) -> HashSet<String> {
+    ast.iter()
+        .flat_map(|node| std::iter::once(node).chain(node.descendants()))
+        .filter_map(|node| match node {
+            AstNode::Identifier(token) | AstNode::Literal(token) | AstNode::Variable(token) => {
+                token.label.clone()
+            }
+            AstNode::TypeRef(type_ref) => type_ref.tip().map(str::to_owned),
+            _ => None,
+        })
+        .map(|name| name.trim_start_matches('$').to_lowercase())
+        .collect()
+}
+
+/// Mark the files mentioning names that were declared since the last refresh as stale, as their
+/// references could not be resolved before
+pub(crate) fn mark_mentioning_files(state: &mut BackendState) {
+    if state.new_declarations.is_empty() {
+        return;
+    }
+
+    let names = std::mem::take(&mut state.new_declarations);
+
+    for (file, mentioned) in state.mentioned_names.iter() {
+        if names.iter().any(|name| mentioned.contains(name)) {
+            state.stale_references.insert(file.clone());
+        }
+    }
+}
+
+/// Bring the reference index up to date before it is queried. Files that referenced removed or
+/// changed symbols are indexed again, as well as files mentioning names that were declared since
+/// the last refresh.
+pub(crate) fn refresh(state: &mut BackendState) {
+    mark_mentioning_files(state);
+
+    let mut stale = std::mem::take(&mut state.stale_references)
+        .drain()
+        .collect::<Vec<String>>();
+    stale.sort();
+
+    for file in stale {
+        if let Some((ast, _)) = state.opened_files.get(&file) {
+            let ast = ast.clone();
+
+            if let Err(e) = Backend::collect_references(&file, &ast, state, None) {
+                eprintln!("Error indexing references of {}: {}", file, e);
+            }
+
+            continue;
+        }
+
+        // The file was deleted or can not be parsed anymore
+        match source_of(state, &file).map(|source| Backend::source_to_ast(&source)) {
+            Some(Ok((ast, _, _))) if state.files.contains_key(&file) => {
+                index_file(state, &file, &ast)
+            }
+            _ => forget(state, &file),
+        }
+    }
+}
+
+/// All references to a symbol, by file
+pub(crate) fn references_of(state: &BackendState, node: NodeId) -> ReferenceMap {
+    let key = if let Some(key) = symbol_key(state, node) {
+        key
+    } else {
+        return ReferenceMap::new();
+    };
+
+    state
+        .referencing_files
+        .files(&key)
+        .filter_map(|file| {
+            let ranges = state.symbol_references.get(file)?.get(&node)?;
+
+            Some((
+                file.clone(),
+                std::iter::once((node, ranges.clone())).collect(),
+            ))
+        })
+        .collect()
+}
+
+/// Source of a stale file that is not opened, which has to be parsed again
+fn source_of(state: &BackendState, file: &str) -> Option<String> {
+    if let Some(source) = state.latest_version_of_file.get(file) {
+        return Some(source.to_string());
+    }
+
+    std::fs::read_to_string(file).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::tests::populate_state;
+    use crate::environment::get_range;
+
+    fn lines_of_references(state: &BackendState, fqdn: &str, member: &str) -> Vec<(String, u32)> {
+        let class = state.global_symbols[fqdn];
+        let node = class
+            .children(&state.arena)
+            .find(|child| state.arena[*child].get().name() == member)
+            .unwrap();
+
+        let mut lines = references_of(state, node)
+            .iter()
+            .flat_map(|(file, references)| {
+                references[&node]
+                    .iter()
+                    .map(move |range| (file.clone(), get_range(*range).start.line))
+            })
+            .collect::<Vec<(String, u32)>>();
+        lines.sort();
+
+        lines
+    }
+
+    #[test]
+    fn test_restores_references_by_symbol_keys() {
+        let mut state = BackendState::default();
+        populate_state(
+            &mut state,
+            &[
+                (
+                    "/a.php",
+                    "<?php\nnamespace App;\nclass A { public function foo() {} }\n",
+                ),
+                ("/b.php", "<?php\nnamespace App;\n(new A())->foo();\n"),
+            ],
+        );
+
+        let method = state.global_symbols["app\\a"]
+            .children(&state.arena)
+            .find(|child| state.arena[*child].get().name() == "foo")
+            .unwrap();
+        let key = symbol_key(&state, method).unwrap();
+        assert_eq!(Some(method), node_of_key(&state, &key));
+
+        let ranges = state.symbol_references["/b.php"][&method].clone();
+        forget(&mut state, "/b.php");
+
+        restore(
+            &mut state,
+            "/b.php",
+            vec![(key, ranges), (String::from("app\\gone"), Vec::new())],
+            HashSet::new(),
+        );
+        assert_eq!(
+            vec![(String::from("/a.php"), 2), (String::from("/b.php"), 2)],
+            lines_of_references(&state, "app\\a", "foo")
+        );
+
+        // A referenced symbol does not exist anymore
+        assert!(state.stale_references.contains("/b.php"));
+    }
+
+    #[test]
+    fn test_reindexes_files_referencing_removed_symbols() {
+        let caller = "<?php\n(new A())->foo();\n";
+        let mut state = BackendState::default();
+        state
+            .latest_version_of_file
+            .insert(String::from("/b.php"), String::from(caller).into());
+        populate_state(
+            &mut state,
+            &[
+                ("/a.php", "<?php\nclass A { public function foo() {} }\n"),
+                ("/b.php", caller),
+            ],
+        );
+        refresh(&mut state);
+
+        // The declaration counts as a reference, too
+        assert_eq!(
+            vec![(String::from("/a.php"), 1), (String::from("/b.php"), 1)],
+            lines_of_references(&state, "a", "foo")
+        );
+
+        // The method is declared anew, the references to the old one are moved over to it
+        populate_state(
+            &mut state,
+            &[(
+                "/a.php",
+                "<?php\nclass A {\n    public function foo() {}\n}\n",
+            )],
+        );
+        assert!(state.stale_references.is_empty());
+        assert_eq!(
+            vec![(String::from("/a.php"), 2), (String::from("/b.php"), 1)],
+            lines_of_references(&state, "a", "foo")
+        );
+
+        // The method is gone, so the caller has to be indexed again
+        populate_state(
+            &mut state,
+            &[(
+                "/a.php",
+                "<?php\nclass A {\n    public function bar() {}\n}\n",
+            )],
+        );
+        assert!(state.stale_references.contains("/b.php"));
+
+        refresh(&mut state);
+        assert!(state.stale_references.is_empty());
+        assert!(!state.symbol_references["/b.php"]
+            .keys()
+            .any(|node| state.arena[*node].get().name() == "foo"));
+    }
+
+    #[test]
+    fn test_resolves_references_to_new_declarations() {
+        let caller = "<?php\n$a = new A();\n$a->bar();\n";
+        let mut state = BackendState::default();
+        state
+            .latest_version_of_file
+            .insert(String::from("/b.php"), String::from(caller).into());
+        populate_state(
+            &mut state,
+            &[("/a.php", "<?php\nclass A {}\n"), ("/b.php", caller)],
+        );
+        refresh(&mut state);
+
+        populate_state(
+            &mut state,
+            &[("/a.php", "<?php\nclass A { public function bar() {} }\n")],
+        );
+        assert_eq!(
+            vec![String::from("bar")],
+            state
+                .new_declarations
+                .iter()
+                .cloned()
+                .collect::<Vec<String>>()
+        );
+
+        refresh(&mut state);
+        assert_eq!(
+            vec![(String::from("/a.php"), 1), (String::from("/b.php"), 2)],
+            lines_of_references(&state, "a", "bar")
+        );
+    }
+}
@@ -0,0 +1,52 @@
+use super::{reference_index, Backend, BackendState};
+use crate::environment::{fs as EnvFs, get_range};
+use lsp_types::{Location, ReferenceParams, Url};
+use tower_lsp::jsonrpc::Result;
+
+/// Gateway function that accepts the LSP parameters and returns all references to the symbol
+/// at the cursor, across all files of the workspace
+pub(crate) fn references(
+    state: &BackendState,
+    params: ReferenceParams,
+) -> Result<Option<Vec<Location>>> {
+    let position = &params.text_document_position.position;
+    let file = EnvFs::normalize_path(
+        &params
+            .text_document_position
+            .text_document
+            .uri
+            .to_file_path()
+            .unwrap(),
+    );
+
+    let (suc, nuc) = if let Some((suc, nuc)) = Backend::symbol_under_cursor(state, position, &file)
+    {
+        (suc, nuc)
+    } else {
+        return Ok(None);
+    };
+
+    eprintln!("Finding refs for {:?} {}", suc, nuc);
+
+    let locations = reference_index::references_of(state, suc)
+        .iter()
+        .filter_map(|(file, refs)| {
+            // Find all refs that point to our symbol, across all files
+            refs.get(&suc).map(|ranges| {
+                ranges
+                    .iter()
+                    .map(|range| Location {
+                        uri: Url::from_file_path(file).unwrap(),
+                        range: get_range(*range),
+                    })
+                    .collect::<Vec<Location>>()
+            })
+        })
+        .fold(Vec::new(), |cur, mut tot: Vec<Location>| {
+            tot.extend(cur);
+
+            tot
+        });
+
+    Ok(Some(locations))
+}
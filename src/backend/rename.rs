@@ -0,0 +1,61 @@
+use super::{reference_index, Backend, BackendState};
+use crate::environment::{fs as EnvFs, get_range};
+use lsp_types::{RenameParams, TextEdit, Url, WorkspaceEdit};
+use std::collections::HashMap;
+use tower_lsp::jsonrpc::Result;
+
+/// Gateway function that accepts the LSP parameters and renames the referenced symbol at the
+/// cursor, across all files of the workspace
+pub(crate) fn rename(state: &BackendState, params: RenameParams) -> Result<Option<WorkspaceEdit>> {
+    let position = &params.text_document_position.position;
+    let file = EnvFs::normalize_path(
+        &params
+            .text_document_position
+            .text_document
+            .uri
+            .to_file_path()
+            .unwrap(),
+    );
+
+    let nuc = if let Some(references) = &state.symbol_references.get(&file) {
+        if let Some(nuc) = Backend::referenced_symbol_under_cursor(position, references) {
+            *nuc
+        } else {
+            return Ok(None);
+        }
+    } else {
+        return Ok(None);
+    };
+
+    let mut changes: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+
+    reference_index::references_of(state, nuc)
+        .iter()
+        .for_each(|(file, refs)| {
+            let edits: Vec<TextEdit> = refs
+                .iter()
+                .filter_map(|(node, ranges)| {
+                    if node == &nuc {
+                        return Some(ranges.iter().map(|range| TextEdit {
+                            new_text: params.new_name.clone(),
+                            range: get_range(*range),
+                        }));
+                    }
+
+                    None
+                })
+                .flatten()
+                .collect();
+            // Find all refs that point to our symbol, across all files
+            changes
+                .entry(Url::from_file_path(file).unwrap())
+                .or_default()
+                .extend(edits);
+        });
+
+    Ok(Some(WorkspaceEdit {
+        changes: Some(changes),
+        document_changes: None,
+        change_annotations: None,
+    }))
+}
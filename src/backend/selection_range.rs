@@ -0,0 +1,147 @@
+use super::BackendState;
+use crate::environment::{fs as EnvFs, get_range, in_range};
+use crate::parser::node::Node as AstNode;
+use crate::suggester;
+use lsp_types::{Position, Range, SelectionRange, SelectionRangeParams};
+use tower_lsp::jsonrpc::Result;
+
+/// Gateway function that accepts the LSP parameters and calls the exec method
+pub(crate) fn selection_range(
+    state: &BackendState,
+    params: SelectionRangeParams,
+) -> Result<Option<Vec<SelectionRange>>> {
+    let file = EnvFs::normalize_path(&params.text_document.uri.to_file_path().unwrap());
+
+    if let Some((ast, _)) = state.opened_files.get(&file) {
+        return Ok(Some(
+            params
+                .positions
+                .iter()
+                .map(|position| exec(ast, position))
+                .collect(),
+        ));
+    }
+
+    Ok(None)
+}
+
+/// Build the chain of ranges from the node at the position up to the top level statement
+#[inline]
+fn exec(ast: &[AstNode], position: &Position) -> SelectionRange {
+    let ancestors = ast
+        .iter()
+        .find_map(|node| suggester::find(node, position, Vec::new()))
+        .map(|(_, ancestors)| ancestors)
+        .unwrap_or_default();
+
+    // From the outermost to the innermost range
+    let mut ranges = Vec::new();
+    for node in ancestors {
+        ranges.push(get_range(node.range()));
+
+        // The arguments have no node of their own, but should be selectable as a whole
+        if let AstNode::Call { op, cp, .. } = node {
+            let arguments = get_range((op, cp).into());
+
+            if in_range(position, &arguments) {
+                ranges.push(arguments);
+            }
+        }
+    }
+
+    let mut selection: Option<SelectionRange> = None;
+    for range in ranges {
+        // Every range must be contained by its parent, so skip duplicates and odd ranges
+        if let Some(parent) = selection.as_ref() {
+            if parent.range == range || !contains(&parent.range, &range) {
+                continue;
+            }
+        }
+
+        selection = Some(SelectionRange {
+            range,
+            parent: selection.map(Box::new),
+        });
+    }
+
+    selection.unwrap_or(SelectionRange {
+        range: Range::new(*position, *position),
+        parent: None,
+    })
+}
+
+fn contains(outer: &Range, inner: &Range) -> bool {
+    outer.start <= inner.start && inner.end <= outer.end
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parser::{scanner::Scanner, Parser};
+
+    fn selections(source: &str, line: u32, character: u32) -> Vec<((u32, u32), (u32, u32))> {
+        let mut scanner = Scanner::new(source);
+        scanner.scan().unwrap();
+        let (ast, _) = Parser::ast(scanner.tokens).unwrap();
+
+        let mut selection = Some(exec(&ast, &Position { line, character }));
+        let mut ranges = Vec::new();
+        while let Some(current) = selection {
+            ranges.push((
+                (current.range.start.line, current.range.start.character),
+                (current.range.end.line, current.range.end.character),
+            ));
+            selection = current.parent.map(|parent| *parent);
+        }
+
+        ranges
+    }
+
+    #[test]
+    fn test_expands_from_variable_to_class() {
+        let source = "<?php
+class Foo
+{
+    public function bar()
+    {
+        $this->run($value->name, 2);
+    }
+}
+";
+
+        assert_eq!(
+            vec![
+                // $value
+                ((5, 19), (5, 25)),
+                // $value->name, which is also the argument
+                ((5, 19), (5, 31)),
+                // The argument list
+                ((5, 18), (5, 35)),
+                // The call, which is also the statement
+                ((5, 8), (5, 35)),
+                // The block of the method
+                ((4, 4), (6, 5)),
+                // The parameters and the block
+                ((3, 23), (6, 5)),
+                // The method
+                ((3, 4), (6, 5)),
+                // The body of the class
+                ((2, 0), (7, 1)),
+                // The class
+                ((1, 0), (7, 1)),
+            ],
+            selections(source, 5, 21)
+        );
+    }
+
+    #[test]
+    fn test_returns_empty_range_outside_of_nodes() {
+        let selection = exec(&[], &Position::new(3, 1));
+
+        assert_eq!(
+            Range::new(Position::new(3, 1), Position::new(3, 1)),
+            selection.range
+        );
+        assert!(selection.parent.is_none());
+    }
+}
@@ -0,0 +1,588 @@
+use super::BackendState;
+use crate::environment::fs as EnvFs;
+use crate::environment::symbol::PhpSymbolKind;
+use crate::parser::scanner::Scanner;
+use crate::parser::token::TokenType;
+use indextree::NodeId;
+use lsp_types::{
+    Position, Range, SemanticToken, SemanticTokenModifier, SemanticTokenType, SemanticTokens,
+    SemanticTokensDelta, SemanticTokensDeltaParams, SemanticTokensEdit,
+    SemanticTokensFullDeltaResult, SemanticTokensLegend, SemanticTokensParams,
+    SemanticTokensRangeParams, SemanticTokensRangeResult, SemanticTokensResult, Url,
+};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use tower_lsp::jsonrpc::Result;
+
+/// Token types in the order of the legend, the index is what gets sent to the client
+const TOKEN_TYPES: [SemanticTokenType; 12] = [
+    SemanticTokenType::NAMESPACE,
+    SemanticTokenType::CLASS,
+    SemanticTokenType::INTERFACE,
+    SemanticTokenType::new("trait"),
+    SemanticTokenType::ENUM,
+    SemanticTokenType::ENUM_MEMBER,
+    SemanticTokenType::FUNCTION,
+    SemanticTokenType::METHOD,
+    SemanticTokenType::PROPERTY,
+    SemanticTokenType::PARAMETER,
+    SemanticTokenType::VARIABLE,
+    SemanticTokenType::new("constant"),
+];
+
+/// Token modifiers in the order of the legend, the index is the bit in the modifier set
+const TOKEN_MODIFIERS: [SemanticTokenModifier; 5] = [
+    SemanticTokenModifier::DECLARATION,
+    SemanticTokenModifier::STATIC,
+    SemanticTokenModifier::READONLY,
+    SemanticTokenModifier::DEPRECATED,
+    SemanticTokenModifier::DEFAULT_LIBRARY,
+];
+
+// Indices into `TOKEN_TYPES`
+const NAMESPACE: u32 = 0;
+const CLASS: u32 = 1;
+const INTERFACE: u32 = 2;
+const TRAIT: u32 = 3;
+const ENUM: u32 = 4;
+const ENUM_MEMBER: u32 = 5;
+const FUNCTION: u32 = 6;
+const METHOD: u32 = 7;
+const PROPERTY: u32 = 8;
+const PARAMETER: u32 = 9;
+const VARIABLE: u32 = 10;
+const CONSTANT: u32 = 11;
+
+const DECLARATION: u32 = 1;
+const STATIC: u32 = 1 << 1;
+const READONLY: u32 = 1 << 2;
+const DEPRECATED: u32 = 1 << 3;
+const DEFAULT_LIBRARY: u32 = 1 << 4;
+
+/// Source of unique result ids, so that a client never gets a delta against the wrong tokens
+static NEXT_RESULT_ID: AtomicU64 = AtomicU64::new(1);
+
+/// A semantic token with an absolute position
+#[derive(Debug, PartialEq)]
+struct Highlight {
+    line: u32,
+    col: u32,
+    length: u32,
+    token_type: u32,
+    modifiers: u32,
+}
+
+pub(crate) fn legend() -> SemanticTokensLegend {
+    SemanticTokensLegend {
+        token_types: TOKEN_TYPES.to_vec(),
+        token_modifiers: TOKEN_MODIFIERS.to_vec(),
+    }
+}
+
+/// Gateway function that accepts the LSP parameters and returns the tokens of the whole file
+pub(crate) fn semantic_tokens_full(
+    state: &BackendState,
+    params: SemanticTokensParams,
+) -> Result<Option<SemanticTokensResult>> {
+    Ok(tokens_of_file(state, &params.text_document.uri).map(SemanticTokensResult::Tokens))
+}
+
+/// Return only the changes since the tokens with the id `previous_result_id`, or all tokens
+/// if those are not known anymore
+pub(crate) fn semantic_tokens_full_delta(
+    state: &BackendState,
+    params: SemanticTokensDeltaParams,
+) -> Result<Option<SemanticTokensFullDeltaResult>> {
+    let file = EnvFs::normalize_path(&params.text_document.uri.to_file_path().unwrap());
+    let previous = state
+        .semantic_tokens
+        .lock()
+        .unwrap()
+        .get(&file)
+        .filter(|previous| previous.result_id.as_ref() == Some(&params.previous_result_id))
+        .map(|previous| previous.data.clone());
+
+    let tokens = if let Some(tokens) = tokens_of_file(state, &params.text_document.uri) {
+        tokens
+    } else {
+        return Ok(None);
+    };
+
+    if let Some(previous) = previous {
+        return Ok(Some(SemanticTokensFullDeltaResult::TokensDelta(
+            SemanticTokensDelta {
+                result_id: tokens.result_id.clone(),
+                edits: diff(&previous, &tokens.data).into_iter().collect(),
+            },
+        )));
+    }
+
+    Ok(Some(SemanticTokensFullDeltaResult::Tokens(tokens)))
+}
+
+/// Return the tokens within the requested range. Those are not remembered for deltas.
+pub(crate) fn semantic_tokens_range(
+    state: &BackendState,
+    params: SemanticTokensRangeParams,
+) -> Result<Option<SemanticTokensRangeResult>> {
+    let file = EnvFs::normalize_path(&params.text_document.uri.to_file_path().unwrap());
+    let range = params.range;
+
+    Ok(highlights(state, &file).map(|highlights| {
+        let within = highlights
+            .into_iter()
+            .filter(|h| {
+                let start = Position::new(h.line, h.col);
+                let end = Position::new(h.line, h.col + h.length);
+
+                start >= range.start && end <= range.end
+            })
+            .collect::<Vec<Highlight>>();
+
+        SemanticTokensRangeResult::Tokens(SemanticTokens {
+            result_id: None,
+            data: encode(&within),
+        })
+    }))
+}
+
+/// Compute the tokens of a file and remember them as the base for the next delta
+fn tokens_of_file(state: &BackendState, uri: &Url) -> Option<SemanticTokens> {
+    let file = EnvFs::normalize_path(&uri.to_file_path().unwrap());
+
+    let tokens = SemanticTokens {
+        result_id: Some(NEXT_RESULT_ID.fetch_add(1, Ordering::Relaxed).to_string()),
+        data: encode(&highlights(state, &file)?),
+    };
+    state
+        .semantic_tokens
+        .lock()
+        .unwrap()
+        .insert(file, tokens.clone());
+
+    Some(tokens)
+}
+
+/// Classify the tokens of an opened file. Every token that starts where a symbol is declared or
+/// referenced is highlighted according to that symbol. Names that are not resolved but are
+/// followed by a namespace separator are highlighted as namespaces.
+fn highlights(state: &BackendState, file: &str) -> Option<Vec<Highlight>> {
+    let source = state.latest_version_of_file.get(file)?;
+    let enclosing_file = *state.files.get(file)?;
+
+    let mut scanner = Scanner::new(source);
+    if scanner.scan().is_err() {
+        return None;
+    }
+
+    // Start of a name to the symbol it refers to, and whether it is declared there
+    let mut symbols: HashMap<(u32, u32), (NodeId, bool)> = HashMap::new();
+    let mut namespaces: Vec<Range> = Vec::new();
+
+    for node in enclosing_file.descendants(&state.arena).skip(1) {
+        let symbol = state.arena[node].get();
+
+        match symbol.kind {
+            PhpSymbolKind::Namespace => namespaces.push(symbol.selection_range),
+            PhpSymbolKind::MagicConst | PhpSymbolKind::Import | PhpSymbolKind::Unknown => (),
+            _ if symbol.is_implicit => (),
+            _ => {
+                let start = symbol.selection_range.start;
+                symbols.insert((start.line, start.character), (node, true));
+            }
+        }
+    }
+
+    if let Some(references) = state.symbol_references.get(file) {
+        for (node, ranges) in references {
+            for range in ranges {
+                symbols
+                    .entry((range.start_line, range.start_col))
+                    .or_insert((*node, false));
+            }
+        }
+    }
+
+    let mut highlights = Vec::new();
+    let mut tokens = scanner.tokens.iter().peekable();
+    while let Some(token) = tokens.next() {
+        let (col, line) = token.start();
+        let (end_col, end_line) = token.end();
+
+        if end_line != line || end_col <= col {
+            continue;
+        }
+
+        let length = end_col - col;
+
+        if let Some((node, declaration)) = symbols.get(&(line, col)) {
+            let symbol = state.arena[*node].get();
+
+            let token_type = if let Some(token_type) = token_type(&symbol.kind) {
+                token_type
+            } else {
+                continue;
+            };
+
+            // $this resolves to the class, but is still a variable
+            if token.t == TokenType::Variable && !is_variable(&symbol.kind) {
+                highlights.push(Highlight {
+                    line,
+                    col,
+                    length,
+                    token_type: VARIABLE,
+                    modifiers: 0,
+                });
+
+                continue;
+            }
+
+            let mut modifiers = 0;
+            if *declaration {
+                modifiers |= DECLARATION;
+            }
+            if symbol.is_static {
+                modifiers |= STATIC;
+            }
+            if symbol.is_readonly {
+                modifiers |= READONLY;
+            }
+            if symbol.deprecated == Some(true) {
+                modifiers |= DEPRECATED;
+            }
+            if is_from_stubs(state, *node) {
+                modifiers |= DEFAULT_LIBRARY;
+            }
+
+            highlights.push(Highlight {
+                line,
+                col,
+                length,
+                token_type,
+                modifiers,
+            });
+        } else if token.t == TokenType::Identifier {
+            let position = Position::new(line, col);
+
+            if let Some(declaration) = namespaces
+                .iter()
+                .find(|range| position >= range.start && position < range.end)
+            {
+                let modifiers = if declaration.start == position {
+                    DECLARATION
+                } else {
+                    0
+                };
+
+                highlights.push(Highlight {
+                    line,
+                    col,
+                    length,
+                    token_type: NAMESPACE,
+                    modifiers,
+                });
+            } else if let Some(TokenType::NamespaceSeparator) = tokens.peek().map(|t| &t.t) {
+                highlights.push(Highlight {
+                    line,
+                    col,
+                    length,
+                    token_type: NAMESPACE,
+                    modifiers: 0,
+                });
+            }
+        }
+    }
+
+    Some(highlights)
+}
+
+/// Index of the token type of a kind of symbol, if it gets highlighted at all
+fn token_type(kind: &PhpSymbolKind) -> Option<u32> {
+    let token_type = match kind {
+        PhpSymbolKind::Namespace => NAMESPACE,
+        PhpSymbolKind::Class => CLASS,
+        PhpSymbolKind::Interface => INTERFACE,
+        PhpSymbolKind::Trait => TRAIT,
+        PhpSymbolKind::Enum => ENUM,
+        PhpSymbolKind::EnumMember => ENUM_MEMBER,
+        PhpSymbolKind::Function => FUNCTION,
+        PhpSymbolKind::Method | PhpSymbolKind::Constructor => METHOD,
+        PhpSymbolKind::Property | PhpSymbolKind::Field => PROPERTY,
+        PhpSymbolKind::FunctionParameter => PARAMETER,
+        PhpSymbolKind::Variable => VARIABLE,
+        PhpSymbolKind::Constant => CONSTANT,
+        _ => return None,
+    };
+
+    Some(token_type)
+}
+
+fn is_variable(kind: &PhpSymbolKind) -> bool {
+    matches!(
+        kind,
+        PhpSymbolKind::Variable
+            | PhpSymbolKind::FunctionParameter
+            | PhpSymbolKind::Property
+            | PhpSymbolKind::Field
+    )
+}
+
+/// Symbols of the stubs are the standard library
+fn is_from_stubs(state: &BackendState, node: NodeId) -> bool {
+    let stubs = if let Some(stubs) = state.stubs.as_ref() {
+        stubs
+    } else {
+        return false;
+    };
+
+    node.ancestors(&state.arena)
+        .last()
+        .map(|file| Path::new(&state.arena[file].get().name).starts_with(stubs))
+        .unwrap_or(false)
+}
+
+/// Encode the highlights relative to each other, as the protocol expects
+fn encode(highlights: &[Highlight]) -> Vec<SemanticToken> {
+    let mut line = 0;
+    let mut col = 0;
+
+    highlights
+        .iter()
+        .map(|h| {
+            let delta_line = h.line - line;
+            let delta_start = if delta_line == 0 { h.col - col } else { h.col };
+
+            line = h.line;
+            col = h.col;
+
+            SemanticToken {
+                delta_line,
+                delta_start,
+                length: h.length,
+                token_type: h.token_type,
+                token_modifiers_bitset: h.modifiers,
+            }
+        })
+        .collect()
+}
+
+/// A single edit replacing everything between the common prefix and the common suffix. Offsets
+/// are counted in integers, of which every token has five.
+fn diff(old: &[SemanticToken], new: &[SemanticToken]) -> Option<SemanticTokensEdit> {
+    let prefix = old
+        .iter()
+        .zip(new.iter())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    if prefix + suffix == old.len() && prefix + suffix == new.len() {
+        return None;
+    }
+
+    Some(SemanticTokensEdit {
+        start: (prefix * 5) as u32,
+        delete_count: ((old.len() - prefix - suffix) * 5) as u32,
+        data: Some(new[prefix..new.len() - suffix].to_vec()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::tests::populate_state;
+    use lsp_types::{PartialResultParams, TextDocumentIdentifier, WorkDoneProgressParams};
+
+    fn state(source: &str) -> BackendState {
+        let mut state = BackendState::default();
+        state
+            .latest_version_of_file
+            .insert(String::from("/index.php"), source.to_owned().into());
+        populate_state(&mut state, &[("/index.php", source)]);
+
+        state
+    }
+
+    fn highlight(line: u32, col: u32, length: u32, token_type: u32, modifiers: u32) -> Highlight {
+        Highlight {
+            line,
+            col,
+            length,
+            token_type,
+            modifiers,
+        }
+    }
+
+    #[test]
+    fn test_indices_match_the_legend() {
+        let indices = [
+            (NAMESPACE, SemanticTokenType::NAMESPACE),
+            (CLASS, SemanticTokenType::CLASS),
+            (INTERFACE, SemanticTokenType::INTERFACE),
+            (TRAIT, SemanticTokenType::new("trait")),
+            (ENUM, SemanticTokenType::ENUM),
+            (ENUM_MEMBER, SemanticTokenType::ENUM_MEMBER),
+            (FUNCTION, SemanticTokenType::FUNCTION),
+            (METHOD, SemanticTokenType::METHOD),
+            (PROPERTY, SemanticTokenType::PROPERTY),
+            (PARAMETER, SemanticTokenType::PARAMETER),
+            (VARIABLE, SemanticTokenType::VARIABLE),
+            (CONSTANT, SemanticTokenType::new("constant")),
+        ];
+
+        assert_eq!(TOKEN_TYPES.len(), indices.len());
+        for (index, token_type) in indices {
+            assert_eq!(token_type, TOKEN_TYPES[index as usize]);
+        }
+    }
+
+    #[test]
+    fn test_highlights_declarations_and_references() {
+        let source = "<?php
+namespace App\\Models;
+/** @deprecated */
+class A implements I {
+    const C = 1;
+    public static $p;
+    public function m(int $x) { $y = $x; self::C; static::$p; $this->m(1); }
+}
+interface I {}
+trait T {}
+";
+        let state = state(source);
+
+        assert_eq!(
+            vec![
+                highlight(1, 10, 3, NAMESPACE, DECLARATION),
+                highlight(1, 14, 6, NAMESPACE, 0),
+                highlight(3, 6, 1, 1, DECLARATION | DEPRECATED),
+                highlight(3, 19, 1, 2, 0),
+                highlight(4, 10, 1, 11, DECLARATION),
+                highlight(5, 18, 2, 8, DECLARATION | STATIC),
+                highlight(6, 20, 1, 7, DECLARATION),
+                highlight(6, 26, 2, 9, DECLARATION),
+                highlight(6, 32, 2, VARIABLE, DECLARATION),
+                highlight(6, 37, 2, 9, 0),
+                highlight(6, 47, 1, 11, 0),
+                highlight(6, 58, 2, 8, STATIC),
+                highlight(6, 62, 5, VARIABLE, 0),
+                highlight(6, 69, 1, 7, 0),
+                highlight(8, 10, 1, 2, DECLARATION),
+                highlight(9, 6, 1, 3, DECLARATION),
+            ],
+            highlights(&state, "/index.php").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_marks_symbols_of_the_stubs_as_default_library() {
+        let mut state = BackendState {
+            stubs: Some(std::path::PathBuf::from("/stubs")),
+            ..BackendState::default()
+        };
+        let source = "<?php strlen('a'); \\Foo\\bar();";
+        state
+            .latest_version_of_file
+            .insert(String::from("/index.php"), source.to_owned().into());
+        populate_state(
+            &mut state,
+            &[
+                ("/stubs/standard.php", "<?php function strlen($s) {}"),
+                ("/index.php", source),
+            ],
+        );
+
+        assert_eq!(
+            vec![
+                highlight(0, 6, 6, 6, DEFAULT_LIBRARY),
+                highlight(0, 20, 3, NAMESPACE, 0),
+            ],
+            highlights(&state, "/index.php").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_encodes_tokens_relative_and_diffs_them() {
+        let old = encode(&[
+            highlight(1, 4, 2, 1, 0),
+            highlight(1, 10, 2, 1, 0),
+            highlight(3, 2, 2, 1, 0),
+        ]);
+        assert_eq!(
+            SemanticToken {
+                delta_line: 0,
+                delta_start: 6,
+                length: 2,
+                token_type: 1,
+                token_modifiers_bitset: 0
+            },
+            old[1]
+        );
+
+        let new = encode(&[
+            highlight(1, 4, 2, 1, 0),
+            highlight(1, 10, 2, 7, 0),
+            highlight(3, 2, 2, 1, 0),
+        ]);
+        assert_eq!(
+            Some(SemanticTokensEdit {
+                start: 5,
+                delete_count: 5,
+                data: Some(vec![new[1]]),
+            }),
+            diff(&old, &new)
+        );
+        assert_eq!(None, diff(&new, &new));
+    }
+
+    #[test]
+    fn test_returns_delta_against_previous_result() {
+        let state = state("<?php $a = 1;");
+        let uri = Url::from_file_path("/index.php").unwrap();
+
+        let full = semantic_tokens_full(
+            &state,
+            SemanticTokensParams {
+                work_done_progress_params: WorkDoneProgressParams::default(),
+                partial_result_params: PartialResultParams::default(),
+                text_document: TextDocumentIdentifier { uri: uri.clone() },
+            },
+        );
+        let result_id = match full {
+            Ok(Some(SemanticTokensResult::Tokens(tokens))) => tokens.result_id.unwrap(),
+            _ => panic!("Expected tokens"),
+        };
+
+        let delta = |state: &BackendState, previous_result_id: String| {
+            semantic_tokens_full_delta(
+                state,
+                SemanticTokensDeltaParams {
+                    work_done_progress_params: WorkDoneProgressParams::default(),
+                    partial_result_params: PartialResultParams::default(),
+                    text_document: TextDocumentIdentifier { uri: uri.clone() },
+                    previous_result_id,
+                },
+            )
+        };
+
+        match delta(&state, result_id) {
+            Ok(Some(SemanticTokensFullDeltaResult::TokensDelta(delta))) => {
+                assert!(delta.edits.is_empty())
+            }
+            _ => panic!("Expected a delta"),
+        }
+
+        match delta(&state, String::from("unknown")) {
+            Ok(Some(SemanticTokensFullDeltaResult::Tokens(tokens))) => {
+                assert_eq!(1, tokens.data.len())
+            }
+            _ => panic!("Expected all tokens"),
+        }
+    }
+}
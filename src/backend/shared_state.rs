@@ -0,0 +1,137 @@
+use super::BackendState;
+use std::iter::FromIterator;
+use std::ops::{Deref, DerefMut};
+use std::sync::{Arc, Mutex};
+
+/// A part of the state that snapshots share until a writer changes it. Borrowing it mutably
+/// copies it first if another snapshot still uses it, so each change only copies what it touches.
+#[derive(Debug, Default, PartialEq)]
+pub struct Shared<T>(Arc<T>);
+
+impl<T> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Shared(self.0.clone())
+    }
+}
+
+impl<T> Deref for Shared<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Clone> DerefMut for Shared<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        Arc::make_mut(&mut self.0)
+    }
+}
+
+impl<T> From<T> for Shared<T> {
+    fn from(value: T) -> Self {
+        Shared(Arc::new(value))
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Shared<T>
+where
+    &'a T: IntoIterator,
+{
+    type Item = <&'a T as IntoIterator>::Item;
+    type IntoIter = <&'a T as IntoIterator>::IntoIter;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.as_ref().into_iter()
+    }
+}
+
+impl<T: FromIterator<A>, A> FromIterator<A> for Shared<T> {
+    fn from_iter<I: IntoIterator<Item = A>>(iter: I) -> Self {
+        Shared::from(T::from_iter(iter))
+    }
+}
+
+/// Immutable snapshots of the state of the backend. Requests work on the latest snapshot and
+/// never wait for writers. Writers change a copy of the latest snapshot one after another and
+/// publish it once they are done.
+#[derive(Default)]
+pub(crate) struct SharedState {
+    /// The latest published snapshot, only locked to take or replace it
+    latest: Mutex<Arc<BackendState>>,
+
+    /// Lets one writer change the state at a time
+    writer: tokio::sync::Mutex<()>,
+}
+
+impl SharedState {
+    pub(crate) fn new(state: BackendState) -> Self {
+        SharedState {
+            latest: Mutex::new(Arc::new(state)),
+            writer: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// The latest published snapshot
+    pub(crate) fn get(&self) -> Arc<BackendState> {
+        self.latest.lock().unwrap().clone()
+    }
+
+    /// Change the state and publish it as the latest snapshot. Readers keep working on the
+    /// previous snapshot in the meantime.
+    pub(crate) async fn update<R>(&self, f: impl FnOnce(&mut BackendState) -> R) -> R {
+        let _writer = self.writer.lock().await;
+
+        let mut state = BackendState::clone(&self.get());
+        let result = f(&mut state);
+        *self.latest.lock().unwrap() = Arc::new(state);
+
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::tests::populate_state;
+
+    #[tokio::test]
+    async fn test_keeps_snapshots_of_readers_untouched() {
+        let state = SharedState::default();
+        state
+            .update(|state| populate_state(state, &[("/a.php", "<?php\nclass A {}\n")]))
+            .await;
+
+        let before = state.get();
+        state
+            .update(|state| populate_state(state, &[("/b.php", "<?php\nclass B {}\n")]))
+            .await;
+        let after = state.get();
+
+        assert_eq!(1, before.global_symbols.len());
+        assert_eq!(2, after.global_symbols.len());
+    }
+
+    #[tokio::test]
+    async fn test_shares_unchanged_parts_between_snapshots() {
+        let state = SharedState::default();
+        state
+            .update(|state| populate_state(state, &[("/a.php", "<?php\nclass A {}\n")]))
+            .await;
+
+        let before = state.get();
+        state
+            .update(|state| {
+                state.document_versions.insert(String::from("/a.php"), 2);
+            })
+            .await;
+        let after = state.get();
+
+        assert!(Arc::ptr_eq(&before.arena.0, &after.arena.0));
+        assert!(Arc::ptr_eq(
+            &before.symbol_references.0,
+            &after.symbol_references.0
+        ));
+        assert!(!Arc::ptr_eq(&before, &after));
+    }
+}
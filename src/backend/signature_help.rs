@@ -0,0 +1,387 @@
+use super::BackendState;
+use crate::environment::arguments::{is_variadic, Callees};
+use crate::environment::{fs as EnvFs, get_range};
+use crate::formatter::{format_node, FormatterOptions};
+use crate::parser::node::{Node as AstNode, NodeRange};
+use crate::parser::token::TokenType;
+use lsp_types::{
+    Documentation, ParameterInformation, ParameterLabel, Position, SignatureHelp,
+    SignatureHelpParams, SignatureInformation,
+};
+use tower_lsp::jsonrpc::Result;
+
+/// Gateway function that accepts the LSP parameters and calls the exec method
+pub(crate) fn signature_help(
+    state: &BackendState,
+    params: SignatureHelpParams,
+) -> Result<Option<SignatureHelp>> {
+    let file = EnvFs::normalize_path(
+        &params
+            .text_document_position_params
+            .text_document
+            .uri
+            .to_file_path()
+            .unwrap(),
+    );
+    let position = &params.text_document_position_params.position;
+
+    if let Some((ast, _)) = state.opened_files.get(&file) {
+        return exec(state, ast, position, &file);
+    }
+
+    Ok(None)
+}
+
+#[inline]
+fn exec(
+    state: &BackendState,
+    ast: &[AstNode],
+    position: &Position,
+    file: &str,
+) -> Result<Option<SignatureHelp>> {
+    let (call, is_new) = if let Some(call) = ast.iter().find_map(|node| call_at(node, position)) {
+        call
+    } else {
+        return Ok(None);
+    };
+
+    let (callee, parameters) = if let AstNode::Call {
+        callee, parameters, ..
+    } = call
+    {
+        (callee, parameters)
+    } else {
+        return Ok(None);
+    };
+
+    let callable = if let Some((callable, _)) =
+        callees(state, file).and_then(|mut callees| callees.callable(callee, is_new))
+    {
+        callable
+    } else {
+        return Ok(None);
+    };
+
+    let symbol = state.arena[callable].get();
+    let mut label = format!("{}(", symbol.name());
+    let mut parameter_information = Vec::new();
+    let mut parameter_names = Vec::new();
+
+    for (i, parameter) in symbol.parameters.iter().enumerate() {
+        if i > 0 {
+            label.push_str(", ");
+        }
+
+        let parameter_label = parameter_label(&parameter.ast);
+        let start = label.encode_utf16().count() as u32;
+        label.push_str(&parameter_label);
+
+        parameter_information.push(ParameterInformation {
+            label: ParameterLabel::LabelOffsets([start, label.encode_utf16().count() as u32]),
+            documentation: parameter_documentation(&parameter.ast),
+        });
+        parameter_names.push(state.arena[parameter.symbol].get().name().to_lowercase());
+    }
+    label.push(')');
+
+    let variadic = symbol.parameters.last().map(is_variadic).unwrap_or(false);
+    let active_parameter = active_parameter(parameters, position, &parameter_names, variadic);
+
+    Ok(Some(SignatureHelp {
+        signatures: vec![SignatureInformation {
+            label,
+            documentation: None,
+            parameters: Some(parameter_information),
+            active_parameter,
+        }],
+        active_signature: Some(0),
+        active_parameter,
+    }))
+}
+
+/// Find the innermost call whose argument list contains the position. The returned flag
+/// tells if the call is the constructor call of a `new` expression.
+fn call_at<'a>(node: &'a AstNode, position: &Position) -> Option<(&'a AstNode, bool)> {
+    let range = node.range();
+    if before(position, (range.start_line, range.start_col))
+        || before(&end_of(range), (position.line, position.character))
+    {
+        return None;
+    }
+
+    // Depending on the context, `new` is parsed as unary operator or as a node of its own
+    let instantiated = match node {
+        AstNode::New { class, .. } => Some(class),
+        AstNode::Unary { token, expr } if token.t == TokenType::New => Some(expr),
+        _ => None,
+    };
+
+    if let Some(class) = instantiated {
+        return match call_at(class, position) {
+            Some((call, _)) if std::ptr::eq(call, class.as_ref()) => Some((call, true)),
+            inner => inner,
+        };
+    }
+
+    // Prefer calls nested within the arguments
+    if let Some(inner) = node
+        .children()
+        .iter()
+        .find_map(|child| call_at(child, position))
+    {
+        return Some(inner);
+    }
+
+    match node {
+        AstNode::Call { op, cp, .. } => {
+            let op = NodeRange::from(op);
+
+            if before(position, (op.end_line, op.end_col))
+                || before(
+                    &Position::new(cp.line, cp.col),
+                    (position.line, position.character),
+                )
+            {
+                return None;
+            }
+
+            Some((node, false))
+        }
+        _ => None,
+    }
+}
+
+/// Resolves the callables of the calls in a file, using the references of the file
+pub(super) fn callees<'a>(state: &'a BackendState, file: &str) -> Option<Callees<'a>> {
+    Some(Callees::new(
+        &state.arena,
+        &state.global_symbols,
+        *state.files.get(file)?,
+        state.symbol_references.get(file)?,
+    ))
+}
+
+/// Determine the index of the parameter the cursor is at. Named arguments refer to
+/// the parameter with the same name, positional ones to their position. A variadic last
+/// parameter takes all remaining positional arguments.
+fn active_parameter(
+    arguments: &[AstNode],
+    position: &Position,
+    parameter_names: &[String],
+    variadic: bool,
+) -> Option<u32> {
+    let index = arguments
+        .iter()
+        .filter(|argument| {
+            before(
+                &end_of(argument.range()),
+                (position.line, position.character),
+            )
+        })
+        .count();
+
+    if let Some(AstNode::NamedParameter { name, .. }) = arguments.get(index) {
+        if let Some(label) = name.label.as_ref() {
+            return parameter_names
+                .iter()
+                .position(|parameter| parameter.eq(&label.to_lowercase()))
+                .map(|index| index as u32);
+        }
+    }
+
+    if variadic && index >= parameter_names.len() {
+        return Some(parameter_names.len() as u32 - 1);
+    }
+
+    Some(index as u32)
+}
+
+/// Render a parameter the way it is declared, without visibility modifiers or attributes
+fn parameter_label(argument: &AstNode) -> String {
+    let options = FormatterOptions {
+        max_line_length: 100,
+        indent: 4,
+    };
+
+    if let AstNode::FunctionArgument {
+        argument_type,
+        name,
+        default_value,
+        spread,
+        reference,
+        doc_comment,
+        ..
+    } = argument
+    {
+        let mut parts = Vec::new();
+
+        if let Some(argument_type) = argument_type {
+            parts.push(format!("{} ", format_node(argument_type, 0, 0, &options)));
+        } else if let Some(AstNode::DocCommentParam {
+            types: Some(types), ..
+        }) = doc_comment.as_deref()
+        {
+            parts.push(format!(
+                "{} ",
+                types
+                    .iter()
+                    .map(|t| t.to_fqdn())
+                    .collect::<Vec<String>>()
+                    .join("|")
+            ));
+        }
+
+        if let Some(spread) = spread {
+            parts.push(spread.to_string());
+        }
+        if let Some(reference) = reference {
+            parts.push(reference.to_string());
+        }
+        parts.push(name.to_string());
+
+        if let Some(default_value) = default_value {
+            parts.push(format!(" = {}", format_node(default_value, 0, 0, &options)));
+        }
+
+        return parts.join("");
+    }
+
+    format_node(argument, 0, 0, &options)
+}
+
+/// Use the description of the @param tag as documentation of the parameter
+fn parameter_documentation(argument: &AstNode) -> Option<Documentation> {
+    if let AstNode::FunctionArgument {
+        doc_comment: Some(doc_comment),
+        ..
+    } = argument
+    {
+        if let AstNode::DocCommentParam { description, .. } = doc_comment.as_ref() {
+            if !description.trim().is_empty() {
+                return Some(Documentation::String(description.trim().to_string()));
+            }
+        }
+    }
+
+    None
+}
+
+fn end_of(range: NodeRange) -> Position {
+    get_range(range).end
+}
+
+/// Returns true if the position is before the given (line, col)
+fn before(position: &Position, (line, col): (u32, u32)) -> bool {
+    (position.line, position.character) < (line, col)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::tests::populate_state;
+    use crate::parser::{scanner::Scanner, Parser};
+
+    fn signature_at(source: &str, line: u32, character: u32) -> Option<SignatureHelp> {
+        let sources = [("index.php", source)];
+
+        let mut state = BackendState::default();
+        populate_state(&mut state, &sources);
+
+        let mut scanner = Scanner::new(source);
+        scanner.scan().unwrap();
+        let (ast, _) = Parser::ast(scanner.tokens).unwrap();
+
+        exec(&state, &ast, &Position { line, character }, "index.php").unwrap()
+    }
+
+    #[test]
+    fn returns_none_outside_of_calls() {
+        assert_eq!(
+            None,
+            signature_at("<?php function foo(int $a) {} foo(1);", 0, 8)
+        );
+    }
+
+    #[test]
+    fn shows_parameters_of_function() {
+        let help = signature_at(
+            "<?php function foo(int $a, ?string $b = null, ...$rest) {} foo(1, );",
+            0,
+            65,
+        )
+        .unwrap();
+
+        assert_eq!(
+            "foo(int $a, ?string $b = null, ...$rest)",
+            help.signatures[0].label
+        );
+        assert_eq!(Some(1), help.active_parameter);
+        assert_eq!(
+            Some(ParameterLabel::LabelOffsets([12, 29])),
+            help.signatures[0]
+                .parameters
+                .as_ref()
+                .map(|p| p[1].label.clone())
+        );
+    }
+
+    #[test]
+    fn stays_at_variadic_parameter_for_further_arguments() {
+        let help =
+            signature_at("<?php function foo($a, ...$rest) {} foo(1, 2, 3, );", 0, 48).unwrap();
+
+        assert_eq!(Some(1), help.active_parameter);
+    }
+
+    #[test]
+    fn counts_label_offsets_in_utf16_code_units() {
+        let help = signature_at("<?php function foo($a = '😀', $b) {} foo(1, );", 0, 43).unwrap();
+
+        assert_eq!("foo($a = '😀', $b)", help.signatures[0].label);
+        assert_eq!(
+            Some(ParameterLabel::LabelOffsets([15, 17])),
+            help.signatures[0]
+                .parameters
+                .as_ref()
+                .map(|p| p[1].label.clone())
+        );
+    }
+
+    #[test]
+    fn shows_parameters_of_method_and_handles_named_arguments() {
+        let help = signature_at(
+            "<?php
+            class Foo {
+                public function bar(int $first, int $second) {}
+            }
+
+            $foo = new Foo();
+            $foo->bar(second: 1);",
+            6,
+            30,
+        )
+        .unwrap();
+
+        assert_eq!("bar(int $first, int $second)", help.signatures[0].label);
+        assert_eq!(Some(1), help.active_parameter);
+    }
+
+    #[test]
+    fn shows_parameters_of_inherited_constructor() {
+        let help = signature_at(
+            "<?php
+            class Base {
+                public function __construct(string $name) {}
+            }
+            class Child extends Base {}
+
+            new Child('a');",
+            6,
+            22,
+        )
+        .unwrap();
+
+        assert_eq!("__construct(string $name)", help.signatures[0].label);
+        assert_eq!(Some(0), help.active_parameter);
+    }
+}
@@ -0,0 +1,358 @@
+use super::{Backend, BackendState};
+use crate::environment::fs as EnvFs;
+use crate::environment::symbol::{PhpSymbolKind, Symbol};
+use crate::environment::visitor::name_resolver::NameResolver;
+use crate::parser::node::TypeRef;
+use indextree::NodeId;
+use lsp_types::{SymbolKind, SymbolTag, TypeHierarchyItem, TypeHierarchyPrepareParams, Url};
+use tower_lsp::jsonrpc::Result;
+
+/// Gateway function that accepts the LSP parameters and returns the class, interface, trait or
+/// enum at the cursor, either where it is declared or where it is referenced
+pub(crate) fn prepare_type_hierarchy(
+    state: &BackendState,
+    params: TypeHierarchyPrepareParams,
+) -> Result<Option<Vec<TypeHierarchyItem>>> {
+    let position = &params.text_document_position_params.position;
+    let file = EnvFs::normalize_path(
+        &params
+            .text_document_position_params
+            .text_document
+            .uri
+            .to_file_path()
+            .unwrap(),
+    );
+
+    let referenced = state
+        .symbol_references
+        .get(&file)
+        .and_then(|references| Backend::referenced_symbol_under_cursor(position, references))
+        .copied();
+    let node = referenced
+        .or_else(|| Backend::symbol_under_cursor(state, position, &file).map(|(node, _)| node));
+
+    Ok(node
+        .filter(|node| is_type(state, *node))
+        .and_then(|node| hierarchy_item(state, node))
+        .map(|item| vec![item]))
+}
+
+/// The classes, interfaces and traits the item extends, implements or uses
+pub(crate) fn supertypes(
+    state: &BackendState,
+    item: &TypeHierarchyItem,
+) -> Option<Vec<TypeHierarchyItem>> {
+    let node = type_of_item(state, item)?;
+
+    Some(
+        resolve_supertypes(state, node)
+            .drain(..)
+            .filter_map(|supertype| hierarchy_item(state, supertype))
+            .collect(),
+    )
+}
+
+/// The classes, interfaces, traits and enums extending, implementing or using the item
+pub(crate) fn subtypes(
+    state: &BackendState,
+    item: &TypeHierarchyItem,
+) -> Option<Vec<TypeHierarchyItem>> {
+    let node = type_of_item(state, item)?;
+
+    Some(
+        direct_subtypes(state, node)
+            .drain(..)
+            .filter_map(|subtype| hierarchy_item(state, subtype))
+            .collect(),
+    )
+}
+
+/// The subtypes of a type and all of their subtypes, in breadth first order
+pub(crate) fn all_subtypes(state: &BackendState, node: NodeId) -> Vec<NodeId> {
+    let mut subtypes = direct_subtypes(state, node);
+    let mut i = 0;
+
+    while i < subtypes.len() {
+        for subtype in direct_subtypes(state, subtypes[i]) {
+            if subtype != node && !subtypes.contains(&subtype) {
+                subtypes.push(subtype);
+            }
+        }
+
+        i += 1;
+    }
+
+    subtypes
+}
+
+fn direct_subtypes(state: &BackendState, node: NodeId) -> Vec<NodeId> {
+    let name = state.arena[node].get().normalized_name();
+
+    // The index only knows the names, so the candidates must actually resolve to the type
+    let mut candidates = state.inheritors.get(&name).cloned().unwrap_or_default();
+    candidates.sort_by_key(|candidate| state.arena[*candidate].get().fqdn());
+    candidates.dedup();
+
+    candidates
+        .drain(..)
+        .filter(|candidate| resolve_supertypes(state, *candidate).contains(&node))
+        .collect()
+}
+
+/// Add a top level symbol to the reverse inheritance index under the names of its supertypes
+pub(crate) fn register_inheritor(state: &mut BackendState, node: NodeId) {
+    if !is_type(state, node) {
+        return;
+    }
+
+    for name in supertype_names(state.arena[node].get()) {
+        state.inheritors.entry(name).or_default().push(node);
+    }
+}
+
+/// Remove a top level symbol from the reverse inheritance index
+pub(crate) fn deregister_inheritor(state: &mut BackendState, node: NodeId) {
+    if !is_type(state, node) {
+        return;
+    }
+
+    for name in supertype_names(state.arena[node].get()) {
+        if let Some(inheritors) = state.inheritors.get_mut(&name) {
+            inheritors.retain(|inheritor| *inheritor != node);
+
+            if inheritors.is_empty() {
+                state.inheritors.remove(&name);
+            }
+        }
+    }
+}
+
+/// Find the type an item of the type hierarchy was created for
+fn type_of_item(state: &BackendState, item: &TypeHierarchyItem) -> Option<NodeId> {
+    let file = EnvFs::normalize_path(&item.uri.to_file_path().ok()?);
+    let (node, name) = Backend::symbol_under_cursor(state, &item.selection_range.start, &file)?;
+
+    if is_type(state, node) && name == item.name {
+        Some(node)
+    } else {
+        None
+    }
+}
+
+/// The type refs of the extended classes and interfaces, implemented interfaces and used traits
+fn supertype_refs(symbol: &Symbol) -> Vec<&TypeRef> {
+    let mut refs = Vec::new();
+    let own_name = symbol.normalized_name();
+
+    if let Some(inherits_from) = symbol.inherits_from.as_ref() {
+        refs.extend(inherits_from.iter().map(|reference| &reference.type_ref));
+    }
+
+    // Skip self reference
+    refs.extend(
+        symbol
+            .data_types
+            .iter()
+            .map(|reference| &reference.type_ref)
+            .filter(|type_ref| {
+                type_ref
+                    .tip()
+                    .map(|tip| !tip.to_lowercase().eq(&own_name))
+                    .unwrap_or(false)
+            }),
+    );
+
+    if let Some(imports) = symbol.imports.as_ref() {
+        refs.extend(imports.all().map(|import| &import.path));
+    }
+
+    refs
+}
+
+fn supertype_names(symbol: &Symbol) -> Vec<String> {
+    let mut names = supertype_refs(symbol)
+        .iter()
+        .filter_map(|type_ref| type_ref.tip())
+        .map(|tip| tip.to_lowercase())
+        .collect::<Vec<String>>();
+    names.sort();
+    names.dedup();
+
+    names
+}
+
+fn resolve_supertypes(state: &BackendState, node: NodeId) -> Vec<NodeId> {
+    let enclosing_file = if let Some(file) = node
+        .ancestors(&state.arena)
+        .find(|a| state.arena[*a].get().kind == PhpSymbolKind::File)
+    {
+        file
+    } else {
+        return Vec::new();
+    };
+
+    let mut resolver = NameResolver::new(&state.global_symbols, enclosing_file);
+    let mut supertypes = Vec::new();
+
+    for type_ref in supertype_refs(state.arena[node].get()) {
+        if let Some(resolved) =
+            resolver.resolve_type_ref(type_ref, &state.arena, &enclosing_file, false)
+        {
+            if resolved != node && !supertypes.contains(&resolved) {
+                supertypes.push(resolved);
+            }
+        }
+    }
+
+    supertypes
+}
+
+fn is_type(state: &BackendState, node: NodeId) -> bool {
+    matches!(
+        state.arena[node].get().kind,
+        PhpSymbolKind::Class
+            | PhpSymbolKind::Interface
+            | PhpSymbolKind::Trait
+            | PhpSymbolKind::Enum
+    )
+}
+
+/// Represent a class, interface, trait or enum in the type hierarchy, detailed with its namespace
+fn hierarchy_item(state: &BackendState, node: NodeId) -> Option<TypeHierarchyItem> {
+    let symbol = state.arena[node].get();
+    let file = node
+        .ancestors(&state.arena)
+        .find(|a| state.arena[*a].get().kind == PhpSymbolKind::File)?;
+
+    Some(TypeHierarchyItem {
+        name: symbol.name().to_owned(),
+        kind: symbol.kind.get_symbol_kind().unwrap_or(SymbolKind::CLASS),
+        tags: if symbol.deprecated == Some(true) {
+            Some(SymbolTag::DEPRECATED)
+        } else {
+            None
+        },
+        detail: symbol.namespace.clone(),
+        uri: Url::from_file_path(&state.arena[file].get().name).ok()?,
+        range: symbol.range,
+        selection_range: symbol.selection_range,
+        data: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::tests::populate_state;
+    use lsp_types::Position;
+
+    fn prepare(state: &BackendState, file: &str, line: u32, character: u32) -> TypeHierarchyItem {
+        let params = TypeHierarchyPrepareParams {
+            text_document_position_params: lsp_types::TextDocumentPositionParams {
+                text_document: lsp_types::TextDocumentIdentifier {
+                    uri: Url::from_file_path(file).unwrap(),
+                },
+                position: Position { line, character },
+            },
+            work_done_progress_params: lsp_types::WorkDoneProgressParams::default(),
+        };
+
+        prepare_type_hierarchy(state, params)
+            .unwrap()
+            .unwrap()
+            .remove(0)
+    }
+
+    fn names(items: Vec<TypeHierarchyItem>) -> Vec<String> {
+        items.iter().map(|item| item.name.clone()).collect()
+    }
+
+    #[test]
+    fn test_resolves_supertypes_and_subtypes() {
+        let mut state = BackendState::default();
+        populate_state(
+            &mut state,
+            &[
+                (
+                    "/a.php",
+                    "<?php
+namespace App;
+interface Shape {}
+trait Named {}
+abstract class Base implements Shape { use Named; }
+",
+                ),
+                (
+                    "/b.php",
+                    "<?php
+namespace Other;
+use App\\Base;
+use App\\Shape;
+class Circle extends Base implements Shape {}
+enum Kind implements Shape {}
+",
+                ),
+                ("/c.php", "<?php\nnamespace Other;\nclass Shape {}\n"),
+            ],
+        );
+
+        let base = prepare(&state, "/a.php", 4, 16);
+        assert_eq!("Base", base.name);
+        assert_eq!(Some(String::from("App")), base.detail);
+        assert_eq!(
+            vec![String::from("Shape"), String::from("Named")],
+            names(supertypes(&state, &base).unwrap())
+        );
+        assert_eq!(
+            vec![String::from("Circle")],
+            names(subtypes(&state, &base).unwrap())
+        );
+
+        // Other\Shape shares the name but is not implemented by anyone
+        let shape = prepare(&state, "/a.php", 2, 12);
+        assert_eq!(
+            vec![
+                String::from("Base"),
+                String::from("Circle"),
+                String::from("Kind")
+            ],
+            names(subtypes(&state, &shape).unwrap())
+        );
+        let local_shape = prepare(&state, "/c.php", 2, 7);
+        assert!(subtypes(&state, &local_shape).unwrap().is_empty());
+
+        // Prepared at a reference, it resolves to the declaration
+        let implemented = prepare(&state, "/a.php", 4, 32);
+        assert_eq!("Shape", implemented.name);
+        assert_eq!(2, implemented.selection_range.start.line);
+
+        let named = prepare(&state, "/a.php", 3, 7);
+        assert_eq!("Named", named.name);
+        assert_eq!(
+            vec![String::from("Base")],
+            names(subtypes(&state, &named).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_updates_subtypes_when_file_is_replaced() {
+        let mut state = BackendState::default();
+        populate_state(
+            &mut state,
+            &[
+                ("/a.php", "<?php\ninterface Shape {}\n"),
+                ("/b.php", "<?php\nclass Circle implements Shape {}\n"),
+            ],
+        );
+
+        let shape = prepare(&state, "/a.php", 1, 12);
+        assert_eq!(
+            vec![String::from("Circle")],
+            names(subtypes(&state, &shape).unwrap())
+        );
+
+        populate_state(&mut state, &[("/b.php", "<?php\nclass Square {}\n")]);
+        assert!(subtypes(&state, &shape).unwrap().is_empty());
+        assert!(state.inheritors.is_empty());
+    }
+}
@@ -0,0 +1,764 @@
+use super::diagnostics::DiagnosticOptions;
+use super::symbol::{FunctionParameter, PhpSymbolKind, Symbol};
+use super::visitor::name_resolver::NameResolver;
+use super::Notification;
+use crate::parser::node::{Node as AstNode, NodeRange, TypeRef};
+use crate::parser::token::TokenType;
+use indextree::{Arena, NodeId};
+use std::collections::HashMap;
+use tower_lsp::lsp_types::DiagnosticSeverity;
+
+/// The type of an argument, as far as it is obvious from the call site
+enum ArgumentType {
+    Int,
+    Float,
+    String { numeric: bool },
+    Bool,
+    Null,
+    Array,
+    Object(NodeId),
+}
+
+/// Whether a parameter accepts an argument, which can be unknown if its type could not be resolved
+#[derive(PartialEq)]
+pub enum Accepts {
+    Yes,
+    No,
+    Unknown,
+}
+
+/// Finds the functions, methods and constructors called in a file through the references the
+/// name resolver collected
+pub struct Callees<'a> {
+    arena: &'a Arena<Symbol>,
+    resolver: NameResolver<'a>,
+
+    /// Referenced symbols by the end of the reference, which is where a callee ends
+    references: HashMap<(u32, u32), Vec<(NodeRange, NodeId)>>,
+}
+
+impl<'a> Callees<'a> {
+    pub fn new(
+        arena: &'a Arena<Symbol>,
+        global_symbols: &'a HashMap<String, NodeId>,
+        file: NodeId,
+        references: &HashMap<NodeId, Vec<NodeRange>>,
+    ) -> Self {
+        let mut by_end: HashMap<(u32, u32), Vec<(NodeRange, NodeId)>> = HashMap::new();
+        for (node, ranges) in references {
+            for range in ranges {
+                by_end
+                    .entry((range.end_line, range.end_col))
+                    .or_default()
+                    .push((*range, *node));
+            }
+        }
+
+        Callees {
+            arena,
+            resolver: NameResolver::new(global_symbols, file),
+            references: by_end,
+        }
+    }
+
+    /// The function, method or constructor a callee refers to, along with the range of the
+    /// reference to it. `is_new` is true if the callee is the class of a `new` expression.
+    pub fn callable(&mut self, callee: &AstNode, is_new: bool) -> Option<(NodeId, NodeRange)> {
+        let range = callee.range();
+        let arena = self.arena;
+
+        // The reference to the callee is the one ending where the callee ends, which is the
+        // name of the function, method or class
+        let (reference, referenced) =
+            self.referenced_at(range)
+                .find(|(_, node)| match arena[*node].get().kind {
+                    PhpSymbolKind::Function | PhpSymbolKind::Method => !is_new,
+                    PhpSymbolKind::Class => is_new,
+                    _ => false,
+                })?;
+
+        if !is_new {
+            return Some((referenced, reference));
+        }
+
+        arena[referenced]
+            .get()
+            .get_all_symbols(referenced, &mut self.resolver, arena)
+            .get("__construct")
+            .map(|alias| (alias.symbol, reference))
+    }
+
+    /// The symbols referenced within a range, by a reference ending where the range ends
+    fn referenced_at(&self, range: NodeRange) -> impl Iterator<Item = (NodeRange, NodeId)> + '_ {
+        self.references
+            .get(&(range.end_line, range.end_col))
+            .into_iter()
+            .flatten()
+            .filter(move |(reference, _)| {
+                (reference.start_line, reference.start_col) >= (range.start_line, range.start_col)
+            })
+            .copied()
+    }
+}
+
+/// Compares the arguments of all calls and instantiations of a file to the parameters of the
+/// callee. The types of variables passed as argument are the ones the name resolver inferred.
+pub struct ArgumentChecker<'a> {
+    arena: &'a Arena<Symbol>,
+    callees: Callees<'a>,
+    options: &'a DiagnosticOptions,
+    inferred_types: &'a HashMap<NodeRange, Vec<NodeId>>,
+
+    file_name: String,
+    notifications: Vec<Notification>,
+}
+
+impl<'a> ArgumentChecker<'a> {
+    pub fn new(
+        arena: &'a Arena<Symbol>,
+        global_symbols: &'a HashMap<String, NodeId>,
+        file: NodeId,
+        references: &HashMap<NodeId, Vec<NodeRange>>,
+        inferred_types: &'a HashMap<NodeRange, Vec<NodeId>>,
+        options: &'a DiagnosticOptions,
+    ) -> Self {
+        ArgumentChecker {
+            arena,
+            callees: Callees::new(arena, global_symbols, file, references),
+            options,
+            inferred_types,
+            file_name: arena[file].get().name().to_owned(),
+            notifications: Vec::new(),
+        }
+    }
+
+    /// Check all calls within the ast and return the diagnostics
+    pub fn check(mut self, ast: &[AstNode]) -> Vec<Notification> {
+        for node in ast {
+            self.check_node(node, false);
+        }
+
+        self.notifications
+    }
+
+    /// Walk the AST and check every call. `is_new` is true if the node is the constructor call
+    /// of a `new` expression.
+    fn check_node(&mut self, node: &AstNode, is_new: bool) {
+        // Depending on the context, `new` is parsed as unary operator or as a node of its own
+        match node {
+            AstNode::New { class, .. } => return self.check_node(class, true),
+            AstNode::Unary { token, expr } if token.t == TokenType::New => {
+                return self.check_node(expr, true)
+            }
+            AstNode::Call {
+                callee, parameters, ..
+            } => {
+                if let Some((callable, range)) = self.callees.callable(callee, is_new) {
+                    // Constructors are named after their class
+                    let name = if is_new {
+                        callee.name()
+                    } else {
+                        self.arena[callable].get().name().to_owned()
+                    };
+
+                    self.check_call(callable, &name, range, parameters);
+                }
+            }
+            _ => (),
+        }
+
+        for child in node.children() {
+            self.check_node(child, false);
+        }
+    }
+
+    fn check_call(
+        &mut self,
+        callable: NodeId,
+        name: &str,
+        range: NodeRange,
+        arguments: &[AstNode],
+    ) {
+        let callable_symbol = self.arena[callable].get();
+
+        // Implicit members like the methods of enums declare no parameters
+        if callable_symbol.is_implicit {
+            return;
+        }
+
+        let parameters = &callable_symbol.parameters;
+        let variadic = parameters.iter().position(is_variadic);
+
+        // A spread argument passes an unknown number of arguments
+        let spread = arguments.iter().any(|argument| {
+            matches!(argument, AstNode::Unary { token, .. } if token.t == TokenType::Elipsis)
+        });
+
+        let mut bound = vec![false; parameters.len()];
+        let mut pairs = Vec::new();
+
+        for (i, argument) in arguments.iter().enumerate() {
+            if let AstNode::NamedParameter {
+                name: argument_name,
+                expr,
+                ..
+            } = argument
+            {
+                let label = argument_name.label.as_deref().unwrap_or_default();
+
+                if let Some(index) = parameters
+                    .iter()
+                    .position(|parameter| self.parameter_name(parameter) == label)
+                {
+                    bound[index] = true;
+                    pairs.push((&parameters[index], expr.as_ref()));
+                } else if variadic.is_none() {
+                    self.report(
+                        self.options.unknown_named_argument,
+                        argument_name.range(),
+                        format!("Unknown named argument {} of {}", label, name),
+                    );
+                }
+
+                continue;
+            }
+
+            if spread {
+                continue;
+            }
+
+            match variadic {
+                Some(variadic) if i >= variadic => {
+                    bound[variadic] = true;
+                    pairs.push((&parameters[variadic], argument));
+                }
+                _ if i < parameters.len() => {
+                    bound[i] = true;
+                    pairs.push((&parameters[i], argument));
+                }
+                _ => {
+                    self.report(
+                        self.options.too_many_arguments,
+                        argument.range(),
+                        format!(
+                            "Too many arguments, {} expects at most {}",
+                            name,
+                            parameters.len()
+                        ),
+                    );
+
+                    break;
+                }
+            }
+        }
+
+        if !spread {
+            let missing = parameters
+                .iter()
+                .zip(bound.iter())
+                .filter(|(parameter, bound)| !**bound && is_required(parameter))
+                .map(|(parameter, _)| format!("${}", self.parameter_name(parameter)))
+                .collect::<Vec<String>>();
+
+            if !missing.is_empty() {
+                self.report(
+                    self.options.too_few_arguments,
+                    range,
+                    format!(
+                        "Too few arguments to {}, missing {}",
+                        name,
+                        missing.join(", ")
+                    ),
+                );
+            }
+        }
+
+        for (parameter, argument) in pairs {
+            self.check_type(parameter, argument);
+        }
+    }
+
+    /// Report an argument that clearly does not match the declared type of its parameter
+    fn check_type(&mut self, parameter: &FunctionParameter, argument: &AstNode) {
+        let (nullable, type_refs, default_value) = match &parameter.ast {
+            AstNode::FunctionArgument {
+                argument_type: Some(argument_type),
+                default_value,
+                ..
+            } => match argument_type.as_ref() {
+                AstNode::DataType {
+                    nullable,
+                    type_refs,
+                } => (nullable.is_some(), type_refs, default_value),
+                _ => return,
+            },
+            _ => return,
+        };
+
+        let argument_type = if let Some(argument_type) = self.argument_type(argument) {
+            argument_type
+        } else {
+            return;
+        };
+
+        // A default value of null makes the type implicitly nullable
+        let defaults_to_null = matches!(
+            default_value.as_deref(),
+            Some(AstNode::Literal(token)) if token.t == TokenType::Null
+        );
+        if let ArgumentType::Null = argument_type {
+            if nullable || defaults_to_null {
+                return;
+            }
+        }
+
+        let type_refs = type_refs
+            .iter()
+            .filter_map(|type_ref| match type_ref {
+                AstNode::TypeRef(type_ref) => Some(type_ref),
+                _ => None,
+            })
+            .collect::<Vec<&TypeRef>>();
+
+        let mut accepts = Accepts::No;
+        for type_ref in type_refs.iter() {
+            match self.accepts(type_ref, &argument_type, parameter.symbol) {
+                Accepts::Yes => return,
+                Accepts::Unknown => accepts = Accepts::Unknown,
+                Accepts::No => (),
+            }
+        }
+
+        if accepts == Accepts::No && !type_refs.is_empty() {
+            let declared = type_refs
+                .iter()
+                .map(|type_ref| type_ref.to_fqdn())
+                .collect::<Vec<String>>()
+                .join("|");
+
+            self.report(
+                self.options.argument_type,
+                argument.range(),
+                format!(
+                    "Argument of type {} is not compatible with parameter ${} of type {}{}",
+                    self.type_name(&argument_type),
+                    self.parameter_name(parameter),
+                    if nullable { "?" } else { "" },
+                    declared
+                ),
+            );
+        }
+    }
+
+    /// The type of an argument if it is a literal, an array, an instantiation or a variable of
+    /// a known class
+    fn argument_type(&mut self, argument: &AstNode) -> Option<ArgumentType> {
+        match argument {
+            AstNode::Literal(token) => match token.t {
+                TokenType::LongNumber | TokenType::HexNumber | TokenType::BinaryNumber => {
+                    Some(ArgumentType::Int)
+                }
+                TokenType::DecimalNumber | TokenType::ExponentialNumber => {
+                    Some(ArgumentType::Float)
+                }
+                TokenType::ConstantEncapsedString => Some(ArgumentType::String {
+                    numeric: token
+                        .label
+                        .as_ref()
+                        .map(|label| label.trim().parse::<f64>().is_ok())
+                        .unwrap_or(false),
+                }),
+                TokenType::True | TokenType::False => Some(ArgumentType::Bool),
+                TokenType::Null => Some(ArgumentType::Null),
+                _ => None,
+            },
+            // Interpolated strings might be numeric
+            AstNode::EncapsedString { .. } => Some(ArgumentType::String { numeric: true }),
+            AstNode::Array { .. } | AstNode::OldArray { .. } => Some(ArgumentType::Array),
+            AstNode::New { class, .. } => self.instantiated_class(class),
+            AstNode::Unary { token, expr } if token.t == TokenType::New => {
+                self.instantiated_class(expr)
+            }
+            AstNode::Variable(token) => {
+                let types = self.inferred_types.get(&token.range())?;
+
+                // Only a single class is clearly known
+                match types.as_slice() {
+                    [class] if is_class_like(self.arena[*class].get()) => {
+                        Some(ArgumentType::Object(*class))
+                    }
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    fn instantiated_class(&self, class: &AstNode) -> Option<ArgumentType> {
+        let class = match class {
+            AstNode::Call { callee, .. } => callee.as_ref(),
+            class => class,
+        };
+        self.callees
+            .referenced_at(class.range())
+            .map(|(_, node)| node)
+            .find(|node| is_class_like(self.arena[*node].get()))
+            .map(ArgumentType::Object)
+    }
+
+    /// Tell if a declared type accepts an argument. Scalars are compared in coercive typing
+    /// mode, as strict types are not taken into account.
+    fn accepts(&mut self, type_ref: &TypeRef, argument: &ArgumentType, context: NodeId) -> Accepts {
+        let name = type_ref.tip().map(str::to_lowercase).unwrap_or_default();
+
+        let accepted = match (type_ref.root_token_type(), argument) {
+            (TokenType::Mixed, _) => true,
+            (TokenType::TypeSelf | TokenType::Static | TokenType::Parent, _) => {
+                return Accepts::Unknown
+            }
+            (TokenType::Null, ArgumentType::Null) => true,
+            (
+                TokenType::TypeInt | TokenType::TypeFloat,
+                ArgumentType::String { numeric: false },
+            ) => false,
+            (
+                TokenType::TypeInt
+                | TokenType::TypeFloat
+                | TokenType::TypeString
+                | TokenType::TypeBool,
+                ArgumentType::Int
+                | ArgumentType::Float
+                | ArgumentType::String { .. }
+                | ArgumentType::Bool,
+            ) => true,
+            (TokenType::TypeString, ArgumentType::Object(class)) => {
+                self.has_method(*class, "__tostring")
+            }
+            (TokenType::TypeArray, ArgumentType::Array) => true,
+            (TokenType::TypeObject, ArgumentType::Object(_)) => true,
+            (TokenType::Callable, ArgumentType::String { .. } | ArgumentType::Array) => true,
+            (TokenType::Callable, ArgumentType::Object(class)) => {
+                self.arena[*class]
+                    .get()
+                    .name()
+                    .eq_ignore_ascii_case("closure")
+                    || self.has_method(*class, "__invoke")
+            }
+            (_, _) if type_ref.is_builtin() => false,
+            (_, ArgumentType::Bool) if name == "true" || name == "false" => true,
+            (_, ArgumentType::Array) if name == "iterable" => true,
+            (_, ArgumentType::Object(_)) if name == "iterable" => return Accepts::Unknown,
+            (_, ArgumentType::Object(class)) if name == "stringable" => {
+                self.has_method(*class, "__tostring")
+            }
+            (_, ArgumentType::Object(class)) if name == "unitenum" || name == "backedenum" => {
+                self.arena[*class].get().kind == PhpSymbolKind::Enum
+            }
+            (_, ArgumentType::Object(class)) => {
+                let declared = match self
+                    .callees
+                    .resolver
+                    .resolve_type_ref(type_ref, self.arena, &context, false)
+                {
+                    Some(declared) => declared,
+                    None => return Accepts::Unknown,
+                };
+
+                return is_subtype(*class, declared, &mut self.callees.resolver, self.arena);
+            }
+            // Scalars and arrays are no instances of classes, unless the name is not a class
+            (_, _) => {
+                if self
+                    .callees
+                    .resolver
+                    .resolve_type_ref(type_ref, self.arena, &context, false)
+                    .is_none()
+                {
+                    return Accepts::Unknown;
+                }
+
+                false
+            }
+        };
+
+        if accepted {
+            Accepts::Yes
+        } else {
+            Accepts::No
+        }
+    }
+
+    fn has_method(&mut self, class: NodeId, name: &str) -> bool {
+        self.arena[class]
+            .get()
+            .get_all_symbols(class, &mut self.callees.resolver, self.arena)
+            .contains_key(name)
+    }
+
+    fn parameter_name(&self, parameter: &FunctionParameter) -> String {
+        self.arena[parameter.symbol]
+            .get()
+            .name()
+            .trim_start_matches('$')
+            .to_owned()
+    }
+
+    fn type_name(&self, argument: &ArgumentType) -> String {
+        match argument {
+            ArgumentType::Int => String::from("int"),
+            ArgumentType::Float => String::from("float"),
+            ArgumentType::String { .. } => String::from("string"),
+            ArgumentType::Bool => String::from("bool"),
+            ArgumentType::Null => String::from("null"),
+            ArgumentType::Array => String::from("array"),
+            ArgumentType::Object(class) => self.arena[*class].get().name().to_owned(),
+        }
+    }
+
+    fn report(&mut self, severity: Option<DiagnosticSeverity>, range: NodeRange, message: String) {
+        if let Some(severity) = severity {
+            self.notifications.push(Notification {
+                file: self.file_name.clone(),
+                message,
+                range,
+                severity,
+                tags: Vec::new(),
+                code: None,
+            });
+        }
+    }
+}
+
+/// The parameter an argument at a position is passed to. Named arguments are passed to the
+/// parameter of the same name, the variadic parameter takes all remaining positional ones.
+pub fn parameter_of<'p>(
+    parameters: &'p [FunctionParameter],
+    index: usize,
+    argument: &AstNode,
+    arena: &Arena<Symbol>,
+) -> Option<&'p FunctionParameter> {
+    if let AstNode::NamedParameter { name, .. } = argument {
+        let name = name.label.as_deref()?;
+
+        return parameters.iter().find(|parameter| {
+            arena[parameter.symbol].get().name().trim_start_matches('$') == name
+        });
+    }
+
+    match parameters.iter().position(is_variadic) {
+        Some(variadic) if index >= variadic => parameters.get(variadic),
+        _ => parameters.get(index),
+    }
+}
+
+/// Tell if a class is the declared type or one of its descendants. If a supertype in between
+/// can not be resolved the answer is unknown.
+pub fn is_subtype(
+    class: NodeId,
+    declared: NodeId,
+    resolver: &mut NameResolver,
+    arena: &Arena<Symbol>,
+) -> Accepts {
+    let mut queue = vec![class];
+    let mut visited = Vec::new();
+    let mut unresolved = false;
+
+    while let Some(current) = queue.pop() {
+        if current == declared {
+            return Accepts::Yes;
+        }
+
+        if visited.contains(&current) {
+            continue;
+        }
+        visited.push(current);
+
+        let symbol = arena[current].get();
+        let supertypes = symbol
+            .inherits_from
+            .iter()
+            .flatten()
+            .chain(symbol.data_types.iter())
+            .map(|reference| reference.type_ref.clone())
+            .collect::<Vec<TypeRef>>();
+
+        for type_ref in supertypes {
+            match resolver.resolve_type_ref(&type_ref, arena, &current, false) {
+                Some(supertype) => queue.push(supertype),
+                None => unresolved = true,
+            }
+        }
+    }
+
+    if unresolved {
+        Accepts::Unknown
+    } else {
+        Accepts::No
+    }
+}
+
+pub fn is_variadic(parameter: &FunctionParameter) -> bool {
+    matches!(
+        &parameter.ast,
+        AstNode::FunctionArgument {
+            spread: Some(_),
+            ..
+        }
+    )
+}
+
+/// Parameters without default value must be passed, unless they are variadic
+pub fn is_required(parameter: &FunctionParameter) -> bool {
+    matches!(
+        &parameter.ast,
+        AstNode::FunctionArgument {
+            has_default: None,
+            spread: None,
+            ..
+        }
+    )
+}
+
+fn is_class_like(symbol: &Symbol) -> bool {
+    matches!(
+        symbol.kind,
+        PhpSymbolKind::Class | PhpSymbolKind::Interface | PhpSymbolKind::Enum
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::tests::populate_state;
+    use crate::backend::BackendState;
+
+    fn diagnostics(source: &str, options: DiagnosticOptions) -> Vec<(u32, String)> {
+        let mut state = BackendState {
+            diagnostic_options: DiagnosticOptions {
+                undefined_variable: None,
+                unused_variable: None,
+                ..options
+            },
+            ..BackendState::default()
+        };
+        populate_state(&mut state, &[("calls.php", source)]);
+
+        let mut diagnostics = state
+            .diagnostics
+            .remove("calls.php")
+            .unwrap_or_default()
+            .drain(..)
+            .map(|diagnostic| (diagnostic.range.start.line, diagnostic.message))
+            .collect::<Vec<(u32, String)>>();
+        diagnostics.sort();
+
+        diagnostics
+    }
+
+    #[test]
+    fn test_checks_argument_counts() {
+        let source = "<?php
+function first(int $a, string $b = '', ...$rest) {}
+function second(int $a) {}
+class User { public function __construct(string $name) {} }
+
+first(1, 'b', 3, 4);
+first();
+second(1, 2);
+second(b: 1);
+second(...[1, 2]);
+second(a: 1);
+new User();
+new User('name');
+";
+
+        assert_eq!(
+            vec![
+                (6, String::from("Too few arguments to first, missing $a")),
+                (
+                    7,
+                    String::from("Too many arguments, second expects at most 1")
+                ),
+                (8, String::from("Too few arguments to second, missing $a")),
+                (8, String::from("Unknown named argument b of second")),
+                (11, String::from("Too few arguments to User, missing $name")),
+            ],
+            diagnostics(source, DiagnosticOptions::default())
+        );
+
+        // Diagnostics can be turned off
+        let options = DiagnosticOptions {
+            too_few_arguments: None,
+            ..DiagnosticOptions::default()
+        };
+        assert_eq!(
+            vec![
+                (
+                    7,
+                    String::from("Too many arguments, second expects at most 1")
+                ),
+                (8, String::from("Unknown named argument b of second")),
+            ],
+            diagnostics(source, options)
+        );
+    }
+
+    #[test]
+    fn test_checks_argument_types() {
+        let source = "<?php
+interface Named {}
+class User implements Named {}
+class Admin extends User {}
+class Post {}
+
+function scalar(int $number, ?string $text = null) {}
+function accept(Named $named, $anything = null) {}
+
+scalar('12', 'text');
+scalar('twelve');
+scalar(1, null);
+scalar(null);
+accept(new Admin(), []);
+accept(new Post());
+$post = new Post();
+accept($post);
+accept([]);
+";
+
+        assert_eq!(
+            vec![
+                (
+                    10,
+                    String::from(
+                        "Argument of type string is not compatible with parameter $number of type int"
+                    )
+                ),
+                (
+                    12,
+                    String::from(
+                        "Argument of type null is not compatible with parameter $number of type int"
+                    )
+                ),
+                (
+                    14,
+                    String::from(
+                        "Argument of type Post is not compatible with parameter $named of type Named"
+                    )
+                ),
+                (
+                    16,
+                    String::from(
+                        "Argument of type Post is not compatible with parameter $named of type Named"
+                    )
+                ),
+                (
+                    17,
+                    String::from(
+                        "Argument of type array is not compatible with parameter $named of type Named"
+                    )
+                ),
+            ],
+            diagnostics(source, DiagnosticOptions::default())
+        );
+    }
+}
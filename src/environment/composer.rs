@@ -0,0 +1,335 @@
+use super::fs::normalize_lexically;
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+/// Autoload rules of composer.json and the installed packages
+#[derive(Clone, Debug, Default)]
+pub struct Autoload {
+    /// Namespace prefixes and the directories they map to according to PSR-4
+    pub psr4: Vec<(String, Vec<PathBuf>)>,
+
+    /// Namespace prefixes and the directories they map to according to PSR-0
+    pub psr0: Vec<(String, Vec<PathBuf>)>,
+
+    /// Files and directories that are scanned for classes
+    pub classmap: Vec<PathBuf>,
+
+    /// Files that are always included
+    pub files: Vec<PathBuf>,
+}
+
+impl Autoload {
+    /// Collect the rules of an `autoload` or `autoload-dev` section. Paths are relative
+    /// to `base`, the root of the package.
+    fn extend(&mut self, section: &Value, base: &Path) {
+        if let Some(psr4) = section.get("psr-4").and_then(Value::as_object) {
+            for (prefix, dirs) in psr4 {
+                self.psr4.push((prefix.to_owned(), paths(dirs, base)));
+            }
+        }
+
+        if let Some(psr0) = section.get("psr-0").and_then(Value::as_object) {
+            for (prefix, dirs) in psr0 {
+                self.psr0.push((prefix.to_owned(), paths(dirs, base)));
+            }
+        }
+
+        if let Some(classmap) = section.get("classmap") {
+            self.classmap.extend(paths(classmap, base));
+        }
+
+        if let Some(files) = section.get("files") {
+            self.files.extend(paths(files, base));
+        }
+    }
+}
+
+/// Composer project with the autoload rules of its root package and all installed packages
+#[derive(Clone, Debug, Default)]
+pub struct Composer {
+    pub autoload: Autoload,
+}
+
+impl Composer {
+    /// Read `composer.json` and `vendor/composer/installed.json` in `root`. Returns `None` if
+    /// the root does not contain a readable composer.json.
+    pub fn load(root: &Path, include_dev: bool) -> Option<Self> {
+        let manifest = read_json(&root.join("composer.json"))?;
+
+        let vendor_dir = root.join(
+            manifest
+                .pointer("/config/vendor-dir")
+                .and_then(Value::as_str)
+                .unwrap_or("vendor"),
+        );
+        let installed = read_json(&vendor_dir.join("composer").join("installed.json"));
+
+        Some(Self::from_manifests(
+            root,
+            &manifest,
+            &vendor_dir,
+            installed.as_ref(),
+            include_dev,
+        ))
+    }
+
+    /// Build the autoload rules from the already parsed manifests
+    pub fn from_manifests(
+        root: &Path,
+        manifest: &Value,
+        vendor_dir: &Path,
+        installed: Option<&Value>,
+        include_dev: bool,
+    ) -> Self {
+        let mut autoload = Autoload::default();
+
+        if let Some(section) = manifest.get("autoload") {
+            autoload.extend(section, root);
+        }
+
+        if include_dev {
+            if let Some(section) = manifest.get("autoload-dev") {
+                autoload.extend(section, root);
+            }
+        }
+
+        // Composer 1 stores a list of packages, composer 2 an object with a list of packages
+        let packages = installed.and_then(|installed| {
+            installed
+                .as_array()
+                .or_else(|| installed.get("packages").and_then(Value::as_array))
+        });
+
+        for package in packages.into_iter().flatten() {
+            let section = if let Some(section) = package.get("autoload") {
+                section
+            } else {
+                continue;
+            };
+
+            let base =
+                if let Some(install_path) = package.get("install-path").and_then(Value::as_str) {
+                    vendor_dir.join("composer").join(install_path)
+                } else if let Some(name) = package.get("name").and_then(Value::as_str) {
+                    vendor_dir.join(name)
+                } else {
+                    continue;
+                };
+
+            autoload.extend(section, &base);
+        }
+
+        Composer { autoload }
+    }
+
+    /// All files and directories that contain autoloadable code
+    pub fn autoloadable_paths(&self) -> Vec<PathBuf> {
+        let autoload = &self.autoload;
+
+        autoload
+            .psr4
+            .iter()
+            .chain(autoload.psr0.iter())
+            .flat_map(|(_, dirs)| dirs.iter())
+            .chain(autoload.classmap.iter())
+            .chain(autoload.files.iter())
+            .cloned()
+            .collect()
+    }
+
+    /// Returns true if the file is covered by any of the autoload rules
+    pub fn is_autoloadable(&self, file: &Path) -> bool {
+        self.autoloadable_paths()
+            .iter()
+            .any(|path| file.starts_with(path))
+    }
+
+    /// The files a class, interface, trait or enum is expected in according to the
+    /// PSR-4 and PSR-0 rules
+    pub fn expected_files(&self, fqdn: &str) -> Vec<PathBuf> {
+        let fqdn = fqdn.trim_start_matches('\\');
+        let mut files = Vec::new();
+
+        for (prefix, dirs) in self.autoload.psr4.iter() {
+            if let Some(relative) = fqdn.strip_prefix(prefix.as_str()) {
+                let relative = format!("{}.php", relative.replace('\\', "/"));
+
+                files.extend(dirs.iter().map(|dir| dir.join(&relative)));
+            }
+        }
+
+        for (prefix, dirs) in self.autoload.psr0.iter() {
+            if !fqdn.starts_with(prefix.as_str()) {
+                continue;
+            }
+
+            // Underscores in the class name (but not the namespace) are directory separators
+            let (namespace, class) = match fqdn.rfind('\\') {
+                Some(pos) => (&fqdn[..=pos], &fqdn[pos + 1..]),
+                None => ("", fqdn),
+            };
+            let relative = format!(
+                "{}{}.php",
+                namespace.replace('\\', "/"),
+                class.replace('_', "/")
+            );
+
+            files.extend(dirs.iter().map(|dir| dir.join(&relative)));
+        }
+
+        files
+    }
+
+    /// The FQDN a class declared in `file` should have according to PSR-4. Returns `None` if the
+    /// file is not within a PSR-4 directory.
+    pub fn expected_fqdn(&self, file: &Path) -> Option<String> {
+        // The longest matching directory is the most specific rule
+        let (prefix, relative) = self
+            .autoload
+            .psr4
+            .iter()
+            .flat_map(|(prefix, dirs)| dirs.iter().map(move |dir| (prefix, dir)))
+            .filter_map(|(prefix, dir)| Some((prefix, file.strip_prefix(dir).ok()?)))
+            .min_by_key(|(_, relative)| relative.components().count())?;
+
+        let relative = relative.with_extension("");
+        let relative = relative
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("\\");
+
+        Some(format!("{}{}", prefix, relative))
+    }
+}
+
+/// Convert a single path or a list of paths into paths relative to `base`
+fn paths(value: &Value, base: &Path) -> Vec<PathBuf> {
+    // Install paths are relative to vendor/composer, so the joined paths contain `..`
+    let join = |path: &str| {
+        if path.is_empty() {
+            normalize_lexically(base)
+        } else {
+            normalize_lexically(&base.join(path))
+        }
+    };
+
+    match value {
+        Value::String(path) => vec![join(path)],
+        Value::Array(list) => list.iter().filter_map(Value::as_str).map(join).collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn read_json(path: &Path) -> Option<Value> {
+    let content = std::fs::read_to_string(path).ok()?;
+
+    match serde_json::from_str(&content) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            eprintln!("Error reading {:?}: {}", path, e);
+
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn composer(include_dev: bool) -> Composer {
+        let manifest = json!({
+            "autoload": {
+                "psr-4": {
+                    "App\\": "src/",
+                    "App\\Legacy\\": ["lib/", "legacy/"]
+                },
+                "psr-0": { "Old_": "old/" },
+                "classmap": ["database/"],
+                "files": ["helpers.php"]
+            },
+            "autoload-dev": {
+                "psr-4": { "Tests\\": "tests/" }
+            }
+        });
+        let installed = json!({
+            "packages": [
+                {
+                    "name": "acme/log",
+                    "install-path": "../acme/log",
+                    "autoload": { "psr-4": { "Acme\\Log\\": "src" } }
+                },
+                { "name": "acme/empty" }
+            ]
+        });
+
+        Composer::from_manifests(
+            Path::new("/project"),
+            &manifest,
+            Path::new("/project/vendor"),
+            Some(&installed),
+            include_dev,
+        )
+    }
+
+    #[test]
+    fn test_collects_autoloadable_paths() {
+        let paths = composer(false).autoloadable_paths();
+
+        assert!(paths.contains(&PathBuf::from("/project/src/")));
+        assert!(paths.contains(&PathBuf::from("/project/legacy/")));
+        assert!(paths.contains(&PathBuf::from("/project/database/")));
+        assert!(paths.contains(&PathBuf::from("/project/helpers.php")));
+        assert!(paths.contains(&PathBuf::from("/project/vendor/acme/log/src")));
+        assert!(!paths.contains(&PathBuf::from("/project/tests/")));
+        assert!(
+            composer(false).is_autoloadable(Path::new("/project/vendor/acme/log/src/Logger.php"))
+        );
+
+        assert!(composer(true)
+            .autoloadable_paths()
+            .contains(&PathBuf::from("/project/tests/")));
+    }
+
+    #[test]
+    fn test_resolves_expected_files() {
+        let composer = composer(false);
+
+        assert_eq!(
+            vec![PathBuf::from("/project/src/Models/User.php")],
+            composer.expected_files("\\App\\Models\\User")
+        );
+        assert_eq!(
+            vec![
+                PathBuf::from("/project/src/Legacy/Thing.php"),
+                PathBuf::from("/project/lib/Thing.php"),
+                PathBuf::from("/project/legacy/Thing.php"),
+            ],
+            composer.expected_files("App\\Legacy\\Thing")
+        );
+        assert_eq!(
+            vec![PathBuf::from("/project/old/Old/Package/Name.php")],
+            composer.expected_files("Old_Package_Name")
+        );
+    }
+
+    #[test]
+    fn test_derives_expected_fqdn_from_path() {
+        let composer = composer(false);
+
+        assert_eq!(
+            Some("App\\Legacy\\Thing".to_owned()),
+            composer.expected_fqdn(Path::new("/project/lib/Thing.php"))
+        );
+        assert_eq!(
+            Some("App\\Models\\User".to_owned()),
+            composer.expected_fqdn(Path::new("/project/src/Models/User.php"))
+        );
+        assert_eq!(
+            None,
+            composer.expected_fqdn(Path::new("/project/database/seed.php"))
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use tower_lsp::lsp_types::DiagnosticSeverity;
+
+/// Severities of the diagnostics of the static analysis, configurable via the `diagnostics`
+/// section of the settings. A diagnostic set to `"off"` is not reported at all.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DiagnosticOptions {
+    /// A call passes fewer arguments than the callee requires
+    #[serde(deserialize_with = "severity")]
+    pub too_few_arguments: Option<DiagnosticSeverity>,
+
+    /// A call passes more arguments than a callee without variadic parameter accepts
+    #[serde(deserialize_with = "severity")]
+    pub too_many_arguments: Option<DiagnosticSeverity>,
+
+    /// A named argument does not match any parameter of the callee
+    #[serde(deserialize_with = "severity")]
+    pub unknown_named_argument: Option<DiagnosticSeverity>,
+
+    /// A literal or an object is passed to a parameter of an incompatible type
+    #[serde(deserialize_with = "severity")]
+    pub argument_type: Option<DiagnosticSeverity>,
+
+    /// A variable is read but never assigned within its function
+    #[serde(deserialize_with = "severity")]
+    pub undefined_variable: Option<DiagnosticSeverity>,
+
+    /// A variable or parameter is never read within its function
+    #[serde(deserialize_with = "severity")]
+    pub unused_variable: Option<DiagnosticSeverity>,
+
+    /// A concrete class does not implement all abstract and interface methods it inherits
+    #[serde(deserialize_with = "severity")]
+    pub missing_implementation: Option<DiagnosticSeverity>,
+
+    /// A method overrides a method of a parent or an interface with an incompatible signature
+    #[serde(deserialize_with = "severity")]
+    pub incompatible_signature: Option<DiagnosticSeverity>,
+}
+
+impl Default for DiagnosticOptions {
+    fn default() -> Self {
+        DiagnosticOptions {
+            too_few_arguments: Some(DiagnosticSeverity::ERROR),
+            too_many_arguments: Some(DiagnosticSeverity::WARNING),
+            unknown_named_argument: Some(DiagnosticSeverity::ERROR),
+            argument_type: Some(DiagnosticSeverity::WARNING),
+            undefined_variable: Some(DiagnosticSeverity::WARNING),
+            unused_variable: Some(DiagnosticSeverity::HINT),
+            missing_implementation: Some(DiagnosticSeverity::ERROR),
+            incompatible_signature: Some(DiagnosticSeverity::ERROR),
+        }
+    }
+}
+
+impl DiagnosticOptions {
+    /// Read the options from the settings sent by the client, falling back to the defaults
+    pub fn from_settings(settings: Option<&Value>) -> Self {
+        settings
+            .and_then(|settings| settings.get("diagnostics"))
+            .and_then(|options| serde_json::from_value(options.clone()).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Parse a severity by its name, `"off"` disables the diagnostic
+fn severity<'de, D>(deserializer: D) -> Result<Option<DiagnosticSeverity>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let name = String::deserialize(deserializer)?;
+
+    match name.as_str() {
+        "error" => Ok(Some(DiagnosticSeverity::ERROR)),
+        "warning" => Ok(Some(DiagnosticSeverity::WARNING)),
+        "information" => Ok(Some(DiagnosticSeverity::INFORMATION)),
+        "hint" => Ok(Some(DiagnosticSeverity::HINT)),
+        "off" => Ok(None),
+        _ => Err(serde::de::Error::unknown_variant(
+            &name,
+            &["error", "warning", "information", "hint", "off"],
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_options_from_settings() {
+        let settings = serde_json::json!({
+            "diagnostics": { "tooManyArguments": "off", "argumentType": "error" }
+        });
+
+        assert_eq!(
+            DiagnosticOptions {
+                too_many_arguments: None,
+                argument_type: Some(DiagnosticSeverity::ERROR),
+                ..DiagnosticOptions::default()
+            },
+            DiagnosticOptions::from_settings(Some(&settings))
+        );
+        assert_eq!(
+            DiagnosticOptions::default(),
+            DiagnosticOptions::from_settings(Some(
+                &serde_json::json!({ "diagnostics": { "argumentType": "loud" } })
+            ))
+        );
+    }
+}
@@ -1,4 +1,4 @@
-use std::{fs, path::Path, path::PathBuf};
+use std::{fs, path::Component, path::Path, path::PathBuf};
 use tokio::io;
 
 pub(crate) fn reindex_folder(dir: &Path, ignore: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
@@ -45,6 +45,23 @@ pub(crate) fn normalize_path(path: &Path) -> String {
     path.to_str().unwrap().to_owned()
 }
 
+/// Remove `.` and `..` from a path without touching the file system, as the file might not exist
+pub(crate) fn normalize_lexically(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            _ => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
 pub(crate) fn file_read_range(path: &str, start: u32, end: u32) -> String {
     let content = match fs::read_to_string(path) {
         Ok(content) => content,
@@ -4,16 +4,17 @@ use crate::{
     environment::symbol::{PhpSymbolKind, Symbol},
     parser::node::TypeRef,
 };
+use serde::{Deserialize, Serialize};
 use tower_lsp::lsp_types::{Position, Range};
 
 use super::get_range;
 
-#[derive(Clone, Debug, Default, PartialEq)]
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
 pub struct SymbolImport {
     pub path: TypeRef,
     pub alias: Option<Token>,
 }
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct SymbolImportBlock(Vec<SymbolImport>);
 
 impl SymbolImportBlock {
@@ -32,7 +33,7 @@ impl From<Vec<SymbolImport>> for SymbolImportBlock {
     }
 }
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub enum TraitUseAlteration {
     As {
         visibility: Option<Token>,
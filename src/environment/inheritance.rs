@@ -0,0 +1,767 @@
+use super::arguments::{is_required, is_subtype, is_variadic, Accepts};
+use super::diagnostics::DiagnosticOptions;
+use super::symbol::{PhpSymbolKind, Symbol, Visibility};
+use super::visitor::name_resolver::NameResolver;
+use super::Notification;
+use crate::formatter::{format_node, FormatterOptions};
+use crate::parser::node::{Node as AstNode, NodeRange, TypeRef};
+use indextree::{Arena, NodeId};
+use std::collections::HashMap;
+use std::path::Path;
+use tower_lsp::lsp_types::DiagnosticSeverity;
+
+/// Names of types that are no classes
+const BUILTIN_TYPES: [&str; 18] = [
+    "array", "bool", "callable", "false", "float", "int", "iterable", "mixed", "never", "null",
+    "object", "parent", "resource", "self", "static", "string", "true", "void",
+];
+
+/// Code of the notification about abstract methods a class does not implement
+pub const MISSING_IMPLEMENTATION: &str = "missing-implementation";
+
+/// A single type of a declared return type
+enum ReturnType {
+    Builtin(String),
+    Static,
+    /// A class, which is unknown if it could not be resolved
+    Class(Option<NodeId>),
+}
+
+/// Checks that concrete classes implement all abstract methods they inherit and that methods
+/// overriding the method of a parent or an interface keep a compatible signature
+pub struct InheritanceChecker<'a> {
+    arena: &'a Arena<Symbol>,
+    resolver: NameResolver<'a>,
+    options: &'a DiagnosticOptions,
+    file: NodeId,
+
+    /// Return types of the standard library are tentative, implementations may omit them
+    stubs: Option<&'a Path>,
+
+    notifications: Vec<Notification>,
+}
+
+impl<'a> InheritanceChecker<'a> {
+    pub fn new(
+        arena: &'a Arena<Symbol>,
+        global_symbols: &'a HashMap<String, NodeId>,
+        file: NodeId,
+        options: &'a DiagnosticOptions,
+        stubs: Option<&'a Path>,
+    ) -> Self {
+        InheritanceChecker {
+            arena,
+            resolver: NameResolver::new(global_symbols, file),
+            options,
+            file,
+            stubs,
+            notifications: Vec::new(),
+        }
+    }
+
+    /// Check all classes, enums and interfaces of the file and return the diagnostics
+    pub fn check(mut self) -> Vec<Notification> {
+        let arena = self.arena;
+
+        let classes = self
+            .file
+            .descendants(arena)
+            .filter(|node| {
+                matches!(
+                    arena[*node].get().kind,
+                    PhpSymbolKind::Class | PhpSymbolKind::Enum | PhpSymbolKind::Interface
+                )
+            })
+            .collect::<Vec<NodeId>>();
+
+        for class in classes {
+            let symbol = arena[class].get();
+
+            if (symbol.kind == PhpSymbolKind::Class && !symbol.is_abstract)
+                || symbol.kind == PhpSymbolKind::Enum
+            {
+                let missing = self
+                    .missing_methods(class)
+                    .iter()
+                    .map(|method| self.method_name(*method))
+                    .collect::<Vec<String>>();
+
+                if !missing.is_empty() {
+                    let kind = if symbol.kind == PhpSymbolKind::Enum {
+                        "Enum"
+                    } else {
+                        "Class"
+                    };
+
+                    self.report_with_code(
+                        self.options.missing_implementation,
+                        symbol.selection_range.into(),
+                        format!(
+                            "{} {} must implement {}",
+                            kind,
+                            symbol.name(),
+                            missing.join(", ")
+                        ),
+                        Some(MISSING_IMPLEMENTATION),
+                    );
+                }
+            }
+
+            let inherited = self.inherited_methods(class, &mut Vec::new());
+
+            for method in class.children(arena) {
+                let method_symbol = arena[method].get();
+
+                if method_symbol.kind != PhpSymbolKind::Method || method_symbol.is_implicit {
+                    continue;
+                }
+
+                if let Some(prototype) = inherited.get(&method_symbol.normalized_name()) {
+                    self.check_override(class, method, *prototype);
+                }
+            }
+        }
+
+        self.notifications
+    }
+
+    /// The abstract and interface methods a class inherits but does not implement, ordered by
+    /// the name of their class and their name
+    pub fn missing_methods(&mut self, class: NodeId) -> Vec<NodeId> {
+        let mut missing = self
+            .methods(class, &mut Vec::new())
+            .into_values()
+            .filter(|method| self.is_abstract(*method))
+            .collect::<Vec<NodeId>>();
+
+        missing.sort_by_key(|method| self.method_name(*method).to_lowercase());
+
+        missing
+    }
+
+    /// A stub implementing a method, with all class names of its signature fully qualified
+    pub fn stub(&mut self, method: NodeId, indent: &str) -> String {
+        let symbol = self.arena[method].get();
+
+        let parameters = symbol
+            .parameters
+            .iter()
+            .map(|parameter| self.parameter(&parameter.ast, method))
+            .collect::<Vec<String>>()
+            .join(", ");
+
+        let return_type = symbol
+            .return_type
+            .as_ref()
+            .map(|data_type| format!(": {}", self.type_name(data_type, method, true)))
+            .unwrap_or_default();
+
+        let visibility = match symbol.visibility {
+            Visibility::Protected => "protected",
+            _ => "public",
+        };
+        let modifier = if symbol.is_static { " static" } else { "" };
+
+        format!(
+            "{indent}{}{} function {}({}){}\n{indent}{{\n{indent}    // TODO: Implement {}()\n{indent}}}\n",
+            visibility,
+            modifier,
+            symbol.name(),
+            parameters,
+            return_type,
+            symbol.name(),
+            indent = indent
+        )
+    }
+
+    /// All methods of a class by their normalized name. Own methods take precedence over the
+    /// ones of used traits, which take precedence over inherited ones.
+    fn methods(&mut self, class: NodeId, path: &mut Vec<NodeId>) -> HashMap<String, NodeId> {
+        let arena = self.arena;
+        let mut methods = self.inherited_methods(class, path);
+
+        for (name, alias) in arena[class]
+            .get()
+            .get_imports(class, &mut self.resolver, arena)
+        {
+            let method = arena[alias.symbol].get();
+
+            if method.kind != PhpSymbolKind::Method {
+                continue;
+            }
+
+            // Abstract methods of traits are implemented by inherited methods
+            if method.is_abstract && methods.contains_key(&name) {
+                continue;
+            }
+
+            methods.insert(name, alias.symbol);
+        }
+
+        for child in class.children(arena) {
+            let symbol = arena[child].get();
+
+            if symbol.kind == PhpSymbolKind::Method {
+                methods.insert(symbol.normalized_name(), child);
+            }
+        }
+
+        methods
+    }
+
+    /// The methods a class inherits from its interfaces and its parent class. `path` holds the
+    /// classes currently visited, to not loop on circular inheritance.
+    fn inherited_methods(
+        &mut self,
+        class: NodeId,
+        path: &mut Vec<NodeId>,
+    ) -> HashMap<String, NodeId> {
+        let mut methods = HashMap::new();
+
+        if path.contains(&class) {
+            return methods;
+        }
+        path.push(class);
+
+        let arena = self.arena;
+        let symbol = arena[class].get();
+
+        let parents = symbol
+            .get_parent_nodes(class, &mut self.resolver, arena)
+            .into_values()
+            .collect::<Vec<NodeId>>();
+
+        // Interfaces extend their parents, classes list their interfaces among their types
+        let interfaces = if symbol.kind == PhpSymbolKind::Interface {
+            parents.clone()
+        } else {
+            symbol
+                .data_types
+                .iter()
+                .filter_map(|reference| {
+                    self.resolver
+                        .resolve_type_ref(&reference.type_ref, arena, &class, false)
+                })
+                .filter(|interface| {
+                    *interface != class && arena[*interface].get().kind == PhpSymbolKind::Interface
+                })
+                .collect()
+        };
+
+        for interface in interfaces {
+            for (name, method) in self.methods(interface, path) {
+                methods.entry(name).or_insert(method);
+            }
+        }
+
+        if symbol.kind != PhpSymbolKind::Interface {
+            for parent in parents {
+                let inherited = self.methods(parent, path);
+
+                methods.extend(inherited);
+            }
+        }
+
+        path.pop();
+
+        methods
+    }
+
+    fn check_override(&mut self, class: NodeId, method: NodeId, prototype: NodeId) {
+        let arena = self.arena;
+        let symbol = arena[method].get();
+        let prototype_symbol = arena[prototype].get();
+
+        // Private methods are not inherited
+        if prototype_symbol.visibility == Visibility::Private {
+            return;
+        }
+
+        let class_name = arena[class].get().name();
+        let name = format!("{}::{}()", class_name, symbol.name());
+        let prototype_name = self.method_name(prototype);
+        let range: NodeRange = symbol.selection_range.into();
+
+        if prototype_symbol.is_static != symbol.is_static {
+            self.report(
+                self.options.incompatible_signature,
+                range,
+                format!(
+                    "Cannot make {}static method {} {}static in class {}",
+                    if prototype_symbol.is_static {
+                        ""
+                    } else {
+                        "non "
+                    },
+                    prototype_name,
+                    if symbol.is_static { "" } else { "non " },
+                    class_name
+                ),
+            );
+        }
+
+        if symbol.visibility < prototype_symbol.visibility {
+            self.report(
+                self.options.incompatible_signature,
+                range,
+                if prototype_symbol.visibility == Visibility::Protected {
+                    format!(
+                        "Access level to {} must be protected (as in {}) or weaker",
+                        name, prototype_name
+                    )
+                } else {
+                    format!(
+                        "Access level to {} must be public (as in {})",
+                        name, prototype_name
+                    )
+                },
+            );
+        }
+
+        // Constructors only need to be compatible with abstract ones
+        if symbol.normalized_name() == "__construct" && !self.is_abstract(prototype) {
+            return;
+        }
+
+        let accepted = symbol.parameters.len();
+        let required = symbol.parameters.iter().filter(|p| is_required(p)).count();
+
+        if accepted < prototype_symbol.parameters.len()
+            && !symbol.parameters.iter().any(is_variadic)
+        {
+            self.report(
+                self.options.incompatible_signature,
+                range,
+                format!("{} accepts fewer parameters than {}", name, prototype_name),
+            );
+        } else if required
+            > prototype_symbol
+                .parameters
+                .iter()
+                .filter(|p| is_required(p))
+                .count()
+        {
+            self.report(
+                self.options.incompatible_signature,
+                range,
+                format!("{} requires more parameters than {}", name, prototype_name),
+            );
+        }
+
+        if self.is_from_stubs(prototype) {
+            return;
+        }
+
+        let prototype_type = if let Some(prototype_type) = prototype_symbol.return_type.as_ref() {
+            prototype_type
+        } else {
+            return;
+        };
+
+        let return_type = if let Some(return_type) = symbol.return_type.as_ref() {
+            return_type
+        } else {
+            let message = format!(
+                "{} must declare the return type {} of {}",
+                name,
+                self.type_name(prototype_type, prototype, false),
+                prototype_name
+            );
+            self.report(self.options.incompatible_signature, range, message);
+
+            return;
+        };
+
+        if !self.is_covariant(return_type, method, prototype_type, prototype) {
+            let message = format!(
+                "Return type {} of {} is not compatible with {} of {}",
+                self.type_name(return_type, method, false),
+                name,
+                self.type_name(prototype_type, prototype, false),
+                prototype_name
+            );
+            self.report(self.options.incompatible_signature, range, message);
+        }
+    }
+
+    /// Tell if every type a method may return is accepted by the return type of the method it
+    /// overrides. Types that can not be resolved are accepted.
+    fn is_covariant(
+        &mut self,
+        return_type: &AstNode,
+        method: NodeId,
+        prototype_type: &AstNode,
+        prototype: NodeId,
+    ) -> bool {
+        let (types, prototype_types) = match (
+            self.return_types(return_type, method),
+            self.return_types(prototype_type, prototype),
+        ) {
+            (Some(types), Some(prototype_types)) => (types, prototype_types),
+            _ => return true,
+        };
+
+        types.iter().all(|return_type| {
+            prototype_types
+                .iter()
+                .any(|prototype_type| self.is_subtype(return_type, prototype_type, method))
+        })
+    }
+
+    fn is_subtype(
+        &mut self,
+        return_type: &ReturnType,
+        declared: &ReturnType,
+        method: NodeId,
+    ) -> bool {
+        match (return_type, declared) {
+            (ReturnType::Builtin(name), _) if name == "never" => true,
+            (ReturnType::Builtin(name), ReturnType::Builtin(declared)) => {
+                name == declared
+                    || (declared == "mixed" && name != "void")
+                    || (declared == "bool" && (name == "true" || name == "false"))
+                    || (declared == "iterable" && (name == "array" || name == "generator"))
+            }
+            // Generators are instances of the Generator class
+            (ReturnType::Builtin(name), ReturnType::Class(_)) if name == "generator" => true,
+            (ReturnType::Builtin(_), _) => false,
+            (_, ReturnType::Builtin(declared)) => matches!(
+                declared.as_str(),
+                "mixed" | "object" | "iterable" | "callable"
+            ),
+            (ReturnType::Static, ReturnType::Static) => true,
+            (ReturnType::Class(None), _) | (_, ReturnType::Class(None)) => true,
+            (ReturnType::Class(Some(_)), ReturnType::Static) => false,
+            (ReturnType::Static, ReturnType::Class(Some(declared))) => {
+                let class = self.arena[method].parent();
+
+                class.is_none_or(|class| self.extends(class, *declared))
+            }
+            (ReturnType::Class(Some(class)), ReturnType::Class(Some(declared))) => {
+                self.extends(*class, *declared)
+            }
+        }
+    }
+
+    fn extends(&mut self, class: NodeId, declared: NodeId) -> bool {
+        is_subtype(class, declared, &mut self.resolver, self.arena) != Accepts::No
+    }
+
+    /// The types of a declared return type, `self` and `parent` resolved relative to the class
+    /// of the method. None if the type has an unknown shape.
+    fn return_types(&mut self, data_type: &AstNode, method: NodeId) -> Option<Vec<ReturnType>> {
+        let (nullable, type_refs) = match data_type {
+            AstNode::DataType {
+                nullable,
+                type_refs,
+            } => (nullable.is_some(), type_refs),
+            _ => return None,
+        };
+
+        let mut types = Vec::new();
+        if nullable {
+            types.push(ReturnType::Builtin(String::from("null")));
+        }
+
+        for type_ref in type_refs {
+            let type_ref = match type_ref {
+                AstNode::TypeRef(type_ref) => type_ref,
+                _ => return None,
+            };
+
+            let return_type = match builtin_name(type_ref).as_deref() {
+                Some("static") => ReturnType::Static,
+                Some("self") => ReturnType::Class(self.arena[method].parent()),
+                Some("parent") => {
+                    let arena = self.arena;
+
+                    ReturnType::Class(arena[method].parent().and_then(|class| {
+                        arena[class]
+                            .get()
+                            .get_unique_parent(class, &mut self.resolver, arena)
+                    }))
+                }
+                Some(name) => ReturnType::Builtin(name.to_owned()),
+                None => ReturnType::Class(
+                    self.resolver
+                        .resolve_type_ref(type_ref, self.arena, &method, false),
+                ),
+            };
+
+            types.push(return_type);
+        }
+
+        Some(types)
+    }
+
+    /// The source of a type, class names are fully qualified if `qualify` is true
+    fn type_name(&mut self, data_type: &AstNode, method: NodeId, qualify: bool) -> String {
+        let (nullable, type_refs) = match data_type {
+            AstNode::DataType {
+                nullable,
+                type_refs,
+            } => (nullable.is_some(), type_refs),
+            _ => return String::new(),
+        };
+
+        let names = type_refs
+            .iter()
+            .map(|type_ref| match type_ref {
+                AstNode::TypeRef(type_ref) if qualify && builtin_name(type_ref).is_none() => self
+                    .resolver
+                    .resolve_type_ref(type_ref, self.arena, &method, false)
+                    .map(|class| format!("\\{}", self.arena[class].get().fqdn()))
+                    .unwrap_or_else(|| type_ref.to_fqdn()),
+                AstNode::TypeRef(type_ref) => type_ref.to_fqdn(),
+                _ => String::new(),
+            })
+            .collect::<Vec<String>>()
+            .join("|");
+
+        if nullable {
+            format!("?{}", names)
+        } else {
+            names
+        }
+    }
+
+    /// The source of a parameter for a stub
+    fn parameter(&mut self, parameter: &AstNode, method: NodeId) -> String {
+        if let AstNode::FunctionArgument {
+            argument_type,
+            name,
+            default_value,
+            spread,
+            reference,
+            ..
+        } = parameter
+        {
+            let mut source = String::new();
+
+            if let Some(argument_type) = argument_type {
+                source.push_str(&self.type_name(argument_type, method, true));
+                source.push(' ');
+            }
+
+            if reference.is_some() {
+                source.push('&');
+            }
+
+            if spread.is_some() {
+                source.push_str("...");
+            }
+
+            source.push_str(&name.to_string());
+
+            if let Some(default_value) = default_value {
+                let options = FormatterOptions {
+                    max_line_length: 100,
+                    indent: 4,
+                };
+
+                source.push_str(" = ");
+                source.push_str(&format_node(default_value, 0, 0, &options));
+            }
+
+            source
+        } else {
+            String::new()
+        }
+    }
+
+    fn is_abstract(&self, method: NodeId) -> bool {
+        self.arena[method].get().is_abstract
+            || self.arena[method]
+                .parent()
+                .is_some_and(|class| self.arena[class].get().kind == PhpSymbolKind::Interface)
+    }
+
+    fn is_from_stubs(&self, node: NodeId) -> bool {
+        let stubs = if let Some(stubs) = self.stubs {
+            stubs
+        } else {
+            return false;
+        };
+
+        node.ancestors(self.arena)
+            .last()
+            .map(|file| Path::new(&self.arena[file].get().name).starts_with(stubs))
+            .unwrap_or(false)
+    }
+
+    /// The name of a method along with its class, like `User::name()`
+    fn method_name(&self, method: NodeId) -> String {
+        let class = self.arena[method]
+            .parent()
+            .map(|class| self.arena[class].get().name())
+            .unwrap_or_default();
+
+        format!("{}::{}()", class, self.arena[method].get().name())
+    }
+
+    fn report(&mut self, severity: Option<DiagnosticSeverity>, range: NodeRange, message: String) {
+        self.report_with_code(severity, range, message, None);
+    }
+
+    fn report_with_code(
+        &mut self,
+        severity: Option<DiagnosticSeverity>,
+        range: NodeRange,
+        message: String,
+        code: Option<&'static str>,
+    ) {
+        if let Some(severity) = severity {
+            self.notifications.push(Notification {
+                file: self.arena[self.file].get().name().to_owned(),
+                message,
+                range,
+                severity,
+                tags: Vec::new(),
+                code,
+            });
+        }
+    }
+}
+
+/// The lower case name of a type if it is no class
+fn builtin_name(type_ref: &TypeRef) -> Option<String> {
+    if type_ref.len() != 1 {
+        return None;
+    }
+
+    let name = type_ref.root_token().to_string().to_lowercase();
+
+    if BUILTIN_TYPES.contains(&name.as_str()) || type_ref.is_builtin() {
+        Some(name)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::backend::tests::populate_state;
+    use crate::backend::BackendState;
+    use crate::environment::diagnostics::DiagnosticOptions;
+
+    fn diagnostics(source: &str) -> Vec<(u32, String)> {
+        let mut state = BackendState {
+            diagnostic_options: DiagnosticOptions {
+                undefined_variable: None,
+                unused_variable: None,
+                ..DiagnosticOptions::default()
+            },
+            ..BackendState::default()
+        };
+        populate_state(&mut state, &[("inheritance.php", source)]);
+
+        let mut diagnostics = state
+            .diagnostics
+            .remove("inheritance.php")
+            .unwrap_or_default()
+            .drain(..)
+            .map(|diagnostic| (diagnostic.range.start.line, diagnostic.message))
+            .collect::<Vec<(u32, String)>>();
+        diagnostics.sort();
+
+        diagnostics
+    }
+
+    #[test]
+    fn test_reports_missing_implementations() {
+        let source = "<?php
+interface Shape { public function area(): float; }
+interface Named { public function name(): string; }
+abstract class Base implements Shape { abstract protected function draw(); }
+trait Naming { public function name(): string { return ''; } }
+trait Abstracts { abstract public function scale(int $factor); }
+class Square extends Base { }
+class Circle extends Base implements Named {
+    use Naming;
+    public function area(): float { return 1.0; }
+    protected function draw() {}
+}
+class Triangle { use Abstracts; }
+abstract class Partial implements Shape {}
+enum Suit implements Named { case Hearts; }
+";
+
+        assert_eq!(
+            vec![
+                (
+                    6,
+                    String::from("Class Square must implement Base::draw(), Shape::area()")
+                ),
+                (
+                    12,
+                    String::from("Class Triangle must implement Abstracts::scale()")
+                ),
+                (14, String::from("Enum Suit must implement Named::name()")),
+            ],
+            diagnostics(source)
+        );
+    }
+
+    #[test]
+    fn test_reports_incompatible_overrides() {
+        let source = "<?php
+interface Model {}
+class User implements Model {}
+class Repository {
+    public function find(int $id): Model { return new User(); }
+    protected function save(Model $model) {}
+    public static function create() {}
+    public function all(): ?array { return []; }
+}
+class UserRepository extends Repository {
+    public function find(int $id): User { return new User(); }
+    private function save(Model $model, $force) {}
+    public function create() {}
+    public function all() { return []; }
+}
+class Loose extends Repository {
+    public function find(): string { return ''; }
+    public function save(Model $model, ...$rest) {}
+    public function all(): array { return []; }
+}
+";
+
+        assert_eq!(
+            vec![
+                (
+                    11,
+                    String::from(
+                        "Access level to UserRepository::save() must be protected (as in Repository::save()) or weaker"
+                    )
+                ),
+                (
+                    11,
+                    String::from("UserRepository::save() requires more parameters than Repository::save()")
+                ),
+                (
+                    12,
+                    String::from(
+                        "Cannot make static method Repository::create() non static in class UserRepository"
+                    )
+                ),
+                (
+                    13,
+                    String::from(
+                        "UserRepository::all() must declare the return type ?array of Repository::all()"
+                    )
+                ),
+                (
+                    16,
+                    String::from("Loose::find() accepts fewer parameters than Repository::find()")
+                ),
+                (
+                    16,
+                    String::from(
+                        "Return type string of Loose::find() is not compatible with Model of Repository::find()"
+                    )
+                ),
+            ],
+            diagnostics(source)
+        );
+    }
+}
@@ -1,13 +1,18 @@
 use crate::environment::symbol::{PhpSymbolKind, Symbol};
 use crate::parser::node::NodeRange;
 use indextree::{Arena, NodeId};
-use tower_lsp::lsp_types::{DiagnosticSeverity, Location, Position, Range, Url};
+use tower_lsp::lsp_types::{DiagnosticSeverity, DiagnosticTag, Location, Position, Range, Url};
 
+pub mod arguments;
+pub mod composer;
+pub mod diagnostics;
 pub mod fs;
 pub mod import;
+pub mod inheritance;
 pub mod scope;
 pub mod symbol;
 pub mod traverser;
+pub mod variables;
 pub mod visitor;
 
 /// Find the definition or reference under the cursor
@@ -77,6 +82,10 @@ pub struct Notification {
     pub message: String,
     pub range: NodeRange,
     pub severity: DiagnosticSeverity,
+    pub tags: Vec<DiagnosticTag>,
+
+    /// Identifies the kind of the notification, for example for code actions fixing it
+    pub code: Option<&'static str>,
 }
 
 impl Notification {
@@ -86,6 +95,8 @@ impl Notification {
             message,
             range,
             severity: DiagnosticSeverity::ERROR,
+            tags: Vec::new(),
+            code: None,
         }
     }
 
@@ -95,6 +106,8 @@ impl Notification {
             message,
             range,
             severity: DiagnosticSeverity::WARNING,
+            tags: Vec::new(),
+            code: None,
         }
     }
 }
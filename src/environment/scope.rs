@@ -1,9 +1,10 @@
 use super::get_range;
 use crate::parser::node::TypeRef;
 use indextree::NodeId;
+use serde::{Deserialize, Serialize};
 use tower_lsp::lsp_types::Range;
 
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Reference {
     /// The type_ref if applicable
     pub type_ref: TypeRef,
@@ -11,6 +12,8 @@ pub struct Reference {
     /// Selection range of the usage
     pub range: Range,
 
+    /// Nodes are only known after name resolution, so they are not serialized
+    #[serde(skip)]
     pub node: Option<NodeId>,
 }
 
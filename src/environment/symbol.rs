@@ -5,11 +5,12 @@ use super::{
     visitor::name_resolver::NameResolver,
 };
 use crate::environment::scope::Reference;
-use crate::parser::node::Node as AstNode;
+use crate::parser::node::{DocType, Node as AstNode, TypeRef};
 use crate::parser::token::{Token, TokenType};
 use crate::{environment::fs::file_read_range, parser::node::NodeRange};
 use indextree::{Arena, NodeId};
 use lsp_types::SymbolTag;
+use serde::{Deserialize, Serialize};
 use std::{cmp::PartialOrd, collections::HashMap, fmt::Display};
 use tower_lsp::lsp_types::{
     CompletionItem, CompletionItemKind, CompletionItemTag, DocumentSymbol, InsertTextFormat,
@@ -17,7 +18,7 @@ use tower_lsp::lsp_types::{
 };
 
 /// An extension if the LSP-type "SymbolKind"
-#[derive(Debug, Eq, PartialEq, Copy, Clone)]
+#[derive(Debug, Eq, PartialEq, Copy, Clone, Serialize, Deserialize)]
 #[repr(u8)]
 pub enum PhpSymbolKind {
     File = 1,
@@ -27,6 +28,7 @@ pub enum PhpSymbolKind {
     Property = 7,
     Field = 8,
     Constructor = 9,
+    Enum = 10,
     Interface = 11,
     Function = 12,
     Variable = 13,
@@ -38,6 +40,7 @@ pub enum PhpSymbolKind {
     Object = 19,
     Key = 20,
     Null = 21,
+    EnumMember = 22,
     Operator = 25,
     Import = 26,
 
@@ -50,7 +53,7 @@ pub enum PhpSymbolKind {
     // Capturing all unknown enums by this lib.
     Unknown = 255,
 }
-#[derive(Clone, Debug, PartialEq, Copy)]
+#[derive(Clone, Debug, PartialEq, Copy, Serialize, Deserialize)]
 pub enum Visibility {
     None,
     Public,
@@ -127,6 +130,13 @@ impl FunctionParameter {
     }
 }
 
+/// A template parameter of a generic class or function, declared with `@template T of Bound`
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TemplateParameter {
+    pub name: String,
+    pub bound: Option<TypeRef>,
+}
+
 /// Struct to store the alias to a symbol, for instance the alias to a
 /// method name. This should also the only use case for now, methods that
 /// are equipped with an alias during inclusion via traits. The alias could
@@ -167,7 +177,8 @@ impl<'a> SymbolAlias<'a> {
 
 /// Contains information about a symbol in a scope. This can be a function, a class, a variable etc.
 /// It is bacially an extended `lsp_types::DocumentSymbol` that also contains a data type (for vars and properties)
-#[derive(Clone, Debug)]
+/// Symbols are cached on disk, bump `CACHE_SCHEMA_VERSION` of the index cache when changing its fields
+#[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Symbol {
     /// The data type of this symbol. Of course not all symbols have data types (Namespaces for example do not), so
     /// this remains an `Option`
@@ -196,17 +207,49 @@ pub struct Symbol {
     /// return types or implemented interfaces
     pub data_types: Vec<Reference>,
 
-    /// An optional set of parameters. This is mainly for methods and functions.
+    /// The structured type from the doc comment, like array{id: int, user: User}. The data
+    /// types only know the flattened type refs of it
+    #[serde(default)]
+    pub doc_type: Option<DocType>,
+
+    /// An optional set of parameters. This is mainly for methods and functions. They refer
+    /// to nodes in the arena, so they are not serialized along with the symbol.
+    #[serde(skip)]
     pub parameters: Vec<FunctionParameter>,
 
+    /// The return type a method declares in its signature, without the types of its doc
+    /// comment. Overriding methods have to stay compatible with it.
+    #[serde(default)]
+    pub return_type: Option<AstNode>,
+
     /// True if this value was declared static
     pub is_static: bool,
 
+    /// True if this property (or all properties of this class) can only be initialized once
+    pub is_readonly: bool,
+
+    /// True if this class or method was declared abstract
+    #[serde(default)]
+    pub is_abstract: bool,
+
     /// The visibility of the symbol
     pub visibility: Visibility,
 
     /// Can this symbol be used as an attribute?
     pub is_attribute: bool,
+
+    /// True if this symbol is not declared in the source but provided by the language,
+    /// like the `cases()` method of an enum
+    pub is_implicit: bool,
+
+    /// Template parameters of a generic class, interface, trait, method or function
+    #[serde(default)]
+    pub templates: Vec<TemplateParameter>,
+
+    /// Parents instantiated with type arguments by @extends, @implements or @use, binding
+    /// their templates
+    #[serde(default)]
+    pub generic_parents: Vec<Reference>,
 }
 
 impl Default for Symbol {
@@ -220,12 +263,19 @@ impl Default for Symbol {
             deprecated: None,
             inherits_from: None,
             data_types: Vec::new(),
+            doc_type: None,
             is_static: false,
+            is_readonly: false,
+            is_abstract: false,
             imports: None,
             import_resolutions: None,
             parameters: Vec::new(),
+            return_type: None,
             visibility: Visibility::None,
             is_attribute: false,
+            is_implicit: false,
+            templates: Vec::new(),
+            generic_parents: Vec::new(),
         }
     }
 }
@@ -240,6 +290,8 @@ impl PhpSymbolKind {
             PhpSymbolKind::Property => SymbolKind::PROPERTY,
             PhpSymbolKind::Field => SymbolKind::FIELD,
             PhpSymbolKind::Constructor => SymbolKind::CONSTRUCTOR,
+            PhpSymbolKind::Enum => SymbolKind::ENUM,
+            PhpSymbolKind::EnumMember => SymbolKind::ENUM_MEMBER,
             PhpSymbolKind::Interface => SymbolKind::INTERFACE,
             PhpSymbolKind::Function => SymbolKind::FUNCTION,
             PhpSymbolKind::Variable => SymbolKind::VARIABLE,
@@ -271,6 +323,7 @@ impl PhpSymbolKind {
             PhpSymbolKind::Class
                 | PhpSymbolKind::Trait
                 | PhpSymbolKind::Interface
+                | PhpSymbolKind::Enum
                 | PhpSymbolKind::Function
                 | PhpSymbolKind::Constant
         )
@@ -316,6 +369,20 @@ impl Symbol {
                 tags,
                 ..CompletionItem::default()
             },
+            PhpSymbolKind::Enum => CompletionItem {
+                label,
+                detail: Some(format!("enum {}\\{}", ns, self.name)),
+                kind: Some(CompletionItemKind::ENUM),
+                tags,
+                ..CompletionItem::default()
+            },
+            PhpSymbolKind::EnumMember => CompletionItem {
+                label,
+                detail: Some(format!("case {}", self.name)),
+                kind: Some(CompletionItemKind::ENUM_MEMBER),
+                tags,
+                ..CompletionItem::default()
+            },
             PhpSymbolKind::Constant => CompletionItem {
                 label,
                 detail: Some(format!("{} {}()", self.visibility, self.name)),
@@ -427,7 +494,10 @@ impl Symbol {
             let s = arena[c].get();
 
             // Ignore namespaces as they span the entire document but do not contain symbols
-            if s.kind == PhpSymbolKind::Namespace || s.kind == PhpSymbolKind::MagicConst {
+            if s.kind == PhpSymbolKind::Namespace
+                || s.kind == PhpSymbolKind::MagicConst
+                || s.is_implicit
+            {
                 continue;
             }
 
@@ -751,7 +821,7 @@ impl Symbol {
     pub fn to_doc_sym(&self, arena: &Arena<Symbol>, node: &NodeId) -> Option<DocumentSymbol> {
         let children = node
             .children(arena)
-            .filter(|s| !arena[*s].get().kind.is_internal())
+            .filter(|s| !arena[*s].get().kind.is_internal() && !arena[*s].get().is_implicit)
             .filter_map(|s| arena[s].get().to_doc_sym(arena, &s))
             .collect::<Vec<DocumentSymbol>>();
 
@@ -792,15 +862,29 @@ impl Symbol {
         }) {
             let doc = file_read_range(&file.name, self.range.start.line, self.range.end.line);
 
+            let doc_type = if let Some(doc_type) = self.doc_type.as_ref() {
+                format!(
+                    "
+# Type
+```php
+{}
+```
+",
+                    doc_type
+                )
+            } else {
+                String::new()
+            };
+
             format!(
-                "
+                "{}
 # Documentation
 ```php
 <?php
 {}
 ```
 ",
-                doc
+                doc_type, doc
             )
         } else {
             self.name.clone()
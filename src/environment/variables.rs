@@ -0,0 +1,850 @@
+use super::arguments::{parameter_of, Callees};
+use super::diagnostics::DiagnosticOptions;
+use super::symbol::{FunctionParameter, Symbol};
+use super::Notification;
+use crate::parser::node::{Node as AstNode, NodeRange};
+use crate::parser::token::{Token, TokenType};
+use indextree::{Arena, NodeId};
+use std::collections::HashMap;
+use tower_lsp::lsp_types::{DiagnosticSeverity, DiagnosticTag};
+
+/// Variables that are available in every scope without being assigned
+const SUPERGLOBALS: [&str; 13] = [
+    "this",
+    "GLOBALS",
+    "_GET",
+    "_POST",
+    "_SERVER",
+    "_COOKIE",
+    "_FILES",
+    "_ENV",
+    "_REQUEST",
+    "_SESSION",
+    "http_response_header",
+    "argc",
+    "argv",
+];
+
+/// What is known about a variable of a function scope
+#[derive(Default)]
+struct Variable {
+    /// The parameter declaring the variable
+    parameter: Option<NodeRange>,
+
+    /// Assignments that are reported if the variable is never read
+    assignments: Vec<NodeRange>,
+
+    /// Reads that are reported if the variable is never assigned
+    reads: Vec<NodeRange>,
+
+    is_assigned: bool,
+    is_read: bool,
+
+    /// Bound by reference, `global` or `static`, so it is written or read outside the scope
+    is_bound: bool,
+}
+
+#[derive(Default)]
+struct Scope {
+    variables: HashMap<String, Variable>,
+
+    /// Names of the parameters in order of their declaration
+    parameters: Vec<String>,
+
+    /// The top level of a file shares its variables with other files, so nothing is reported
+    is_file: bool,
+
+    /// Arrow functions read the variables of their enclosing scope
+    is_arrow: bool,
+
+    /// The parameters are required by the signature of a parent class or interface
+    keeps_parameters: bool,
+
+    /// Variables are written without being named, like by `extract()` or `$$name = 1`
+    has_dynamic_writes: bool,
+
+    /// Variables are read without being named, like by `get_defined_vars()` or `$$name`
+    has_dynamic_reads: bool,
+
+    /// The parameters are read by `func_get_args()` and friends
+    reads_arguments: bool,
+}
+
+/// Reports variables that are read but never assigned and variables that are assigned but never
+/// read. The check is flow insensitive, a variable is defined if it is assigned anywhere within
+/// its function.
+pub struct VariableChecker<'a> {
+    arena: &'a Arena<Symbol>,
+    callees: Callees<'a>,
+    options: &'a DiagnosticOptions,
+
+    scopes: Vec<Scope>,
+
+    /// True while visiting a class that extends or implements another one
+    in_subclass: bool,
+
+    file_name: String,
+    notifications: Vec<Notification>,
+}
+
+impl<'a> VariableChecker<'a> {
+    pub fn new(
+        arena: &'a Arena<Symbol>,
+        global_symbols: &'a HashMap<String, NodeId>,
+        file: NodeId,
+        references: &HashMap<NodeId, Vec<NodeRange>>,
+        options: &'a DiagnosticOptions,
+    ) -> Self {
+        VariableChecker {
+            arena,
+            callees: Callees::new(arena, global_symbols, file, references),
+            options,
+            scopes: Vec::new(),
+            in_subclass: false,
+            file_name: arena[file].get().name().to_owned(),
+            notifications: Vec::new(),
+        }
+    }
+
+    /// Check all functions within the ast and return the diagnostics
+    pub fn check(mut self, ast: &[AstNode]) -> Vec<Notification> {
+        self.scopes.push(Scope {
+            is_file: true,
+            ..Scope::default()
+        });
+
+        for node in ast {
+            self.visit(node, false);
+        }
+
+        self.pop_scope();
+
+        self.notifications.sort_by_key(|notification| {
+            (notification.range.start_line, notification.range.start_col)
+        });
+
+        self.notifications
+    }
+
+    /// Visit a node whose variables are read. `is_new` is true if the node is the constructor
+    /// call of a `new` expression.
+    fn visit(&mut self, node: &AstNode, is_new: bool) {
+        match node {
+            AstNode::Variable(token) => self.read(token, node.range(), false),
+            AstNode::AliasedVariable { expr, .. } | AstNode::DynamicVariable { expr, .. } => {
+                self.scope().has_dynamic_reads = true;
+                self.visit(expr, false);
+            }
+            AstNode::LexicalVariable { variable, .. } => self.bind(variable),
+            AstNode::New { class, .. } => self.visit(class, true),
+            AstNode::Unary { token, expr } => match token.t {
+                TokenType::New => self.visit(expr, true),
+                // Taking a reference, like `$a = &$b`, creates the variable
+                TokenType::BinaryAnd => self.bind_target(expr),
+                _ => self.visit(expr, false),
+            },
+            AstNode::StaticMember { object, member, .. } => {
+                self.visit(object, false);
+
+                // `A::$b` is a static property, not a local variable
+                if !matches!(member.as_ref(), AstNode::Variable(_)) {
+                    self.visit(member, false);
+                }
+            }
+            AstNode::Isset { parameters, .. } | AstNode::Empty { parameters, .. } => {
+                for parameter in parameters {
+                    self.check_existence(parameter);
+                }
+            }
+            AstNode::UnsetStatement { vars, .. } => {
+                for var in vars {
+                    self.check_existence(var);
+                }
+            }
+            AstNode::Binary { left, token, right } => match token.t {
+                TokenType::Assignment => {
+                    self.visit(right, false);
+                    self.assign(left);
+                }
+                TokenType::CoalesceAssignment => {
+                    self.check_existence(left);
+                    self.visit(right, false);
+                    self.assign(left);
+                }
+                TokenType::BinaryAndAssignment
+                | TokenType::BinaryOrAssignment
+                | TokenType::ModuloAssignment
+                | TokenType::ConcatAssignment
+                | TokenType::XorAssignment
+                | TokenType::RightShiftAssignment
+                | TokenType::LeftShiftAssignment
+                | TokenType::PowerAssignment
+                | TokenType::PlusAssign
+                | TokenType::MinusAssign
+                | TokenType::MulAssign
+                | TokenType::DivAssign => {
+                    self.visit(left, false);
+                    self.visit(right, false);
+                    self.assign(left);
+                }
+                TokenType::Coalesce => {
+                    self.check_existence(left);
+                    self.visit(right, false);
+                }
+                _ => {
+                    self.visit(left, false);
+                    self.visit(right, false);
+                }
+            },
+            AstNode::Call {
+                callee, parameters, ..
+            } => self.call(callee, parameters, is_new),
+            AstNode::ForEachStatement {
+                collection,
+                kv,
+                body,
+                ..
+            } => {
+                self.visit(collection, false);
+
+                if let AstNode::ArrayElement { key, value, .. } = kv.as_ref() {
+                    if let Some(key) = key {
+                        self.assign(key);
+                    }
+
+                    self.assign(value);
+                } else {
+                    self.assign(kv);
+                }
+
+                self.visit(body, false);
+            }
+            AstNode::CatchBlock { var, body, .. } => {
+                // The variable of a catch block is mandatory before PHP 8, so it is not reported
+                if let Some(name) = var.label.as_deref() {
+                    self.variable(name).is_assigned = true;
+                }
+
+                self.visit(body, false);
+            }
+            AstNode::StaticVariablesStatement { assignments, .. } => {
+                for assignment in assignments {
+                    if let AstNode::StaticVariable {
+                        variable, value, ..
+                    } = assignment
+                    {
+                        if let Some(value) = value {
+                            self.visit(value, false);
+                        }
+
+                        self.bind(variable);
+                    }
+                }
+            }
+            AstNode::GlobalVariablesStatement { vars, .. } => {
+                for var in vars {
+                    if let AstNode::Variable(token) = var {
+                        self.bind(token);
+                    } else {
+                        self.scope().has_dynamic_writes = true;
+                    }
+                }
+            }
+            AstNode::FileInclude { resource, .. } => {
+                // Included files share the scope they are included into
+                let scope = self.scope();
+                scope.has_dynamic_reads = true;
+                scope.has_dynamic_writes = true;
+
+                self.visit(resource, false);
+            }
+            AstNode::ClassStatement(statement) => {
+                let inherits = statement.extends.is_some() || statement.implements.is_some();
+
+                self.class(node, inherits);
+            }
+            AstNode::Class {
+                extends,
+                implements,
+                ..
+            } => self.class(node, extends.is_some() || implements.is_some()),
+            AstNode::EnumStatement { implements, .. } => self.class(node, implements.is_some()),
+            // Methods of traits may implement abstract methods of the classes using them
+            AstNode::TraitStatement { .. } => self.class(node, true),
+            AstNode::NamedFunctionDefinitionStatement { function, .. } => {
+                self.function(function, false)
+            }
+            AstNode::MethodDefinitionStatement { function, .. } => {
+                self.function(function, self.in_subclass)
+            }
+            AstNode::Function {
+                arguments,
+                uses,
+                body,
+                ..
+            } => {
+                let uses = uses.as_deref().unwrap_or_default();
+
+                // Variables captured by value are read when the closure is created
+                for lexical_variable in uses {
+                    if let AstNode::LexicalVariable {
+                        reference,
+                        variable,
+                    } = lexical_variable
+                    {
+                        if reference.is_some() {
+                            self.bind(variable);
+                        } else {
+                            self.read(variable, variable.into(), false);
+                        }
+                    }
+                }
+
+                self.scopes.push(Scope::default());
+                self.declare(arguments.as_deref().unwrap_or_default());
+
+                for lexical_variable in uses {
+                    if let AstNode::LexicalVariable { variable, .. } = lexical_variable {
+                        self.bind(variable);
+                    }
+                }
+
+                self.visit(body, false);
+                self.pop_scope();
+            }
+            AstNode::ArrowFunction {
+                arguments, body, ..
+            } => {
+                self.scopes.push(Scope {
+                    is_arrow: true,
+                    ..Scope::default()
+                });
+                self.declare(arguments.as_deref().unwrap_or_default());
+
+                self.visit(body, false);
+                self.pop_scope();
+            }
+            _ => {
+                for child in node.children() {
+                    self.visit(child, false);
+                }
+            }
+        }
+    }
+
+    /// Visit the target of an assignment
+    fn assign(&mut self, node: &AstNode) {
+        match node {
+            AstNode::Variable(token) => {
+                if let Some(name) = token.label.as_deref() {
+                    let variable = self.variable(name);
+
+                    variable.is_assigned = true;
+                    variable.assignments.push(node.range());
+                }
+            }
+            AstNode::Array { elements, .. }
+            | AstNode::OldArray { elements, .. }
+            | AstNode::List { elements, .. } => {
+                for element in elements {
+                    if let AstNode::ArrayElement { key, value, .. } = element {
+                        if let Some(key) = key {
+                            self.visit(key, false);
+                        }
+
+                        self.assign(value);
+                    } else {
+                        self.assign(element);
+                    }
+                }
+            }
+            // Writing into an array, like `$a[] = 1`, creates the array but is no reportable
+            // assignment of its own
+            AstNode::Field { array, index, .. } => {
+                if let Some(index) = index {
+                    self.visit(index, false);
+                }
+
+                if let AstNode::Variable(token) = array.as_ref() {
+                    if let Some(name) = token.label.as_deref() {
+                        self.variable(name).is_assigned = true;
+                    }
+                } else {
+                    self.assign(array);
+                }
+            }
+            AstNode::AliasedVariable { expr, .. } | AstNode::DynamicVariable { expr, .. } => {
+                self.scope().has_dynamic_writes = true;
+                self.visit(expr, false);
+            }
+            AstNode::Unary { token, expr } if token.t == TokenType::BinaryAnd => {
+                self.bind_target(expr)
+            }
+            _ => self.visit(node, false),
+        }
+    }
+
+    /// Visit an expression passed by reference, which is written and read elsewhere
+    fn bind_target(&mut self, node: &AstNode) {
+        match node {
+            AstNode::Variable(token) => self.bind(token),
+            AstNode::Field { array, index, .. } => {
+                if let Some(index) = index {
+                    self.visit(index, false);
+                }
+
+                self.bind_target(array);
+            }
+            _ => self.visit(node, false),
+        }
+    }
+
+    /// Visit an expression whose existence is checked, like within `isset()`, which reads it
+    /// without requiring it to be assigned
+    fn check_existence(&mut self, node: &AstNode) {
+        match node {
+            AstNode::Variable(token) => self.read(token, node.range(), true),
+            AstNode::Field { array, index, .. } => {
+                if let Some(index) = index {
+                    self.visit(index, false);
+                }
+
+                self.check_existence(array);
+            }
+            AstNode::Member { object, member, .. } => {
+                self.check_existence(object);
+                self.visit(member, false);
+            }
+            _ => self.visit(node, false),
+        }
+    }
+
+    /// Visit a call. Arguments passed by reference, like the matches of
+    /// `preg_match($pattern, $subject, $matches)`, are assigned by the callee.
+    fn call(&mut self, callee: &AstNode, arguments: &[AstNode], is_new: bool) {
+        if !is_new && matches!(callee, AstNode::TypeRef(_)) {
+            let name = callee.normalized_name();
+
+            match name.trim_start_matches('\\') {
+                "compact" => {
+                    for argument in arguments {
+                        self.compact(argument);
+                    }
+
+                    return;
+                }
+                "extract" => self.scope().has_dynamic_writes = true,
+                "get_defined_vars" => self.scope().has_dynamic_reads = true,
+                "func_get_args" | "func_get_arg" | "func_num_args" => {
+                    self.scope().reads_arguments = true
+                }
+                "eval" => {
+                    let scope = self.scope();
+                    scope.has_dynamic_reads = true;
+                    scope.has_dynamic_writes = true;
+                }
+                _ => (),
+            }
+        }
+
+        let arena = self.arena;
+        let parameters = self
+            .callees
+            .callable(callee, is_new)
+            .map(|(callable, _)| &arena[callable].get().parameters);
+
+        for (i, argument) in arguments.iter().enumerate() {
+            // Without knowing the callee, a variable might as well be passed by reference
+            let by_reference = if let Some(parameters) = parameters {
+                parameter_of(parameters, i, argument, arena).is_some_and(is_reference)
+            } else {
+                matches!(argument, AstNode::Variable(_))
+            };
+
+            let argument = match argument {
+                AstNode::NamedParameter { expr, .. } => expr.as_ref(),
+                _ => argument,
+            };
+
+            if by_reference {
+                self.bind_target(argument);
+            } else {
+                self.visit(argument, false);
+            }
+        }
+
+        self.visit(callee, false);
+    }
+
+    /// Visit an argument of `compact()`, which reads the variables named by it
+    fn compact(&mut self, argument: &AstNode) {
+        match argument {
+            AstNode::Literal(token) if token.t == TokenType::ConstantEncapsedString => {
+                if let Some(name) = token.label.as_deref() {
+                    self.read_name(name, argument.range(), false);
+                }
+            }
+            AstNode::Array { elements, .. } | AstNode::OldArray { elements, .. } => {
+                for element in elements {
+                    if let AstNode::ArrayElement { value, .. } = element {
+                        self.compact(value);
+                    }
+                }
+            }
+            _ => {
+                self.scope().has_dynamic_reads = true;
+                self.visit(argument, false);
+            }
+        }
+    }
+
+    /// Visit a class, `inherits` is true if its methods might implement the signature of a
+    /// parent
+    fn class(&mut self, node: &AstNode, inherits: bool) {
+        let in_subclass = std::mem::replace(&mut self.in_subclass, inherits);
+
+        for child in node.children() {
+            self.visit(child, false);
+        }
+
+        self.in_subclass = in_subclass;
+    }
+
+    /// Visit a named function or a method, abstract methods have no body to check
+    fn function(&mut self, function: &AstNode, keeps_parameters: bool) {
+        if let AstNode::FunctionDefinitionStatement {
+            arguments,
+            body: Some(body),
+            ..
+        } = function
+        {
+            self.scopes.push(Scope {
+                keeps_parameters,
+                ..Scope::default()
+            });
+            self.declare(arguments.as_deref().unwrap_or_default());
+
+            self.visit(body, false);
+            self.pop_scope();
+        }
+    }
+
+    /// Declare the parameters of the current scope
+    fn declare(&mut self, arguments: &[AstNode]) {
+        for argument in arguments {
+            if let AstNode::FunctionArgument {
+                name,
+                visibility,
+                is_readonly,
+                reference,
+                ..
+            } = argument
+            {
+                // Promoted parameters are properties and references are read by the caller
+                if visibility.is_some() || is_readonly.is_some() || reference.is_some() {
+                    self.bind(name);
+
+                    continue;
+                }
+
+                if let Some(label) = name.label.as_deref() {
+                    self.variable(label).parameter = Some(name.into());
+                    self.scope().parameters.push(label.to_owned());
+                }
+            }
+        }
+    }
+
+    fn read(&mut self, token: &Token, range: NodeRange, quiet: bool) {
+        if let Some(name) = token.label.as_deref() {
+            self.read_name(name, range, quiet);
+        }
+    }
+
+    /// Read a variable, reads that are `quiet` are not reported if the variable is undefined
+    fn read_name(&mut self, name: &str, range: NodeRange, quiet: bool) {
+        if SUPERGLOBALS.contains(&name) {
+            return;
+        }
+
+        let variable = self.variable(name);
+        variable.is_read = true;
+
+        if !quiet {
+            variable.reads.push(range);
+        }
+    }
+
+    fn bind(&mut self, token: &Token) {
+        if let Some(name) = token.label.as_deref() {
+            let variable = self.variable(name);
+
+            variable.is_assigned = true;
+            variable.is_read = true;
+            variable.is_bound = true;
+        }
+    }
+
+    fn scope(&mut self) -> &mut Scope {
+        self.scopes.last_mut().unwrap()
+    }
+
+    fn variable(&mut self, name: &str) -> &mut Variable {
+        self.scope().variables.entry(name.to_owned()).or_default()
+    }
+
+    /// Leave the current scope and report its variables
+    fn pop_scope(&mut self) {
+        let scope = self.scopes.pop().unwrap();
+
+        // Variables of an arrow function that are not local are the ones of the enclosing scope
+        if scope.is_arrow {
+            let parent = self.scope();
+
+            parent.has_dynamic_reads |= scope.has_dynamic_reads;
+            parent.has_dynamic_writes |= scope.has_dynamic_writes;
+
+            for (name, variable) in scope.variables.iter() {
+                if variable.is_assigned || variable.parameter.is_some() {
+                    continue;
+                }
+
+                let outer = parent.variables.entry(name.clone()).or_default();
+                outer.is_read |= variable.is_read;
+                outer.reads.extend(variable.reads.iter().copied());
+            }
+        }
+
+        if scope.is_file {
+            return;
+        }
+
+        // Parameters before a used one can not be removed without changing the signature
+        let last_read = scope
+            .parameters
+            .iter()
+            .rposition(|name| scope.variables[name].is_read);
+        let keeps_parameters =
+            scope.keeps_parameters || scope.reads_arguments || scope.has_dynamic_reads;
+
+        for (name, variable) in scope.variables.iter() {
+            if variable.is_bound {
+                continue;
+            }
+
+            if !variable.is_assigned && variable.parameter.is_none() && !scope.has_dynamic_writes {
+                // Reads of an arrow function have been handed to the enclosing scope
+                if !scope.is_arrow {
+                    for read in variable.reads.iter() {
+                        self.report(
+                            self.options.undefined_variable,
+                            *read,
+                            format!("Undefined variable ${}", name),
+                            Vec::new(),
+                        );
+                    }
+                }
+
+                continue;
+            }
+
+            if variable.is_read || scope.has_dynamic_reads {
+                continue;
+            }
+
+            if let Some(parameter) = variable.parameter {
+                let position = scope.parameters.iter().position(|p| p == name);
+
+                if !keeps_parameters && position > last_read {
+                    self.report(
+                        self.options.unused_variable,
+                        parameter,
+                        format!("Parameter ${} is never read", name),
+                        vec![DiagnosticTag::UNNECESSARY],
+                    );
+                }
+
+                continue;
+            }
+
+            for assignment in variable.assignments.iter() {
+                self.report(
+                    self.options.unused_variable,
+                    *assignment,
+                    format!("Variable ${} is assigned but never read", name),
+                    vec![DiagnosticTag::UNNECESSARY],
+                );
+            }
+        }
+    }
+
+    fn report(
+        &mut self,
+        severity: Option<DiagnosticSeverity>,
+        range: NodeRange,
+        message: String,
+        tags: Vec<DiagnosticTag>,
+    ) {
+        if let Some(severity) = severity {
+            self.notifications.push(Notification {
+                file: self.file_name.clone(),
+                message,
+                range,
+                severity,
+                tags,
+                code: None,
+            });
+        }
+    }
+}
+
+fn is_reference(parameter: &FunctionParameter) -> bool {
+    matches!(
+        &parameter.ast,
+        AstNode::FunctionArgument {
+            reference: Some(_),
+            ..
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::backend::tests::populate_state;
+    use crate::backend::BackendState;
+
+    fn diagnostics(source: &str, options: DiagnosticOptions) -> Vec<(u32, String)> {
+        let stubs = "<?php
+function preg_match($pattern, $subject, &$matches = null) { return $pattern . $subject; }
+function compact($name, ...$names) { return [$name, $names]; }
+function extract(array &$array) {}
+function get_defined_vars() {}
+function func_get_args() {}
+function array_sum(array $array) { return $array; }
+";
+        let mut state = BackendState {
+            diagnostic_options: options,
+            ..BackendState::default()
+        };
+        populate_state(
+            &mut state,
+            &[("stubs.php", stubs), ("variables.php", source)],
+        );
+
+        let mut diagnostics = state
+            .diagnostics
+            .remove("variables.php")
+            .unwrap_or_default()
+            .drain(..)
+            .map(|diagnostic| (diagnostic.range.start.line, diagnostic.message))
+            .collect::<Vec<(u32, String)>>();
+        diagnostics.sort();
+
+        diagnostics
+    }
+
+    #[test]
+    fn test_reports_undefined_variables() {
+        let source = "<?php
+$top = $unknown;
+function first($a) {
+    echo $a, $b;
+    $c = $d ?? 1;
+    if (isset($e) || empty($f)) { unset($g); }
+    static $h;
+    global $i;
+    echo $h, $i, $_GET, $GLOBALS, $c;
+}
+function second(array $list, callable $callback) {
+    foreach ($list as $key => $value) { echo $key, $value; }
+    [$j, 'k' => $k] = $list;
+    list(, $l) = $list;
+    preg_match('/x/', 'x', $matches);
+    $callback($out);
+    echo $j, $k, $l, $matches, $out, compact('m');
+}
+function third() {
+    $n = 1;
+    $closure = function ($o) use ($n, &$p, $q) { return $o + $n + $p + $q + $r; };
+    $arrow = fn($s) => $s + $n + $t;
+    return [$closure, $arrow];
+}
+function fourth() {
+    extract(['u' => 1]);
+    echo $u;
+}
+function fifth() {
+    try {} catch (Exception $exception) {}
+    echo $this, $v;
+}
+";
+
+        assert_eq!(
+            vec![
+                (3, String::from("Undefined variable $b")),
+                (16, String::from("Undefined variable $m")),
+                (20, String::from("Undefined variable $q")),
+                (20, String::from("Undefined variable $r")),
+                (21, String::from("Undefined variable $t")),
+                (30, String::from("Undefined variable $v")),
+            ],
+            diagnostics(source, DiagnosticOptions::default())
+        );
+
+        // Diagnostics can be turned off
+        let options = DiagnosticOptions {
+            undefined_variable: None,
+            ..DiagnosticOptions::default()
+        };
+        assert!(diagnostics(source, options).is_empty());
+    }
+
+    #[test]
+    fn test_reports_unused_variables() {
+        let source = "<?php
+$top = 1;
+function first($a, $b, $c, &$d) {
+    $e = 1;
+    $f = 2;
+    $f .= 'x';
+    $g[] = 1;
+    foreach ([] as $h) {}
+    return $b;
+}
+function second($a) {
+    $b = 1;
+    return get_defined_vars();
+}
+function third() {
+    return array_sum(func_get_args());
+}
+interface Named { function name($a); }
+class User implements Named {
+    public function __construct(private string $name, $a) {}
+    public function name($a) { return $this->name; }
+}
+class Post {
+    public function __construct(private string $title, $a) {}
+    public function title($a) {}
+}
+function fourth() {
+    $a = 1;
+    $b = fn($c) => $a;
+    return $b;
+}
+";
+
+        assert_eq!(
+            vec![
+                (2, String::from("Parameter $c is never read")),
+                (3, String::from("Variable $e is assigned but never read")),
+                (7, String::from("Variable $h is assigned but never read")),
+                (23, String::from("Parameter $a is never read")),
+                (24, String::from("Parameter $a is never read")),
+                (28, String::from("Parameter $c is never read")),
+            ],
+            diagnostics(source, DiagnosticOptions::default())
+        );
+    }
+}
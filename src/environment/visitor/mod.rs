@@ -19,6 +19,20 @@ macro_rules! ref_from_doc {
     };
 }
 
+macro_rules! doc_type_from_doc {
+    ($source:ident, $part:ident) => {
+        if let Some(doc_comment) = $source.as_ref() {
+            if let AstNode::DocComment { $part, .. } = doc_comment.as_ref() {
+                $part.iter().find_map(get_doc_type)
+            } else {
+                None
+            }
+        } else {
+            None
+        }
+    };
+}
+
 macro_rules! deprecated_from_doc {
     ($source:ident) => {
         if let Some(doc_comment) = $source.as_ref() {
@@ -37,6 +51,52 @@ macro_rules! deprecated_from_doc {
     };
 }
 
+macro_rules! templates_from_doc {
+    ($source:ident) => {
+        if let Some(doc_comment) = $source.as_ref() {
+            if let AstNode::DocComment { templates, .. } = doc_comment.as_ref() {
+                templates
+                    .iter()
+                    .filter_map(|template| {
+                        if let AstNode::DocCommentTemplate { name, bound } = template {
+                            Some(TemplateParameter {
+                                name: name.to_string(),
+                                bound: bound.clone(),
+                            })
+                        } else {
+                            None
+                        }
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            }
+        } else {
+            Vec::new()
+        }
+    };
+}
+
+macro_rules! generic_parents_from_doc {
+    ($source:ident) => {
+        if let Some(doc_comment) = $source.as_ref() {
+            if let AstNode::DocComment {
+                generic_parents, ..
+            } = doc_comment.as_ref()
+            {
+                generic_parents
+                    .iter()
+                    .map(|tr| Reference::type_ref(tr.clone()))
+                    .collect()
+            } else {
+                Vec::new()
+            }
+        } else {
+            Vec::new()
+        }
+    };
+}
+
 pub mod name_resolver;
 pub mod workspace_symbol;
 
@@ -1,10 +1,12 @@
 use super::{super::PhpSymbolKind, Symbol};
 use super::{workspace_symbol::get_type_ref, Visitor};
 use super::{workspace_symbol::get_type_refs, NextAction};
+use crate::environment::symbol::{TemplateParameter, Visibility};
+use crate::environment::traverser::traverse;
 use crate::environment::{scope::Reference as SymbolReference, Notification};
-use crate::parser::node::{Node as AstNode, NodeRange, TypeRef};
+use crate::parser::node::ClassStatement;
+use crate::parser::node::{DocType, Node as AstNode, NodeRange, TypeRef};
 use crate::parser::token::{Token, TokenType};
-use crate::{environment::symbol::Visibility, parser::node::ClassStatement};
 use indextree::{Arena, NodeId};
 use std::collections::HashMap;
 use tower_lsp::lsp_types::DiagnosticSeverity;
@@ -26,12 +28,30 @@ impl Reference {
 
 type UniqueNodeRange = (NodeId, NodeRange);
 
+/// Types of local variables at a certain point of the control flow, by variable name
+pub type LocalTypes = HashMap<String, Vec<NodeId>>;
+
+/// Types bound to templates, by the symbol declaring the template and the name of the template
+pub type TemplateBindings = HashMap<(NodeId, String), NodeId>;
+
 pub struct NameResolver<'a> {
     global_scope: &'a HashMap<String, NodeId>,
 
     /// Contains locally defined variables and functions
     local_scopes: Vec<HashMap<String, NodeId>>,
 
+    /// Types of the local variables inferred from assignments and narrowing, following the
+    /// control flow. Variables without an entry fall back to the data types of their symbol
+    local_types: Vec<LocalTypes>,
+
+    /// Inferred types of variables at the locations they are used at, and of members returning
+    /// a template type at the locations they are accessed at
+    inferred_types: HashMap<NodeRange, Vec<NodeId>>,
+
+    /// Template bindings of variables that were assigned an instance of a generic type, by the
+    /// symbol of the variable
+    bindings: HashMap<NodeId, TemplateBindings>,
+
     /// Contains a stack of references to $this
     current_class: Option<NodeId>,
 
@@ -52,6 +72,9 @@ impl<'a> NameResolver<'a> {
         NameResolver {
             global_scope,
             local_scopes: vec![HashMap::new()],
+            local_types: vec![HashMap::new()],
+            inferred_types: HashMap::new(),
+            bindings: HashMap::new(),
             current_class: None,
             document_references: HashMap::new(),
             scope_container,
@@ -78,12 +101,20 @@ impl<'a> NameResolver<'a> {
             message,
             range,
             severity,
+            tags: Vec::new(),
+            code: None,
         });
     }
 
+    /// Return the inferred types of variables at the locations they are used at
+    pub fn inferred_types(&self) -> HashMap<NodeRange, Vec<NodeId>> {
+        self.inferred_types.clone()
+    }
+
     /// Enter a new scope
     pub fn push_scope(&mut self) {
         self.local_scopes.push(HashMap::new());
+        self.local_types.push(HashMap::new());
     }
 
     /// Enter a new scope and prefill it with the current scope
@@ -95,11 +126,44 @@ impl<'a> NameResolver<'a> {
         };
 
         self.local_scopes.push(new);
+        self.local_types.push(self.local_types());
     }
 
     /// Leave the current scope and discard it
     pub fn pop_scope(&mut self) {
         self.local_scopes.pop();
+        self.local_types.pop();
+    }
+
+    /// Return the inferred types of a local variable at the current point of the control flow
+    pub fn get_local_types(&self, token: &Token) -> Option<&Vec<NodeId>> {
+        self.local_types.last()?.get(token.label.as_ref()?)
+    }
+
+    /// Set the inferred types of a local variable. Without any types the variable falls back
+    /// to the data types of its symbol
+    pub fn set_local_types(&mut self, token: &Token, types: Vec<NodeId>) {
+        if let (Some(top_scope), Some(label)) = (self.local_types.last_mut(), token.label.as_ref())
+        {
+            if types.is_empty() {
+                top_scope.remove(label);
+            } else {
+                top_scope.insert(label.clone(), types);
+            }
+        }
+    }
+
+    /// Return a copy of the inferred types of the current scope, to follow a branch of the
+    /// control flow and restore the types afterwards
+    pub fn local_types(&self) -> LocalTypes {
+        self.local_types.last().cloned().unwrap_or_default()
+    }
+
+    /// Replace the inferred types of the current scope
+    pub fn restore_local_types(&mut self, types: LocalTypes) {
+        if let Some(top_scope) = self.local_types.last_mut() {
+            *top_scope = types;
+        }
     }
 
     /// Enter a new class
@@ -139,6 +203,15 @@ impl<'a> NameResolver<'a> {
         self.reference(file, Reference::new(token.range(), *node))
     }
 
+    /// Register a new reference to a local variable and remember its inferred types at this location
+    pub fn reference_variable(&mut self, file: NodeId, token: &Token, node: &NodeId) {
+        if let Some(types) = self.get_local_types(token) {
+            self.inferred_types.insert(token.range(), types.clone());
+        }
+
+        self.reference_local(file, token, node);
+    }
+
     /// Register a new reference to a symbol
     pub fn reference(&mut self, file: NodeId, reference: Reference) {
         self.document_references
@@ -217,6 +290,11 @@ impl<'a> NameResolver<'a> {
             return None;
         }
 
+        // Templates are substituted by the caller, they are not types of their own
+        if template_of(type_ref, *context_anchor, arena).is_some() {
+            return None;
+        }
+
         let fully_qualified = type_ref.is_fully_qualified();
 
         let file = context_anchor.ancestors(arena).last().unwrap();
@@ -374,6 +452,10 @@ impl<'a, 'b: 'a> NameResolveVisitor<'a, 'b> {
         self.resolver.references()
     }
 
+    pub fn inferred_types(&self) -> HashMap<NodeRange, Vec<NodeId>> {
+        self.resolver.inferred_types()
+    }
+
     pub fn diagnostics(&self) -> std::slice::Iter<'_, Notification> {
         // TODO: Rather return an iterator?
         self.resolver.diagnostics.iter()
@@ -431,7 +513,9 @@ impl<'a, 'b: 'a> Visitor for NameResolveVisitor<'a, 'b> {
                     NextAction::Abort
                 }
             }
-            AstNode::TraitStatement { name, .. } | AstNode::Interface { name, .. } => {
+            AstNode::TraitStatement { name, .. }
+            | AstNode::Interface { name, .. }
+            | AstNode::EnumStatement { name, .. } => {
                 // Register $this in the current scope
                 if let Some(current_class) =
                     self.resolver
@@ -446,15 +530,18 @@ impl<'a, 'b: 'a> Visitor for NameResolveVisitor<'a, 'b> {
             }
             AstNode::MethodDefinitionStatement { doc_comment, .. }
             | AstNode::FunctionDefinitionStatement { doc_comment, .. } => {
-                // Is there a doc comment?
+                // Is there a doc comment? Its types may refer to the templates of the function,
+                // which became the scope container already
                 if let Some(doc_comment) = doc_comment {
                     if let AstNode::DocComment { return_type, .. } = doc_comment.as_ref() {
+                        let function = self.resolver.scope_container;
+
                         return_type
                             .iter()
                             .flat_map(get_type_ref)
                             .for_each(|type_ref| {
                                 self.resolver
-                                    .resolve_type_ref(&type_ref, arena, &parent, true);
+                                    .resolve_type_ref(&type_ref, arena, &function, true);
                             });
                     }
                 }
@@ -470,7 +557,7 @@ impl<'a, 'b: 'a> Visitor for NameResolveVisitor<'a, 'b> {
 
             AstNode::Variable(token) => {
                 if let Some(node) = self.resolver.get_local(token) {
-                    self.resolver.reference_local(self.file, token, &node);
+                    self.resolver.reference_variable(self.file, token, &node);
                 } else {
                     let child = arena.new_node(Symbol::from(token));
 
@@ -480,7 +567,12 @@ impl<'a, 'b: 'a> Visitor for NameResolveVisitor<'a, 'b> {
 
                 NextAction::Abort
             }
-            AstNode::DocCommentVar { name, types, .. } => {
+            AstNode::DocCommentVar {
+                name,
+                types,
+                doc_type,
+                ..
+            } => {
                 let mut data_types = Vec::new();
                 if let Some(types) = types {
                     for type_ref in types {
@@ -495,13 +587,24 @@ impl<'a, 'b: 'a> Visitor for NameResolveVisitor<'a, 'b> {
                     }
                 }
 
+                // Collections are only known by their symbol, flow types are single instances
+                let types = data_types
+                    .iter()
+                    .filter(|reference| !reference.type_ref.is_multiple())
+                    .filter_map(|reference| reference.node)
+                    .collect();
+                self.resolver.set_local_types(name, types);
+
                 let child = if let Some(existing) = self.resolver.get_local(name) {
-                    arena[existing].get_mut().data_types.extend(data_types);
+                    let variable = arena[existing].get_mut();
+                    variable.data_types.extend(data_types);
+                    variable.doc_type = doc_type.clone();
 
                     existing
                 } else {
                     arena.new_node(Symbol {
                         data_types,
+                        doc_type: doc_type.clone(),
                         ..Symbol::from(name)
                     })
                 };
@@ -535,53 +638,145 @@ impl<'a, 'b: 'a> Visitor for NameResolveVisitor<'a, 'b> {
             AstNode::ForEachStatement { collection, kv, .. } => {
                 let collection = if let AstNode::Variable(collection) = collection.as_ref() {
                     if let Some(collection) = self.resolver.get_local(collection) {
-                        arena[collection].get()
+                        collection
                     } else {
                         // Not resolvable .. too bad
-                        return NextAction::ProcessChildren(parent);
+                        return self.traverse_loop(node, arena, parent);
                     }
                 } else if let AstNode::Call { callee, .. } = collection.as_ref() {
                     // For some reason many symbols get referenced too often
                     if let Some(node) = self.resolve_member_type(callee, arena) {
                         // Resolved the type of the call
-                        arena[node].get()
+                        node
                     } else {
-                        return NextAction::ProcessChildren(parent);
+                        return self.traverse_loop(node, arena, parent);
                     }
                 } else {
                     // Not a variable, so no easy way (yet) of determing the type. Later on we will have a
                     // way of getting the return types of expressions
-                    return NextAction::ProcessChildren(parent);
+                    return self.traverse_loop(node, arena, parent);
                 };
 
                 if let AstNode::ArrayElement { value, .. } = kv.as_ref() {
                     if let AstNode::Variable(item) = value.as_ref() {
-                        let data_types = collection
-                            .data_types
+                        // The doc type knows the items of nested collections and shapes
+                        let item_type = arena[collection]
+                            .get()
+                            .doc_type
+                            .as_ref()
+                            .and_then(DocType::value_type)
+                            .cloned();
+
+                        let data_types: Vec<SymbolReference> =
+                            if let Some(item_type) = item_type.as_ref() {
+                                item_type
+                                    .type_refs()
+                                    .into_iter()
+                                    .map(|type_ref| {
+                                        match self.resolver.resolve_type_ref(
+                                            &type_ref,
+                                            arena,
+                                            &collection,
+                                            false,
+                                        ) {
+                                            Some(node) => SymbolReference::node(type_ref, node),
+                                            None => SymbolReference::type_ref(type_ref),
+                                        }
+                                    })
+                                    .collect()
+                            } else {
+                                arena[collection]
+                                    .get()
+                                    .data_types
+                                    .iter()
+                                    .map(|reference| match reference.node {
+                                        Some(node) if reference.type_ref.is_multiple() => {
+                                            SymbolReference::node(
+                                                reference.type_ref.to_collection_item(),
+                                                node,
+                                            )
+                                        }
+                                        _ => reference.clone(),
+                                    })
+                                    .collect()
+                            };
+
+                        // Collections are only known by their symbol, flow types are single instances
+                        let types = data_types
                             .iter()
-                            .map(|reference| {
-                                if reference.type_ref.is_multiple() && reference.node.is_some() {
-                                    SymbolReference::node(
-                                        reference.type_ref.to_collection_item(),
-                                        reference.node.unwrap(),
-                                    )
-                                } else {
-                                    reference.clone()
-                                }
-                            })
+                            .filter(|reference| !reference.type_ref.is_multiple())
+                            .filter_map(|reference| reference.node)
                             .collect();
 
                         let mut s = Symbol::from(item);
                         s.data_types = data_types;
+                        s.doc_type = item_type;
                         let child = arena.new_node(s);
 
                         self.resolver.scope_container.append(child, arena);
                         self.resolver
                             .declare_or_overwrite_local(self.file, item, child, false);
+                        self.resolver.set_local_types(item, types);
                     }
                 }
 
-                NextAction::ProcessChildren(parent)
+                self.traverse_loop(node, arena, parent)
+            }
+            AstNode::WhileStatement { .. } | AstNode::ForStatement { .. } => {
+                self.traverse_loop(node, arena, parent)
+            }
+            AstNode::IfStatement {
+                if_branch,
+                elseif_branches,
+                else_branch,
+            } => {
+                let mut outcomes = Vec::new();
+
+                for branch in std::iter::once(if_branch.as_ref()).chain(elseif_branches.iter()) {
+                    if let AstNode::IfBranch {
+                        condition, body, ..
+                    } = branch
+                    {
+                        traverse(condition, self, arena, parent);
+
+                        let (when_true, when_false) = self.narrowings(condition, arena);
+                        let fallthrough = self.resolver.local_types();
+
+                        self.narrow(when_true.clone());
+                        traverse(body, self, arena, parent);
+                        if !ends_control_flow(body) {
+                            outcomes.push(widen(
+                                self.resolver.local_types(),
+                                &when_true,
+                                &fallthrough,
+                            ));
+                        }
+
+                        // The following branches are only reached if the condition did not hold
+                        self.resolver.restore_local_types(fallthrough);
+                        self.narrow(when_false);
+                    }
+                }
+
+                if let Some(else_branch) = else_branch {
+                    traverse(else_branch, self, arena, parent);
+
+                    if let AstNode::ElseBranch { body, .. } = else_branch.as_ref() {
+                        if !ends_control_flow(body) {
+                            outcomes.push(self.resolver.local_types());
+                        }
+                    }
+                } else {
+                    outcomes.push(self.resolver.local_types());
+                }
+
+                // If every branch returns the code after the if statement is unreachable anyway
+                if !outcomes.is_empty() {
+                    self.resolver
+                        .restore_local_types(merge_local_types(&outcomes));
+                }
+
+                NextAction::Abort
             }
             AstNode::FunctionArgument {
                 name, doc_comment, ..
@@ -651,45 +846,30 @@ impl<'a, 'b: 'a> Visitor for NameResolveVisitor<'a, 'b> {
             }
             AstNode::Binary { left, right, token } => {
                 if token.t == TokenType::Assignment {
-                    let data_type = self.resolve_member_type(right, arena);
+                    let (types, bindings) = self.resolve_assigned_types(right, arena);
 
                     if let AstNode::Variable(variable) = left.as_ref() {
-                        let v_node = if let Some(data_type) = data_type {
-                            arena.new_node(Symbol {
-                                data_types: vec![SymbolReference::node(
-                                    vec![variable.clone()].into(),
-                                    data_type,
-                                )],
-                                ..Symbol::from(variable)
-                            })
-                        } else {
-                            arena.new_node(Symbol::from(variable))
-                        };
-
-                        self.resolver.scope_container.append(v_node, arena);
-
-                        // Should never panic
-                        let repr = self
-                            .resolver
-                            .declare_local_if_new(self.file, variable, v_node)
-                            .unwrap();
-
-                        // If the variable existed before we encountered a case of shadowing and add the
-                        // newly detected data_type to the already existing variable.
-                        if repr != v_node && data_type.is_some() {
-                            arena[repr].get_mut().data_types.push(SymbolReference::node(
-                                vec![variable.clone()].into(),
-                                data_type.unwrap(),
-                            ))
-                        };
+                        self.assign_variable(variable, types, bindings, arena);
                     } else {
+                        self.check_readonly_write(left, arena);
                         self.resolve_member_type(left, arena);
                     }
                     NextAction::Abort
                 } else {
+                    if token.is_assignment() {
+                        self.check_readonly_write(left, arena);
+                    }
+
                     NextAction::ProcessChildren(parent)
                 }
             }
+            AstNode::Unary { token, expr } | AstNode::PostUnary { token, expr }
+                if matches!(token.t, TokenType::Increment | TokenType::Decrement) =>
+            {
+                self.check_readonly_write(expr, arena);
+
+                NextAction::ProcessChildren(parent)
+            }
             AstNode::StaticMember { .. } | AstNode::Member { .. } => {
                 self.resolve_member_type(node, arena);
 
@@ -725,7 +905,9 @@ impl<'a, 'b: 'a> Visitor for NameResolveVisitor<'a, 'b> {
                     }
                 }
             }
-            AstNode::Property { name, .. } | AstNode::Const { name, .. } => {
+            AstNode::Property { name, .. }
+            | AstNode::Const { name, .. }
+            | AstNode::EnumCase { name, .. } => {
                 if let Some(label) = name.label.as_ref() {
                     for container in self.resolver.scope_container.children(arena) {
                         if arena[container].get().name() == label {
@@ -759,7 +941,8 @@ impl<'a, 'b: 'a> Visitor for NameResolveVisitor<'a, 'b> {
         match node {
             AstNode::ClassStatement { .. }
             | AstNode::TraitStatement { .. }
-            | AstNode::Interface { .. } => {
+            | AstNode::Interface { .. }
+            | AstNode::EnumStatement { .. } => {
                 self.resolver.leave_class();
                 self.resolver.scope_container = parent;
             }
@@ -775,14 +958,507 @@ impl<'a, 'b: 'a> Visitor for NameResolveVisitor<'a, 'b> {
 }
 
 impl<'a, 'b: 'a> NameResolveVisitor<'a, 'b> {
+    /// The minimal visibility of members of `class` that are accessible from the current scope,
+    /// which depends on if we are in the same class or not
+    fn visibility_towards(&self, class: NodeId) -> Visibility {
+        if self.resolver.current_class == Some(class) {
+            Visibility::Private
+        } else {
+            Visibility::Public
+        }
+    }
+
+    /// Declare or update a variable that is assigned a value of the given types
+    fn assign_variable(
+        &mut self,
+        variable: &Token,
+        types: Vec<NodeId>,
+        bindings: TemplateBindings,
+        arena: &mut Arena<Symbol>,
+    ) {
+        let data_types = types
+            .iter()
+            .map(|t| SymbolReference::node(vec![variable.clone()].into(), *t))
+            .collect::<Vec<SymbolReference>>();
+
+        let v_node = arena.new_node(Symbol {
+            data_types: data_types.clone(),
+            ..Symbol::from(variable)
+        });
+        self.resolver.scope_container.append(v_node, arena);
+
+        // If the variable existed before we encountered a case of shadowing and add the
+        // newly detected data types to the already existing variable.
+        if let Some(repr) = self
+            .resolver
+            .declare_local_if_new(self.file, variable, v_node)
+        {
+            if repr != v_node {
+                arena[repr].get_mut().data_types.extend(data_types);
+            }
+
+            if bindings.is_empty() {
+                self.resolver.bindings.remove(&repr);
+            } else {
+                self.resolver.bindings.insert(repr, bindings);
+            }
+        }
+
+        if !types.is_empty() {
+            self.resolver
+                .inferred_types
+                .insert(variable.range(), types.clone());
+        }
+        self.resolver.set_local_types(variable, types);
+    }
+
+    /// Traverse a loop. Its body might not be executed at all, so the types inferred before the
+    /// loop are merged with the ones inferred within
+    fn traverse_loop(
+        &mut self,
+        node: &AstNode,
+        arena: &mut Arena<Symbol>,
+        parent: NodeId,
+    ) -> NextAction {
+        let before = self.resolver.local_types();
+
+        for child in node.children() {
+            traverse(child, self, arena, parent);
+        }
+
+        let after = self.resolver.local_types();
+        self.resolver
+            .restore_local_types(merge_local_types(&[before, after]));
+
+        NextAction::Abort
+    }
+
+    /// Narrow the types of variables, for example after an instanceof check
+    fn narrow(&mut self, narrowings: Vec<(Token, Vec<NodeId>)>) {
+        for (variable, types) in narrowings {
+            self.resolver.set_local_types(&variable, types);
+        }
+    }
+
+    /// Return the narrowed types of the variables in a condition if it holds and if it does not
+    /// ```php
+    /// if ($a instanceof Foo && !($b instanceof Bar)) {}
+    /// ```
+    fn narrowings(
+        &mut self,
+        condition: &AstNode,
+        arena: &Arena<Symbol>,
+    ) -> (Vec<(Token, Vec<NodeId>)>, Vec<(Token, Vec<NodeId>)>) {
+        match condition {
+            AstNode::Grouping(inside) => self.narrowings(inside, arena),
+            AstNode::Unary { token, expr } if token.t == TokenType::Negation => {
+                let (when_true, when_false) = self.narrowings(expr, arena);
+
+                (when_false, when_true)
+            }
+            AstNode::Binary { left, token, right } => match token.t {
+                TokenType::InstanceOf => {
+                    let variable = if let AstNode::Variable(variable) = left.as_ref() {
+                        variable
+                    } else {
+                        return (Vec::new(), Vec::new());
+                    };
+
+                    let type_ref = match right.as_ref() {
+                        AstNode::TypeRef(type_ref) => type_ref.clone(),
+                        AstNode::Literal(token) | AstNode::Identifier(token) => {
+                            vec![token.clone()].into()
+                        }
+                        _ => return (Vec::new(), Vec::new()),
+                    };
+
+                    // The class was already referenced while traversing the condition
+                    let sc = self.resolver.scope_container;
+                    if let Some(class) =
+                        self.resolver.resolve_type_ref(&type_ref, arena, &sc, false)
+                    {
+                        (vec![(variable.clone(), vec![class])], Vec::new())
+                    } else {
+                        (Vec::new(), Vec::new())
+                    }
+                }
+                TokenType::LogicAnd => {
+                    let (mut when_true, _) = self.narrowings(left, arena);
+                    when_true.extend(self.narrowings(right, arena).0);
+
+                    (when_true, Vec::new())
+                }
+                TokenType::LogicOr => {
+                    let (_, mut when_false) = self.narrowings(left, arena);
+                    when_false.extend(self.narrowings(right, arena).1);
+
+                    (Vec::new(), when_false)
+                }
+                _ => (Vec::new(), Vec::new()),
+            },
+            _ => (Vec::new(), Vec::new()),
+        }
+    }
+
+    /// Resolve all possible types of an expression. Unlike `resolve_member_type` this follows
+    /// every arm of ternaries, null coalescing and match expressions
+    fn resolve_expression_types(
+        &mut self,
+        node: &AstNode,
+        arena: &mut Arena<Symbol>,
+    ) -> Vec<NodeId> {
+        match node {
+            AstNode::Grouping(inside) => self.resolve_expression_types(inside, arena),
+            AstNode::Ternary {
+                check,
+                true_arm,
+                false_arm,
+                ..
+            } => {
+                let mut types = if let Some(true_arm) = true_arm {
+                    self.resolve_member_type(check, arena);
+
+                    let (when_true, _) = self.narrowings(check, arena);
+                    let before = self.resolver.local_types();
+                    self.narrow(when_true);
+
+                    let types = self.resolve_expression_types(true_arm, arena);
+                    self.resolver.restore_local_types(before);
+
+                    types
+                } else {
+                    self.resolve_expression_types(check, arena)
+                };
+
+                types.extend(self.resolve_expression_types(false_arm, arena));
+                unique(types)
+            }
+            AstNode::Binary { left, token, right } if token.t == TokenType::Coalesce => {
+                let mut types = self.resolve_expression_types(left, arena);
+                types.extend(self.resolve_expression_types(right, arena));
+
+                unique(types)
+            }
+            AstNode::Match {
+                condition, body, ..
+            } => {
+                self.resolve_member_type(condition, arena);
+
+                let mut types = Vec::new();
+                for arm in body {
+                    if let AstNode::MatchArm {
+                        patterns,
+                        expression,
+                        ..
+                    } = arm
+                    {
+                        patterns.iter().flatten().for_each(|pattern| {
+                            self.resolve_member_type(pattern, arena);
+                        });
+
+                        types.extend(self.resolve_expression_types(expression, arena));
+                    }
+                }
+
+                unique(types)
+            }
+            AstNode::Variable(token) if self.resolver.get_local_types(token).is_some() => {
+                let types = self
+                    .resolver
+                    .get_local_types(token)
+                    .cloned()
+                    .unwrap_or_default();
+
+                if let Some(node) = self.resolver.get_local(token) {
+                    self.resolver.reference_variable(self.file, token, &node);
+                }
+
+                types
+            }
+            AstNode::Call { .. } => {
+                if let Some(resolved) = self.resolve_member_type(node, arena) {
+                    self.return_types(resolved, arena)
+                } else {
+                    Vec::new()
+                }
+            }
+            AstNode::EncapsedString { parts, .. } => {
+                // Only the embedded expressions refer to symbols, the string itself has no type
+                for part in parts {
+                    match part {
+                        AstNode::Literal(_) => (),
+                        AstNode::EncapsedExpression { expression, .. } => {
+                            self.resolve_member_type(expression, arena);
+                        }
+                        _ => {
+                            self.resolve_member_type(part, arena);
+                        }
+                    }
+                }
+
+                Vec::new()
+            }
+            _ => self.resolve_member_type(node, arena).into_iter().collect(),
+        }
+    }
+
+    /// Resolve the types of an assigned value. Member chains like `$repository->findAll()` may
+    /// also bind the templates of the generic type they return
+    fn resolve_assigned_types(
+        &mut self,
+        value: &AstNode,
+        arena: &mut Arena<Symbol>,
+    ) -> (Vec<NodeId>, TemplateBindings) {
+        match value {
+            AstNode::Call { .. } | AstNode::Member { .. } | AstNode::StaticMember { .. } => {
+                if let Some((resolved, bindings)) = self.resolve_generic_member_type(value, arena) {
+                    (self.return_types(resolved, arena), bindings)
+                } else {
+                    (Vec::new(), TemplateBindings::new())
+                }
+            }
+            _ => (
+                self.resolve_expression_types(value, arena),
+                TemplateBindings::new(),
+            ),
+        }
+    }
+
+    /// Bind the templates of a class to the given type arguments, and the templates of its
+    /// ancestors to the type arguments it passes on with @extends, @implements or @use
+    fn bind_templates(
+        &mut self,
+        class: NodeId,
+        arguments: Vec<Option<NodeId>>,
+        arena: &Arena<Symbol>,
+    ) -> TemplateBindings {
+        let mut bindings = TemplateBindings::new();
+        self.bind_templates_of(class, arguments, &mut bindings, arena, 0);
+
+        bindings
+    }
+
+    fn bind_templates_of(
+        &mut self,
+        class: NodeId,
+        arguments: Vec<Option<NodeId>>,
+        bindings: &mut TemplateBindings,
+        arena: &Arena<Symbol>,
+        depth: usize,
+    ) {
+        // Guard against circular inheritance
+        if depth > 16 {
+            return;
+        }
+
+        let symbol = arena[class].get();
+        for (template, argument) in symbol.templates.iter().zip(arguments) {
+            if let Some(argument) = argument {
+                bindings.insert((class, template.name.clone()), argument);
+            }
+        }
+
+        for parent in &symbol.generic_parents {
+            let parent_class =
+                match self
+                    .resolver
+                    .resolve_type_ref(&parent.type_ref, arena, &class, false)
+                {
+                    Some(parent_class) if parent_class != class => parent_class,
+                    _ => continue,
+                };
+
+            let arguments = parent
+                .type_ref
+                .arguments()
+                .iter()
+                .map(|argument| self.resolve_type_argument(argument, class, bindings, arena))
+                .collect();
+
+            self.bind_templates_of(parent_class, arguments, bindings, arena, depth + 1);
+        }
+    }
+
+    /// Bind the templates of the type a variable resolved to, either from the member chain it
+    /// was assigned or from its declared generic type, like `@var Collection<int, User> $users`
+    fn bindings_of_variable(
+        &mut self,
+        variable: NodeId,
+        class: NodeId,
+        arena: &Arena<Symbol>,
+    ) -> TemplateBindings {
+        if let Some(bindings) = self.resolver.bindings.get(&variable) {
+            return bindings.clone();
+        }
+
+        let generic_type = arena[variable].get().data_types.iter().find(|reference| {
+            !reference.type_ref.arguments().is_empty()
+                && !reference.type_ref.is_multiple()
+                && reference.node == Some(class)
+        });
+
+        let arguments = if let Some(generic_type) = generic_type {
+            generic_type
+                .type_ref
+                .arguments()
+                .iter()
+                .map(|argument| {
+                    self.resolve_type_argument(argument, variable, &TemplateBindings::new(), arena)
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        self.bind_templates(class, arguments, arena)
+    }
+
+    /// Resolve a type argument, which may refer to a template in scope of `context`
+    fn resolve_type_argument(
+        &mut self,
+        type_ref: &TypeRef,
+        context: NodeId,
+        bindings: &TemplateBindings,
+        arena: &Arena<Symbol>,
+    ) -> Option<NodeId> {
+        if let Some((declaring, template)) = template_of(type_ref, context, arena) {
+            return self.bound_type(declaring, template, bindings, arena);
+        }
+
+        self.resolver
+            .resolve_type_ref(type_ref, arena, &context, false)
+    }
+
+    /// The type bound to a template. Without binding, the template stands for the bound it was
+    /// declared with, like Model in `@template T of Model`
+    fn bound_type(
+        &mut self,
+        declaring: NodeId,
+        template: &TemplateParameter,
+        bindings: &TemplateBindings,
+        arena: &Arena<Symbol>,
+    ) -> Option<NodeId> {
+        if let Some(bound) = bindings.get(&(declaring, template.name.clone())) {
+            return Some(*bound);
+        }
+
+        self.resolver
+            .resolve_type_ref(template.bound.as_ref()?, arena, &declaring, false)
+    }
+
+    /// Resolve the declared return types of a function. Calls of methods are already resolved to
+    /// their return types by `resolve_member_type`, calls of functions are not.
+    fn return_types(&mut self, node: NodeId, arena: &Arena<Symbol>) -> Vec<NodeId> {
+        if arena[node].get().kind != PhpSymbolKind::Function {
+            return vec![node];
+        }
+
+        let types = arena[node]
+            .get()
+            .data_types
+            .iter()
+            .filter_map(|data_type| {
+                self.resolver
+                    .resolve_type_ref(&data_type.type_ref, arena, &node, false)
+            })
+            .collect::<Vec<NodeId>>();
+
+        if types.is_empty() {
+            vec![node]
+        } else {
+            types
+        }
+    }
+
+    /// Raise a diagnostic if a readonly property is written outside of the constructor of its class
+    /// ```php
+    /// $this->readonlyProperty = 1;
+    /// ```
+    fn check_readonly_write(&mut self, left: &AstNode, arena: &Arena<Symbol>) {
+        let (object, member) = if let AstNode::Member { object, member, .. } = left {
+            (object, member)
+        } else {
+            return;
+        };
+
+        let variable = if let AstNode::Variable(variable) = object.as_ref() {
+            variable
+        } else {
+            return;
+        };
+
+        let classes = if let Some(local) = self.resolver.get_local(variable) {
+            if Some(local) == self.resolver.current_class {
+                vec![local]
+            } else {
+                arena[local]
+                    .get()
+                    .data_types
+                    .iter()
+                    .filter_map(|reference| reference.node)
+                    .collect()
+            }
+        } else {
+            return;
+        };
+
+        let in_constructor =
+            arena[self.resolver.scope_container].get().normalized_name() == "__construct";
+
+        for class in classes {
+            let property = arena[class]
+                .get()
+                .get_all_symbols(class, self.resolver, arena)
+                .get(&member.name().to_lowercase())
+                .map(|alias| alias.symbol);
+
+            if let Some(property) = property {
+                if !arena[property].get().is_readonly {
+                    continue;
+                }
+
+                if in_constructor && Some(class) == self.resolver.current_class {
+                    continue;
+                }
+
+                let file_name = arena[self.file].get().name().to_owned();
+                self.resolver.diagnostic(
+                    file_name,
+                    member.range(),
+                    format!(
+                        "Cannot modify readonly property {} outside of the constructor",
+                        member.name()
+                    ),
+                    DiagnosticSeverity::ERROR,
+                );
+
+                return;
+            }
+        }
+    }
+
     /// Resolve a member chain like
     /// ```php
     /// $object->method()->member;
     /// ```
     /// and return the type of the last link, in this case of member
     fn resolve_member_type(&mut self, node: &AstNode, arena: &mut Arena<Symbol>) -> Option<NodeId> {
+        self.resolve_generic_member_type(node, arena)
+            .map(|(resolved, _)| resolved)
+    }
+
+    /// Resolve a member chain like `resolve_member_type`, and return the type of the last link
+    /// along with the types bound to its templates, if it is generic
+    fn resolve_generic_member_type(
+        &mut self,
+        node: &AstNode,
+        arena: &mut Arena<Symbol>,
+    ) -> Option<(NodeId, TemplateBindings)> {
         let mut reversed_chain = Vec::with_capacity(5);
 
+        // The variable the chain starts at, whose generic type binds templates
+        let mut root_variable = None;
+
         let mut current_object = node;
         let (root_node, minimal_visibility) = 'root_node: loop {
             match current_object {
@@ -793,22 +1469,8 @@ impl<'a, 'b: 'a> NameResolveVisitor<'a, 'b> {
                         }
 
                         if let AstNode::Variable(token) = left.as_ref() {
-                            let data_type = self.resolve_member_type(right, arena);
-
-                            let child = if let Some(data_type) = data_type {
-                                arena.new_node(Symbol {
-                                    data_types: vec![SymbolReference::node(
-                                        vec![token.clone()].into(),
-                                        data_type,
-                                    )],
-                                    ..Symbol::from(token)
-                                })
-                            } else {
-                                arena.new_node(Symbol::from(token))
-                            };
-
-                            self.resolver.scope_container.append(child, arena);
-                            self.resolver.declare_local_if_new(self.file, token, child);
+                            let (types, bindings) = self.resolve_assigned_types(right, arena);
+                            self.assign_variable(token, types, bindings, arena);
 
                             current_object = left;
                         } else {
@@ -817,7 +1479,7 @@ impl<'a, 'b: 'a> NameResolveVisitor<'a, 'b> {
                     } else {
                         self.resolve_member_type(left, arena);
 
-                        return self.resolve_member_type(right, arena);
+                        return self.resolve_generic_member_type(right, arena);
                     }
                 }
                 AstNode::Ternary {
@@ -872,13 +1534,35 @@ impl<'a, 'b: 'a> NameResolveVisitor<'a, 'b> {
                 }
                 AstNode::Variable(token) => {
                     if let Some(node) = self.resolver.get_local(token) {
-                        self.resolver.reference_local(self.file, token, &node);
+                        self.resolver.reference_variable(self.file, token, &node);
+                        root_variable = Some(node);
 
                         if let Some(name) = token.label.as_ref() {
                             // TODO: Once tested, reduce this if as the test is already done in resolver::get_local
                             if name == "this" {
                                 // In this case this was automatically resolved to the current class
                                 break (Some(node), Visibility::Private);
+                            } else if let Some(types) = self.resolver.get_local_types(token) {
+                                // The type at this point of the control flow takes precedence. Of
+                                // multiple possible types, take the first one that has the next link
+                                let types = types.clone();
+                                let next_link = reversed_chain.last().map(|link| link.name());
+                                let resolved = types
+                                    .iter()
+                                    .copied()
+                                    .find(|t| match next_link.as_ref() {
+                                        Some(link) => arena[*t]
+                                            .get()
+                                            .get_all_symbols(*t, self.resolver, arena)
+                                            .contains_key(&link.to_lowercase()),
+                                        None => true,
+                                    })
+                                    .unwrap_or(types[0]);
+
+                                break 'root_node (
+                                    Some(resolved),
+                                    self.visibility_towards(resolved),
+                                );
                             } else {
                                 // In this case we need to resolve to the type of the variable
                                 for reference in &arena[node].get().data_types {
@@ -886,17 +1570,7 @@ impl<'a, 'b: 'a> NameResolveVisitor<'a, 'b> {
                                         break 'root_node (
                                             // Return the referenced node
                                             Some(referenced_node),
-                                            // Now, the required visibility depends on if we are in the same class or not
-                                            if let Some(current_class) = self.resolver.current_class
-                                            {
-                                                if current_class == referenced_node {
-                                                    Visibility::Private
-                                                } else {
-                                                    Visibility::Public
-                                                }
-                                            } else {
-                                                Visibility::Public
-                                            },
+                                            self.visibility_towards(referenced_node),
                                         );
                                     } else if let Some(resolved_data_type) = self
                                         .resolver
@@ -936,7 +1610,9 @@ impl<'a, 'b: 'a> NameResolveVisitor<'a, 'b> {
                             return None;
                         }
                     }
-                    TokenType::ConstantEncapsedString | TokenType::EncapsedAndWhitespaceString => {
+                    TokenType::ConstantEncapsedString
+                    | TokenType::EncapsedAndWhitespaceString
+                    | TokenType::EncapsedStringPart => {
                         return None;
                     }
                     _ => {
@@ -974,6 +1650,28 @@ impl<'a, 'b: 'a> NameResolveVisitor<'a, 'b> {
                         if let Some(var) = self.resolver.get_local(collection) {
                             self.resolver.reference_local(self.file, collection, &var);
 
+                            // Entries of array shapes like $row['user']
+                            let entry = match index.as_deref() {
+                                Some(AstNode::Literal(key)) => key.label.as_ref().and_then(|key| {
+                                    arena[var]
+                                        .get()
+                                        .doc_type
+                                        .as_ref()?
+                                        .shape_entry(key)
+                                        .cloned()
+                                }),
+                                _ => None,
+                            };
+
+                            for type_ref in entry.iter().flat_map(DocType::type_refs) {
+                                if let Some(resolved) = self
+                                    .resolver
+                                    .resolve_type_ref(&type_ref, arena, &var, false)
+                                {
+                                    break 'root_node (Some(resolved), Visibility::Public);
+                                }
+                            }
+
                             for reference in &arena[var].get().data_types {
                                 if reference.type_ref.is_multiple() {
                                     if let Some(referenced_node) = reference.node {
@@ -1027,6 +1725,13 @@ impl<'a, 'b: 'a> NameResolveVisitor<'a, 'b> {
         .name();
 
         if let Some(mut root_node) = root_node {
+            let mut bindings = match root_variable {
+                Some(variable) if variable != root_node => {
+                    self.bindings_of_variable(variable, root_node, arena)
+                }
+                _ => self.bind_templates(root_node, Vec::new(), arena),
+            };
+
             // $this (root_node) will have resolved to the definition of the class
             // of the object
             'link_loop: for link in reversed_chain.iter().rev() {
@@ -1083,7 +1788,9 @@ impl<'a, 'b: 'a> NameResolveVisitor<'a, 'b> {
                     }
 
                     match child_symbol.kind {
-                        PhpSymbolKind::Property | PhpSymbolKind::Method => {
+                        PhpSymbolKind::Property
+                        | PhpSymbolKind::Method
+                        | PhpSymbolKind::EnumMember => {
                             for data_type in &child_symbol.data_types {
                                 let first_type = data_type.type_ref.root_token_type();
                                 if TokenType::TypeSelf == first_type
@@ -1118,13 +1825,58 @@ impl<'a, 'b: 'a> NameResolveVisitor<'a, 'b> {
                                     }
                                 }
 
+                                // A template of the class declaring the member or of the method
+                                // itself is substituted by the type bound to it
+                                if let Some((declaring, template)) =
+                                    template_of(&data_type.type_ref, child.symbol, arena)
+                                {
+                                    if let Some(bound) =
+                                        self.bound_type(declaring, template, &bindings, arena)
+                                    {
+                                        self.resolver
+                                            .inferred_types
+                                            .insert(link.range(), vec![bound]);
+                                        root_node = bound;
+                                        bindings = self.bind_templates(bound, Vec::new(), arena);
+
+                                        continue 'link_loop;
+                                    }
+
+                                    continue;
+                                }
+
                                 if let Some(resolved_type) = self.resolver.resolve_type_ref(
                                     &data_type.type_ref,
                                     arena,
                                     &root_node,
                                     true,
                                 ) {
+                                    // The native return type is listed before the generic one
+                                    // from the doc comment, which has the type arguments
+                                    let generic_type = child_symbol
+                                        .data_types
+                                        .iter()
+                                        .map(|reference| &reference.type_ref)
+                                        .find(|type_ref| {
+                                            !type_ref.arguments().is_empty()
+                                                && type_ref.tip() == data_type.type_ref.tip()
+                                        })
+                                        .unwrap_or(&data_type.type_ref);
+                                    let arguments = generic_type
+                                        .arguments()
+                                        .iter()
+                                        .map(|argument| {
+                                            self.resolve_type_argument(
+                                                argument,
+                                                child.symbol,
+                                                &bindings,
+                                                arena,
+                                            )
+                                        })
+                                        .collect();
+
                                     root_node = resolved_type;
+                                    bindings = self.bind_templates(root_node, arguments, arena);
 
                                     // Got a match, stop and proceeed with the next link
                                     continue 'link_loop;
@@ -1150,15 +1902,119 @@ impl<'a, 'b: 'a> NameResolveVisitor<'a, 'b> {
             }
 
             // At this point we arrived at the last link and successfully resolved everything
-            return Some(root_node);
+            return Some((root_node, bindings));
         }
 
         None
     }
 }
 
+/// The symbol declaring the template a type refers to, if it refers to one of the templates in
+/// scope of `context`, like `T` within a method of `Collection<T>`
+fn template_of<'s>(
+    type_ref: &TypeRef,
+    context: NodeId,
+    arena: &'s Arena<Symbol>,
+) -> Option<(NodeId, &'s TemplateParameter)> {
+    if type_ref.len() != 1 {
+        return None;
+    }
+
+    let name = type_ref.tip()?;
+
+    context.ancestors(arena).find_map(|ancestor| {
+        arena[ancestor]
+            .get()
+            .templates
+            .iter()
+            .find(|template| template.name == name)
+            .map(|template| (ancestor, template))
+    })
+}
+
+/// Merge the inferred types of several branches of the control flow. A variable only has inferred
+/// types afterwards if it has them in every branch
+fn merge_local_types(branches: &[LocalTypes]) -> LocalTypes {
+    let mut merged = LocalTypes::new();
+
+    if let Some((first, rest)) = branches.split_first() {
+        for (name, types) in first {
+            let mut types = types.clone();
+
+            for branch in rest {
+                if let Some(other) = branch.get(name) {
+                    types.extend(other);
+                } else {
+                    types.clear();
+                    break;
+                }
+            }
+
+            if !types.is_empty() {
+                merged.insert(name.clone(), unique(types));
+            }
+        }
+    }
+
+    merged
+}
+
+/// Undo the narrowing of variables at the end of a branch, unless they were assigned within it.
+/// Narrowing only tells something about the variable inside of the branch.
+fn widen(
+    mut types: LocalTypes,
+    narrowings: &[(Token, Vec<NodeId>)],
+    before: &LocalTypes,
+) -> LocalTypes {
+    for (variable, narrowed) in narrowings {
+        if let Some(label) = variable.label.as_ref() {
+            if types.get(label) != Some(narrowed) {
+                continue;
+            }
+
+            if let Some(previous) = before.get(label) {
+                types.insert(label.clone(), previous.clone());
+            } else {
+                types.remove(label);
+            }
+        }
+    }
+
+    types
+}
+
+/// Remove duplicate types while keeping their order
+fn unique(mut types: Vec<NodeId>) -> Vec<NodeId> {
+    let mut seen = Vec::with_capacity(types.len());
+    types.retain(|t| {
+        if seen.contains(t) {
+            false
+        } else {
+            seen.push(*t);
+            true
+        }
+    });
+
+    types
+}
+
+/// Return true if the statement never completes normally, so the code after it is not reached
+fn ends_control_flow(node: &AstNode) -> bool {
+    match node {
+        AstNode::Block { statements, .. } | AstNode::AlternativeBlock { statements, .. } => {
+            statements.last().map_or(false, ends_control_flow)
+        }
+        AstNode::ReturnStatement { .. } | AstNode::ThrowStatement { .. } => true,
+        AstNode::TokenStatement { token, .. } => {
+            matches!(token.t, TokenType::Continue | TokenType::Break)
+        }
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use crate::environment::symbol::PhpSymbolKind;
     use crate::{backend::Backend, backend::BackendState, environment::get_range, parser};
     use parser::{scanner::Scanner, Parser};
 
@@ -1598,7 +2454,7 @@ mod tests {
             ),
             (
                 "c2.php",
-                "<?php namespace App1; class OtherTest { /** @param OtherTest $var */ public function test($var) {} }",
+                "<?php namespace App1; class OtherTest { /** @param OtherTest $var */ public function test($var = null) { return $var; } }",
             ),
             (
                 "index.php",
@@ -1625,7 +2481,7 @@ mod tests {
         );
 
         assert_reference_names!(
-            vec!["OtherTest", "OtherTest", "test", "var", "var"],
+            vec!["OtherTest", "OtherTest", "test", "var", "var", "var"],
             references!(state, "c2.php")
         );
     }
@@ -1864,7 +2720,7 @@ mod tests {
             ),
             (
                 "Cls.php",
-                "<?php namespace A; use B\\RetVal; class Cls implements RetVal {  public function handle() {} }",
+                "<?php namespace A; use B\\RetVal; class Cls implements RetVal {  public function handle() {} public function handle2() {} }",
             ),
             (
                 "Response.php",
@@ -2020,4 +2876,421 @@ mod tests {
             references!(state, "f.php")
         );
     }
+
+    #[tokio::test]
+    async fn test_resolves_enum_cases_and_implicit_members() {
+        let mut state = BackendState::default();
+
+        collect_and_reference!(
+            state,
+            vec![(
+                "suit.php",
+                "<?php
+                enum Suit: string {
+                    case Hearts = 'H';
+                    case Spades = 'S';
+                }
+
+                Suit::Hearts->value;
+                Suit::from('H')->name;
+                Suit::tryFrom('S');
+                Suit::cases();
+                ",
+            )]
+        );
+
+        assert!(state.diagnostics.is_empty(), "{:?}", state.diagnostics);
+        assert_reference_names!(
+            vec![
+                "Hearts", "Hearts", "Spades", "Suit", "Suit", "Suit", "Suit", "Suit", "cases",
+                "from", "name", "tryFrom", "value"
+            ],
+            references!(state, "suit.php")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_resolves_promoted_constructor_properties() {
+        let mut state = BackendState::default();
+
+        collect_and_reference!(
+            state,
+            vec![(
+                "dto.php",
+                "<?php
+                class Foo {
+                    function bar() {}
+                }
+
+                class Dto {
+                    public function __construct(private readonly Foo $foo) {
+                        $this->foo = $foo;
+                    }
+
+                    public function handle() {
+                        $this->foo->bar();
+                    }
+                }
+                ",
+            )]
+        );
+
+        assert!(state.diagnostics.is_empty(), "{:?}", state.diagnostics);
+    }
+
+    #[tokio::test]
+    async fn test_promotes_only_constructor_parameters_to_properties() {
+        let mut state = BackendState::default();
+
+        collect_and_reference!(
+            state,
+            vec![(
+                "dto.php",
+                "<?php
+                class Dto {
+                    public function __construct(private int $id) {}
+
+                    public function with(private int $other) {}
+                }
+                ",
+            )]
+        );
+
+        let class = state.global_symbols["dto"];
+        let properties = class
+            .children(&state.arena)
+            .filter(|child| state.arena[*child].get().kind == PhpSymbolKind::Property)
+            .map(|child| state.arena[child].get().name().to_owned())
+            .collect::<Vec<String>>();
+
+        assert_eq!(vec!["id"], properties);
+    }
+
+    #[tokio::test]
+    async fn test_resolves_variables_interpolated_in_strings() {
+        let mut state = BackendState::default();
+
+        collect_and_reference!(
+            state,
+            vec![(
+                "user.php",
+                "<?php
+                class User {
+                    public string $name;
+
+                    public function getName(): string {}
+                }
+
+                $user = new User();
+                echo \"Hello {$user->getName()}, $user->name\";
+                echo <<<EOT
+                    Bye {$user->name}
+                EOT;
+                ",
+            )]
+        );
+
+        assert!(state.diagnostics.is_empty(), "{:?}", state.diagnostics);
+        assert_reference_names!(
+            vec![
+                "User", "User", "getName", "getName", "name", "name", "name", "user", "user",
+                "user", "user"
+            ],
+            references!(state, "user.php")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_reports_writes_to_readonly_properties_outside_of_constructor() {
+        let mut state = BackendState::default();
+
+        collect_and_reference!(
+            state,
+            vec![(
+                "dto.php",
+                "<?php
+                readonly class Dto {
+                    public int $count;
+
+                    public function __construct(public string $name) {
+                        $this->count = 1;
+                    }
+
+                    public function rename() {
+                        $this->name = 'new';
+                        $this->name .= 'suffix';
+                        $this->count += 1;
+                        $this->count ??= 0;
+                        $this->count++;
+                        --$this->count;
+                    }
+                }
+
+                $dto = new Dto('name');
+                $dto->count = 2;
+                ",
+            )]
+        );
+
+        let messages = state
+            .diagnostics
+            .get("dto.php")
+            .unwrap()
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect::<Vec<String>>();
+
+        assert_eq!(
+            vec![
+                "Cannot modify readonly property name outside of the constructor",
+                "Cannot modify readonly property name outside of the constructor",
+                "Cannot modify readonly property count outside of the constructor",
+                "Cannot modify readonly property count outside of the constructor",
+                "Cannot modify readonly property count outside of the constructor",
+                "Cannot modify readonly property count outside of the constructor",
+                "Cannot modify readonly property count outside of the constructor"
+            ],
+            messages
+        );
+    }
+
+    #[tokio::test]
+    async fn test_infers_types_of_variables_through_control_flow() {
+        let mut state = BackendState::default();
+
+        collect_and_reference!(
+            state,
+            vec![(
+                "flow.php",
+                "<?php
+                class A { function a() {} }
+                class B { function b() {} }
+                function make(): B {}
+
+                $x = new A();
+                if ($flag) {
+                    $x = make();
+                }
+                $x->a();
+                $x->b();
+
+                if ($y instanceof A) {
+                    $y->a();
+                } elseif ($y instanceof B) {
+                    $y->b();
+                }
+
+                if (!($z instanceof B)) {
+                    return;
+                }
+                $z->b();
+
+                $t = $flag ? new A() : $x ?? make();
+                $t->b();
+
+                $m = match ($flag) {
+                    1 => new A(),
+                    default => new B(),
+                };
+                $m->a();
+                $m->b();
+
+                /** @var array<int, A> $list */
+                $list = [];
+                foreach ($list as $item) {
+                    $item->a();
+                }
+                ",
+            )]
+        );
+
+        assert!(state.diagnostics.is_empty(), "{:?}", state.diagnostics);
+
+        let mut methods = references!(state, "flow.php")
+            .drain(..)
+            .filter(|name| name == "a" || name == "b")
+            .collect::<Vec<String>>();
+        methods.sort();
+
+        assert_eq!(
+            vec!["a", "a", "a", "a", "a", "b", "b", "b", "b", "b", "b"],
+            methods
+        );
+    }
+
+    #[tokio::test]
+    async fn test_narrowed_types_do_not_leak_out_of_branches() {
+        let mut state = BackendState::default();
+
+        collect_and_reference!(
+            state,
+            vec![(
+                "flow.php",
+                "<?php
+                class A { function a() {} }
+                class B { function b() {} }
+
+                $x = new A();
+                if ($x instanceof B) {
+                    $x->b();
+                }
+                $x->a();
+                $x->b();
+                ",
+            )]
+        );
+
+        let messages = state
+            .diagnostics
+            .get("flow.php")
+            .unwrap()
+            .iter()
+            .map(|diagnostic| diagnostic.message.clone())
+            .collect::<Vec<String>>();
+
+        assert_eq!(vec!["Unresolvable symbol b"], messages);
+    }
+
+    #[tokio::test]
+    async fn test_substitutes_templates_in_member_chains() {
+        let mut state = BackendState::default();
+
+        collect_and_reference!(
+            state,
+            vec![(
+                "generics.php",
+                "<?php
+                class User { function name() {} }
+
+                /**
+                 * @template TKey
+                 * @template TValue
+                 */
+                class Collection {
+                    /** @return TValue|null */
+                    function first() {}
+
+                    /** @return Collection<TKey, TValue> */
+                    function filter() {}
+                }
+
+                /** @extends Collection<int, User> */
+                class UserCollection extends Collection {}
+
+                class Repository {
+                    /** @return Collection<int, User> */
+                    function all(): Collection {}
+
+                    /**
+                     * @template T of User
+                     * @param class-string<T> $class
+                     * @return T
+                     */
+                    function find(string $class) { return new $class(); }
+                }
+
+                /** @var Collection<int, User> $users */
+                $users->first()->name();
+                $users->filter()->first()->name();
+
+                $mine = new UserCollection();
+                $mine->first()->name();
+
+                $repository = new Repository();
+                $all = $repository->all();
+                $all->first()->name();
+                $repository->find(User::class)->name();
+                ",
+            )]
+        );
+
+        assert!(state.diagnostics.is_empty(), "{:?}", state.diagnostics);
+
+        let names = references!(state, "generics.php")
+            .drain(..)
+            .filter(|name| name == "name")
+            .count();
+
+        assert_eq!(6, names);
+    }
+
+    #[tokio::test]
+    async fn test_resolves_shapes_and_collections_of_doc_types() {
+        let mut state = BackendState::default();
+
+        collect_and_reference!(
+            state,
+            vec![(
+                "shapes.php",
+                "<?php
+                class User { function name() {} }
+
+                /** @var array{id: int, user: User} $row */
+                $row['user']->name();
+
+                /**
+                 * @param list<User> $list
+                 * @param array<string, list<User>> $groups
+                 */
+                function all(array $list, array $groups) {
+                    foreach ($list as $item) {
+                        $item->name();
+                    }
+
+                    foreach ($groups as $group) {
+                        foreach ($group as $member) {
+                            $member->name();
+                        }
+                    }
+                }
+
+                /** @param array{User, ?User} $pair */
+                function first(array $pair) {
+                    $pair[0]->name();
+                }
+                ",
+            )]
+        );
+
+        assert!(state.diagnostics.is_empty(), "{:?}", state.diagnostics);
+
+        let names = references!(state, "shapes.php")
+            .drain(..)
+            .filter(|name| name == "name")
+            .count();
+
+        // The declaration and four calls
+        assert_eq!(5, names);
+    }
+
+    #[tokio::test]
+    async fn test_resolves_expressions_in_assigned_interpolated_strings() {
+        let mut state = BackendState::default();
+
+        let sources = vec![(
+            "circle.php",
+            "<?php namespace App; class Circle { public $r; public function area() {}
+            public function test() {
+                $c = new Circle();
+                $s = \"a {$c->area()} b\";
+                $t = \"x $c->r y\";
+                $u = <<<EOT
+                    a {$c->area()} b
+                    EOT;
+
+                echo $s, $t, $u;
+            } }",
+        )];
+
+        collect_and_reference!(state, sources);
+
+        eprintln!("{:?}", state.diagnostics);
+        assert!(state.diagnostics.is_empty());
+
+        let area = state.global_symbols["app\\circle"]
+            .children(&state.arena)
+            .find(|child| state.arena[*child].get().name() == "area")
+            .unwrap();
+        assert_eq!(3, state.symbol_references["circle.php"][&area].len());
+    }
 }
@@ -3,11 +3,12 @@ use super::super::import::{collect_alterations, collect_uses};
 use super::NextAction;
 use super::Symbol;
 use super::Visitor;
-use crate::parser::node::Node as AstNode;
+use crate::parser::node::{DocType, Node as AstNode};
 use crate::{environment::scope::Reference, parser::node::ClassStatement};
 use crate::{
-    environment::symbol::{FunctionParameter, PhpSymbolKind, Visibility},
+    environment::symbol::{FunctionParameter, PhpSymbolKind, TemplateParameter, Visibility},
     parser::node::TypeRef,
+    parser::token::{Token, TokenType},
 };
 use indextree::{Arena, NodeId};
 
@@ -112,6 +113,8 @@ impl Visitor for WorkspaceSymbolVisitor {
                 implements,
                 doc_comment,
                 attributes,
+                is_readonly,
+                is_abstract,
                 ..
             }) => {
                 let inherits_from = extends.as_ref().map(|extends| {
@@ -160,6 +163,10 @@ impl Visitor for WorkspaceSymbolVisitor {
                     data_types,
                     deprecated: deprecated_from_doc!(doc_comment),
                     is_attribute,
+                    is_readonly: is_readonly.is_some(),
+                    is_abstract: is_abstract.is_some(),
+                    templates: templates_from_doc!(doc_comment),
+                    generic_parents: generic_parents_from_doc!(doc_comment),
                     ..Symbol::default()
                 });
 
@@ -195,6 +202,8 @@ impl Visitor for WorkspaceSymbolVisitor {
                     range,
                     selection_range,
                     deprecated: deprecated_from_doc!(doc_comment),
+                    templates: templates_from_doc!(doc_comment),
+                    generic_parents: generic_parents_from_doc!(doc_comment),
                     ..Symbol::default()
                 });
                 parent.append(child, arena);
@@ -228,12 +237,97 @@ impl Visitor for WorkspaceSymbolVisitor {
                     selection_range,
                     inherits_from,
                     deprecated: deprecated_from_doc!(doc_comment),
+                    templates: templates_from_doc!(doc_comment),
+                    generic_parents: generic_parents_from_doc!(doc_comment),
                     ..Symbol::default()
                 });
                 parent.append(child, arena);
 
                 NextAction::ProcessChildren(child)
             }
+            AstNode::EnumStatement {
+                name,
+                backed_type,
+                implements,
+                doc_comment,
+                ..
+            } => {
+                let selection_range = get_range(name.range());
+                let range = get_range(node.range());
+
+                let namespace = self.namespace.as_ref().cloned();
+
+                let mut data_types = vec![Reference::type_ref(vec![name.clone()].into())];
+                if let Some(implements) = implements {
+                    data_types.extend(
+                        implements
+                            .iter()
+                            .flat_map(get_type_ref)
+                            .map(Reference::type_ref),
+                    );
+                }
+
+                let child = arena.new_node(Symbol {
+                    namespace,
+                    name: name.to_string(),
+                    kind: PhpSymbolKind::Enum,
+                    range,
+                    selection_range,
+                    data_types,
+                    deprecated: deprecated_from_doc!(doc_comment),
+                    ..Symbol::default()
+                });
+
+                parent.append(child, arena);
+
+                child.append(
+                    arena.new_node(Symbol {
+                        name: "class".to_string(),
+                        kind: PhpSymbolKind::MagicConst,
+                        range: selection_range,
+                        selection_range,
+                        is_static: true,
+                        ..Symbol::default()
+                    }),
+                    arena,
+                );
+
+                for implicit_member in implicit_enum_members(name, backed_type.as_deref()) {
+                    child.append(
+                        arena.new_node(Symbol {
+                            range: selection_range,
+                            selection_range,
+                            visibility: Visibility::Public,
+                            is_implicit: true,
+                            ..implicit_member
+                        }),
+                        arena,
+                    );
+                }
+
+                NextAction::ProcessChildren(child)
+            }
+            AstNode::EnumCase {
+                name, doc_comment, ..
+            } => {
+                let self_type = Token::new(TokenType::TypeSelf, name.line, name.col, 0);
+
+                let child = arena.new_node(Symbol {
+                    name: name.to_string(),
+                    kind: PhpSymbolKind::EnumMember,
+                    range: get_range(node.range()),
+                    selection_range: get_range(name.range()),
+                    data_types: vec![Reference::type_ref(vec![self_type].into())],
+                    is_static: true,
+                    visibility: Visibility::Public,
+                    deprecated: deprecated_from_doc!(doc_comment),
+                    ..Symbol::default()
+                });
+
+                parent.append(child, arena);
+
+                NextAction::Abort
+            }
             AstNode::ClassConstantDefinitionStatement {
                 consts,
                 doc_comment,
@@ -257,6 +351,7 @@ impl Visitor for WorkspaceSymbolVisitor {
                             selection_range: range,
                             visibility: Visibility::from(visibility),
                             data_types,
+                            doc_type: doc_type_from_doc!(doc_comment, var_docs),
                             deprecated,
                             ..Symbol::default()
                         });
@@ -266,7 +361,12 @@ impl Visitor for WorkspaceSymbolVisitor {
 
                 NextAction::Abort
             }
-            AstNode::DocCommentProperty { name, types, .. } => {
+            AstNode::DocCommentProperty {
+                name,
+                types,
+                doc_type,
+                ..
+            } => {
                 let range = get_range(node.range());
                 let mut combined_data_types = Vec::new();
 
@@ -282,6 +382,7 @@ impl Visitor for WorkspaceSymbolVisitor {
                     range,
                     selection_range: range,
                     data_types: combined_data_types,
+                    doc_type: doc_type.clone(),
                     visibility: Visibility::Private,
                     ..Symbol::default()
                 });
@@ -295,10 +396,13 @@ impl Visitor for WorkspaceSymbolVisitor {
                 data_type,
                 doc_comment,
                 visibility,
+                is_static,
+                is_readonly,
                 ..
             } => {
                 let deprecated = deprecated_from_doc!(doc_comment);
                 let range = get_range(node.range());
+                let is_readonly = is_readonly.is_some() || arena[parent].get().is_readonly;
 
                 let mut data_types = if let Some(data_type) = data_type {
                     get_type_refs(data_type)
@@ -310,6 +414,7 @@ impl Visitor for WorkspaceSymbolVisitor {
                 };
 
                 ref_from_doc!(doc_comment, data_types, var_docs);
+                let doc_type = doc_type_from_doc!(doc_comment, var_docs);
 
                 for prop in properties {
                     if let AstNode::Property { name, .. } = prop {
@@ -317,10 +422,13 @@ impl Visitor for WorkspaceSymbolVisitor {
                             name: name.to_string(),
                             kind: PhpSymbolKind::Property,
                             range,
-                            selection_range: range,
+                            selection_range: get_range(name.range()),
                             data_types: data_types.clone(),
+                            doc_type: doc_type.clone(),
                             visibility: Visibility::from(visibility),
                             deprecated,
+                            is_static: is_static.is_some(),
+                            is_readonly,
                             ..Symbol::default()
                         });
 
@@ -334,6 +442,7 @@ impl Visitor for WorkspaceSymbolVisitor {
                 name,
                 function,
                 is_static,
+                is_abstract,
                 doc_comment,
                 visibility,
                 ..
@@ -370,9 +479,16 @@ impl Visitor for WorkspaceSymbolVisitor {
                     range: get_range(range),
                     selection_range: get_range(name.range()),
                     data_types,
+                    doc_type: doc_type_from_doc!(doc_comment, return_type),
+                    return_type: match return_type.as_deref() {
+                        Some(AstNode::ReturnType { data_type, .. }) => Some(*data_type.clone()),
+                        _ => None,
+                    },
                     is_static: is_static.is_some(),
+                    is_abstract: is_abstract.is_some(),
                     visibility: Visibility::from(visibility),
                     deprecated: deprecated_from_doc!(doc_comment),
+                    templates: templates_from_doc!(doc_comment),
                     ..Symbol::default()
                 });
 
@@ -431,6 +547,8 @@ impl Visitor for WorkspaceSymbolVisitor {
                     range: get_range(range),
                     selection_range: get_range(name.range()),
                     data_types,
+                    doc_type: doc_type_from_doc!(doc_comment, return_type),
+                    templates: templates_from_doc!(doc_comment),
                     ..Symbol::default()
                 });
 
@@ -442,6 +560,8 @@ impl Visitor for WorkspaceSymbolVisitor {
                 name,
                 argument_type,
                 doc_comment,
+                visibility,
+                is_readonly,
                 ..
             } => {
                 let mut data_types = if let Some(data_type) = argument_type {
@@ -468,6 +588,7 @@ impl Visitor for WorkspaceSymbolVisitor {
                     range: get_range(node.range()),
                     selection_range: get_range(name.range()),
                     data_types,
+                    doc_type: doc_comment.as_deref().and_then(get_doc_type),
                     deprecated: deprecated_from_doc!(doc_comment),
                     ..Symbol::default()
                 });
@@ -479,6 +600,23 @@ impl Visitor for WorkspaceSymbolVisitor {
                     .parameters
                     .push(FunctionParameter::new(&child, node));
 
+                // Promoted constructor parameters are properties of the class as well
+                let is_constructor = arena[parent].get().kind == PhpSymbolKind::Method
+                    && arena[parent].get().normalized_name() == "__construct";
+
+                if is_constructor && (visibility.is_some() || is_readonly.is_some()) {
+                    if let Some(class) = arena[parent].parent() {
+                        let property = Symbol {
+                            kind: PhpSymbolKind::Property,
+                            visibility: Visibility::from(visibility),
+                            is_readonly: is_readonly.is_some() || arena[class].get().is_readonly,
+                            ..arena[child].get().clone()
+                        };
+
+                        class.append(arena.new_node(property), arena);
+                    }
+                }
+
                 NextAction::Abort
             }
             AstNode::DefineStatement { name, .. } => {
@@ -530,6 +668,77 @@ impl Visitor for WorkspaceSymbolVisitor {
     }
 }
 
+/// Returns the members every enum provides without declaring them. All enums have a `name` property
+/// and a static `cases()` method, backed enums additionally have a `value` property as well as the
+/// static `from()` and `tryFrom()` methods
+fn implicit_enum_members(name: &Token, backed_type: Option<&AstNode>) -> Vec<Symbol> {
+    let self_type = TypeRef::one(vec![Token::new(
+        TokenType::TypeSelf,
+        name.line,
+        name.col,
+        0,
+    )]);
+    let string_type = TypeRef::one(vec![Token::new(
+        TokenType::TypeString,
+        name.line,
+        name.col,
+        0,
+    )]);
+
+    let mut members = vec![
+        Symbol {
+            name: "$name".to_string(),
+            kind: PhpSymbolKind::Property,
+            data_types: vec![Reference::type_ref(string_type)],
+            ..Symbol::default()
+        },
+        Symbol {
+            name: "cases".to_string(),
+            kind: PhpSymbolKind::Method,
+            data_types: vec![Reference::type_ref(TypeRef::many(self_type.kind.clone()))],
+            is_static: true,
+            ..Symbol::default()
+        },
+    ];
+
+    if let Some(backed_type) = backed_type {
+        members.push(Symbol {
+            name: "$value".to_string(),
+            kind: PhpSymbolKind::Property,
+            data_types: get_type_ref(backed_type)
+                .into_iter()
+                .map(Reference::type_ref)
+                .collect(),
+            ..Symbol::default()
+        });
+
+        members.push(Symbol {
+            name: "from".to_string(),
+            kind: PhpSymbolKind::Method,
+            data_types: vec![Reference::type_ref(self_type.clone())],
+            is_static: true,
+            ..Symbol::default()
+        });
+        members.push(Symbol {
+            name: "tryFrom".to_string(),
+            kind: PhpSymbolKind::Method,
+            data_types: vec![
+                Reference::type_ref(self_type),
+                Reference::type_ref(TypeRef::one(vec![Token::new(
+                    TokenType::Null,
+                    name.line,
+                    name.col,
+                    0,
+                )])),
+            ],
+            is_static: true,
+            ..Symbol::default()
+        });
+    }
+
+    members
+}
+
 pub(crate) fn get_type_refs(node: &AstNode) -> Vec<TypeRef> {
     match node {
         AstNode::ReturnType { data_type, .. } => get_type_refs(data_type),
@@ -547,6 +756,17 @@ pub(crate) fn get_type_refs(node: &AstNode) -> Vec<TypeRef> {
     }
 }
 
+/// The structured type of a @param, @return or @var
+pub(crate) fn get_doc_type(node: &AstNode) -> Option<DocType> {
+    match node {
+        AstNode::DocCommentVar { doc_type, .. }
+        | AstNode::DocCommentReturn { doc_type, .. }
+        | AstNode::DocCommentParam { doc_type, .. }
+        | AstNode::DocCommentProperty { doc_type, .. } => doc_type.clone(),
+        _ => None,
+    }
+}
+
 pub(crate) fn get_type_ref(node: &AstNode) -> Vec<TypeRef> {
     if let AstNode::TypeRef(tokens) = node {
         return vec![tokens.clone()];
@@ -1,4 +1,5 @@
 use crate::parser::node::Node;
+use crate::parser::token::TokenType;
 use std::cmp::min;
 
 pub mod classes;
@@ -142,6 +143,7 @@ fn format(ast: &[Node], line: usize, col: usize, options: &FormatterOptions) ->
                 parts.push(" ".repeat(col));
                 push_if_some!(stmt.is_final.as_ref(), parts);
                 push_if_some!(stmt.is_abstract.as_ref(), parts);
+                push_if_some!(stmt.is_readonly.as_ref(), parts);
 
                 parts.push(format!(
                     "{} {}{}{}\n{}{}",
@@ -160,6 +162,44 @@ fn format(ast: &[Node], line: usize, col: usize, options: &FormatterOptions) ->
                     format_node(&stmt.body, line, col, options)
                 ))
             }
+            Node::EnumStatement {
+                token,
+                name,
+                backed_type,
+                implements,
+                body,
+                attributes,
+                ..
+            } => {
+                parts.push(format(attributes, line, col, options));
+                parts.push(" ".repeat(col));
+
+                parts.push(format!(
+                    "{} {}{}{}\n{}{}",
+                    token,
+                    name,
+                    optional_ident!(": ", "", backed_type, line, col, options),
+                    optional_ident_list!(" implements ", "", implements, line, col, options),
+                    " ".repeat(col),
+                    format_node(body, line, col, options)
+                ))
+            }
+            Node::EnumCase {
+                token,
+                name,
+                value,
+                attributes,
+                ..
+            } => {
+                parts.push(format(attributes, line, col, options));
+                parts.push(" ".repeat(col));
+                parts.push(format!(
+                    "{} {}{};\n",
+                    token,
+                    name,
+                    optional_ident!(" = ", "", value, line, col, options)
+                ));
+            }
             Node::ClassConstantDefinitionStatement {
                 token,
                 consts,
@@ -200,6 +240,7 @@ fn format(ast: &[Node], line: usize, col: usize, options: &FormatterOptions) ->
                 visibility,
                 is_abstract,
                 is_static,
+                is_readonly,
                 properties,
                 attributes,
                 ..
@@ -209,6 +250,7 @@ fn format(ast: &[Node], line: usize, col: usize, options: &FormatterOptions) ->
                 push_if_some!(is_abstract, parts);
                 push_if_some!(visibility, parts);
                 push_if_some!(is_static, parts);
+                push_if_some!(is_readonly, parts);
                 parts.push(format(properties, line, col, options));
 
                 parts.push(";\n".to_string());
@@ -574,7 +616,12 @@ fn format(ast: &[Node], line: usize, col: usize, options: &FormatterOptions) ->
 /// * line - The current start line
 /// * col - The current column / indentation level
 /// * options - The formatter options
-fn format_node(node: &Node, line: usize, col: usize, options: &FormatterOptions) -> String {
+pub(crate) fn format_node(
+    node: &Node,
+    line: usize,
+    col: usize,
+    options: &FormatterOptions,
+) -> String {
     match node {
         Node::Grouping(grouped) => format!("({})", format_node(grouped, line, col, options)),
         Node::FileInclude { token, resource } => {
@@ -922,6 +969,8 @@ fn format_node(node: &Node, line: usize, col: usize, options: &FormatterOptions)
             }
         }
         Node::FunctionArgument {
+            visibility,
+            is_readonly,
             argument_type,
             default_value,
             name,
@@ -937,6 +986,8 @@ fn format_node(node: &Node, line: usize, col: usize, options: &FormatterOptions)
                 parts.push(format(attributes, line, 0, options).trim().to_string());
                 parts.push(String::from(" "));
             }
+            push_if_some!(visibility, parts);
+            push_if_some!(is_readonly, parts);
             parts.push(optional_ident!("", " ", argument_type, line, col, options));
             push_unpadded_if_some!(spread, parts);
             push_unpadded_if_some!(reference, parts);
@@ -1029,6 +1080,28 @@ fn format_node(node: &Node, line: usize, col: usize, options: &FormatterOptions)
         Node::AliasedVariable { variable, expr } => {
             format!("{}{}", variable, format_node(expr, line, col, options))
         }
+        Node::EncapsedString { start, parts, end } => format!(
+            "{}{}{}",
+            start,
+            parts
+                .iter()
+                .map(|part| format_node(part, line, col, options))
+                .collect::<Vec<String>>()
+                .join(""),
+            end
+        ),
+        Node::EncapsedExpression { oc, expression, cc } => match (&oc.t, expression.as_ref()) {
+            // ${name} refers to the variable $name
+            (TokenType::DollarOpenCurlyBraces, Node::Variable(variable)) => {
+                format!("{}{}{}", oc, variable.label.as_ref().unwrap(), cc)
+            }
+            _ => format!(
+                "{}{}{}",
+                oc,
+                format_node(expression, line, col, options),
+                cc
+            ),
+        },
         Node::DynamicVariable {
             variable,
             oc,
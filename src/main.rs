@@ -4,8 +4,9 @@ extern crate clap;
 extern crate crossbeam_channel as channel;
 extern crate ignore;
 
+use crate::backend::check;
 use crate::backend::Backend;
-use clap::{App, Arg};
+use clap::{App, Arg, SubCommand};
 use std::path::PathBuf;
 use tower_lsp::{LspService, Server};
 
@@ -15,9 +16,9 @@ pub mod formatter;
 pub mod parser;
 pub mod suggester;
 
-#[tokio::main]
-async fn main() {
-    let matches = App::new("PHPLS-RS")
+/// Command line interface of the server and its subcommands
+fn app() -> App<'static, 'static> {
+    App::new("PHPLS-RS")
         .version("0.1")
         .author("Fabian Becker <fabian.becker@b-it-d.de>")
         .about("PHP language server written in Rust")
@@ -45,6 +46,18 @@ async fn main() {
                 .help("Only parse files in directory instead of launching a server")
                 .takes_value(true),
         )
+        .arg(
+            Arg::with_name("autoload-dev")
+                .long("autoload-dev")
+                .help("Also index the autoload-dev section of composer.json"),
+        )
+        .arg(
+            Arg::with_name("cache-dir")
+                .long("cache-dir")
+                .value_name("Symbol cache directory")
+                .help("Directory to cache the symbols of indexed files in")
+                .takes_value(true),
+        )
         .arg(
             Arg::with_name("ignore-patterns")
                 .long("ignore-patterns")
@@ -53,7 +66,38 @@ async fn main() {
                 .multiple(true)
                 .takes_value(true),
         )
-        .get_matches();
+        .subcommand(
+            SubCommand::with_name("check")
+                .about("Print the diagnostics of all files in a directory and exit")
+                .arg(
+                    Arg::with_name("path")
+                        .value_name("Directory to check")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("format")
+                        .long("format")
+                        .value_name("Output format")
+                        .possible_values(&["text", "json", "checkstyle", "sarif"])
+                        .default_value("text")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("fail-on")
+                        .long("fail-on")
+                        .value_name("Severity")
+                        .help("Exit with a non-zero status if a diagnostic of this or a higher severity is found")
+                        .possible_values(&["error", "warning", "information", "hint"])
+                        .default_value("error")
+                        .takes_value(true),
+                ),
+        )
+}
+
+#[tokio::main]
+async fn main() {
+    let matches = app().get_matches();
 
     let ignore_patterns: Vec<String> = matches
         .values_of("ignore-patterns")
@@ -61,6 +105,27 @@ async fn main() {
         .map(|s| s.to_owned())
         .collect();
 
+    if let Some(check_matches) = matches.subcommand_matches("check") {
+        let ip: Vec<PathBuf> = ignore_patterns.iter().map(PathBuf::from).collect();
+        let result = check::check(
+            &PathBuf::from(check_matches.value_of("path").unwrap()),
+            &PathBuf::from(matches.value_of("stubs").unwrap()),
+            &ip,
+            matches.is_present("autoload-dev"),
+        );
+
+        let format = check::Format::from_name(check_matches.value_of("format").unwrap()).unwrap();
+        print!("{}", check::report(&result, format));
+
+        let threshold =
+            check::severity_from_name(check_matches.value_of("fail-on").unwrap()).unwrap();
+        if check::fails(&result, threshold) {
+            std::process::exit(1);
+        }
+
+        return;
+    }
+
     if let Some(file) = matches.value_of("file") {
         match Backend::source_to_ast(file) {
             Ok((_, _, _)) => println!("Parsed ok"),
@@ -87,14 +152,39 @@ async fn main() {
     }
 
     let stubs = matches.value_of("stubs").unwrap().to_owned();
+    let autoload_dev = matches.is_present("autoload-dev");
+    let cache_dir = matches.value_of("cache-dir").map(|s| s.to_owned());
 
     let stdin = tokio::io::stdin();
     let stdout = tokio::io::stdout();
 
-    let (service, messages) =
-        LspService::new(|client| Backend::new(client, stubs, ignore_patterns));
-    Server::new(stdin, stdout)
-        .interleave(messages)
-        .serve(service)
-        .await;
+    let (service, socket) = LspService::new(|client| {
+        Backend::new(client, stubs, ignore_patterns, autoload_dev, cache_dir)
+    });
+    Server::new(stdin, stdout, socket).serve(service).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parses_stubs_along_with_check_command() {
+        let matches = app().get_matches_from(vec![
+            "phpls-rs", "--stubs", "/stubs", "check", "src", "--format", "json",
+        ]);
+        let check_matches = matches.subcommand_matches("check").unwrap();
+
+        assert_eq!(Some("/stubs"), matches.value_of("stubs"));
+        assert_eq!(Some("src"), check_matches.value_of("path"));
+        assert_eq!(Some("json"), check_matches.value_of("format"));
+    }
+
+    #[test]
+    fn test_parses_stubs_without_subcommand() {
+        let matches = app().get_matches_from(vec!["phpls-rs", "--stubs", "/stubs"]);
+
+        assert_eq!(Some("/stubs"), matches.value_of("stubs"));
+        assert!(matches.subcommand_matches("check").is_none());
+    }
 }
@@ -1,28 +1,42 @@
 use crate::parser::node::ClassStatement;
 
 use super::super::token::{Token, TokenType};
-use super::super::{ExpressionListResult, ExpressionResult, Parser};
+use super::super::{Error, ExpressionListResult, ExpressionResult, Parser};
 use super::{super::node::Node, attributes};
 use super::{comments, expressions, functions, types};
 
-// abstract_class -> "abstract" class
+// abstract_class -> "abstract" "readonly"? class
 pub(crate) fn abstract_class_statement(
     parser: &mut Parser,
     attributes: Vec<Node>,
 ) -> ExpressionResult {
     let is_abstract = parser.consume(TokenType::Abstract)?;
+    let is_readonly = parser.consume_or_ignore(TokenType::Readonly);
 
-    class_statement(parser, Some(is_abstract), None, attributes)
+    class_statement(parser, Some(is_abstract), None, is_readonly, attributes)
 }
 
-// final_class -> "final" class
+// final_class -> "final" "readonly"? class
 pub(crate) fn final_class_statement(
     parser: &mut Parser,
     attributes: Vec<Node>,
 ) -> ExpressionResult {
     let is_final = parser.consume(TokenType::Final)?;
+    let is_readonly = parser.consume_or_ignore(TokenType::Readonly);
 
-    class_statement(parser, None, Some(is_final), attributes)
+    class_statement(parser, None, Some(is_final), is_readonly, attributes)
+}
+
+// readonly_class -> "readonly" ("abstract" | "final")? class
+pub(crate) fn readonly_class_statement(
+    parser: &mut Parser,
+    attributes: Vec<Node>,
+) -> ExpressionResult {
+    let is_readonly = parser.consume(TokenType::Readonly)?;
+    let is_abstract = parser.consume_or_ignore(TokenType::Abstract);
+    let is_final = parser.consume_or_ignore(TokenType::Final);
+
+    class_statement(parser, is_abstract, is_final, Some(is_readonly), attributes)
 }
 
 // class -> "class" identifier (extends identifier_list)? (implements identifier_list)?
@@ -30,6 +44,7 @@ pub(crate) fn class_statement(
     parser: &mut Parser,
     is_abstract: Option<Token>,
     is_final: Option<Token>,
+    is_readonly: Option<Token>,
     attributes: Vec<Node>,
 ) -> ExpressionResult {
     let doc_comment = comments::consume_optional_doc_comment(parser);
@@ -51,14 +66,42 @@ pub(crate) fn class_statement(
         name,
         is_abstract,
         is_final,
+        is_readonly,
         extends,
         implements,
-        body: Box::new(class_block(parser)?),
+        body: Box::new(class_block(parser, false)?),
         doc_comment,
         attributes,
     }))
 }
 
+// enum -> "enum" identifier (":" identifier)? (implements identifier_list)?
+pub(crate) fn enum_statement(parser: &mut Parser, attributes: Vec<Node>) -> ExpressionResult {
+    let doc_comment = comments::consume_optional_doc_comment(parser);
+    let token = parser.consume(TokenType::Enum)?;
+    let name = parser.consume_identifier()?;
+
+    let backed_type = match parser.consume_or_ignore(TokenType::Colon) {
+        Some(_) => Some(Box::new(types::non_empty_type_ref(parser)?)),
+        None => None,
+    };
+
+    let implements = match parser.consume_or_ignore(TokenType::Implements) {
+        Some(_) => Some(identifier_list(parser)?),
+        None => None,
+    };
+
+    Ok(Node::EnumStatement {
+        token,
+        name,
+        backed_type,
+        implements,
+        body: Box::new(class_block(parser, true)?),
+        doc_comment,
+        attributes,
+    })
+}
+
 pub(crate) fn anonymous_class(parser: &mut Parser, attributes: Vec<Node>) -> ExpressionResult {
     let token = parser.consume(TokenType::Class)?;
 
@@ -87,12 +130,14 @@ pub(crate) fn anonymous_class(parser: &mut Parser, attributes: Vec<Node>) -> Exp
         arguments,
         extends,
         implements,
-        body: Box::new(class_block(parser)?),
+        body: Box::new(class_block(parser, false)?),
         attributes,
     })
 }
 
-pub(crate) fn class_block_statement(parser: &mut Parser) -> ExpressionResult {
+/// Parses a single statement of a class-like body. Enum cases are only allowed within the body of
+/// an enum, anywhere else they are reported and skipped
+pub(crate) fn class_block_statement(parser: &mut Parser, allow_cases: bool) -> ExpressionResult {
     if parser.next_token_one_of(&[TokenType::Use]) {
         return use_trait_statement(parser);
     }
@@ -107,10 +152,42 @@ pub(crate) fn class_block_statement(parser: &mut Parser) -> ExpressionResult {
         comments::consume_optional_doc_comment(parser)
     };
 
+    // case -> "case" identifier ("=" expression)? ";"
+    if let Some(token) = parser.consume_or_ignore(TokenType::Case) {
+        if !allow_cases {
+            parser.errors.push(Error::UnexpectedTokenError {
+                token: token.clone(),
+            });
+        }
+
+        let name = parser.consume_identifier()?;
+
+        let value = if parser.consume_or_ignore(TokenType::Assignment).is_some() {
+            Some(Box::new(expressions::expression(parser, 0)?))
+        } else {
+            None
+        };
+
+        parser.consume_or_ff_after(TokenType::Semicolon, &[TokenType::Semicolon])?;
+
+        if !allow_cases {
+            return Err(Error::UnexpectedTokenError { token });
+        }
+
+        return Ok(Node::EnumCase {
+            token,
+            name,
+            value,
+            doc_comment,
+            attributes,
+        });
+    }
+
     let mut is_abstract = None;
     let mut is_final = None;
     let mut visibility = None;
     let mut is_static = None;
+    let mut is_readonly = None;
 
     // Collect all modifiers
     while parser.next_token_one_of(&[
@@ -121,16 +198,24 @@ pub(crate) fn class_block_statement(parser: &mut Parser) -> ExpressionResult {
         TokenType::Private,
         TokenType::Protected,
         TokenType::Static,
+        TokenType::Readonly,
     ]) {
-        is_abstract = parser.consume_or_ignore(TokenType::Abstract);
-        is_final = parser.consume_or_ignore(TokenType::Final);
-        visibility = parser.consume_one_of_or_ignore(&[
-            TokenType::Public,
-            TokenType::Var,
-            TokenType::Private,
-            TokenType::Protected,
-        ]);
-        is_static = parser.consume_or_ignore(TokenType::Static);
+        is_abstract = parser
+            .consume_or_ignore(TokenType::Abstract)
+            .or(is_abstract);
+        is_final = parser.consume_or_ignore(TokenType::Final).or(is_final);
+        visibility = parser
+            .consume_one_of_or_ignore(&[
+                TokenType::Public,
+                TokenType::Var,
+                TokenType::Private,
+                TokenType::Protected,
+            ])
+            .or(visibility);
+        is_static = parser.consume_or_ignore(TokenType::Static).or(is_static);
+        is_readonly = parser
+            .consume_or_ignore(TokenType::Readonly)
+            .or(is_readonly);
     }
 
     if let Some(token) = parser.consume_or_ignore(TokenType::Const) {
@@ -228,6 +313,7 @@ pub(crate) fn class_block_statement(parser: &mut Parser) -> ExpressionResult {
         visibility,
         is_abstract,
         is_static,
+        is_readonly,
         doc_comment,
         attributes,
     })
@@ -242,12 +328,12 @@ pub(crate) fn class_block_statement(parser: &mut Parser) -> ExpressionResult {
 /// // Parse here
 /// }
 /// ```
-pub(crate) fn class_block(parser: &mut Parser) -> ExpressionResult {
+pub(crate) fn class_block(parser: &mut Parser, allow_cases: bool) -> ExpressionResult {
     let oc = parser.consume(TokenType::OpenCurly)?;
     let mut statements = Vec::new();
 
     while !parser.next_token_one_of(&[TokenType::CloseCurly]) {
-        if let Ok(statement) = class_block_statement(parser) {
+        if let Ok(statement) = class_block_statement(parser, allow_cases) {
             statements.push(statement);
         }
 
@@ -272,7 +358,7 @@ pub(crate) fn interface(parser: &mut Parser) -> ExpressionResult {
         None => None,
     };
 
-    let body = class_block(parser)?;
+    let body = class_block(parser, false)?;
 
     Ok(Node::Interface {
         token,
@@ -305,7 +391,7 @@ pub(crate) fn trait_statement(parser: &mut Parser) -> ExpressionResult {
     Ok(Node::TraitStatement {
         token: parser.consume(TokenType::Trait)?,
         name: parser.consume(TokenType::Identifier)?,
-        body: Box::new(class_block(parser)?),
+        body: Box::new(class_block(parser, false)?),
         doc_comment,
     })
 }
@@ -838,4 +924,129 @@ interface Treatable extends OtherInterface
 
         assert_eq!(expected, formatted);
     }
+
+    #[test]
+    fn test_parses_backed_enum() {
+        let mut scanner = Scanner::new(
+            "<?php enum Suit: string implements HasColor { case Hearts = 'H'; case Spades = 'S'; const Wild = self::Spades; public function color(): string { return 'Red'; } }",
+        );
+        scanner.scan().unwrap();
+
+        let (ast, errors) = Parser::ast(scanner.tokens).unwrap();
+        assert_eq!(true, errors.is_empty());
+
+        let options = FormatterOptions {
+            max_line_length: 100,
+            indent: 4,
+        };
+
+        let formatted = format_file(&ast, 0, 0, &options);
+
+        let expected = "\
+enum Suit: string implements HasColor
+{
+    case Hearts = 'H';
+    case Spades = 'S';
+    const Wild = self::Spades;
+    public function color(): string
+    {
+        return 'Red';
+    }
+}
+"
+        .to_owned();
+
+        assert_eq!(expected, formatted);
+    }
+
+    #[test]
+    fn test_parses_pure_enum() {
+        let mut scanner = Scanner::new("<?php enum Status { case Active; case Inactive; }");
+        scanner.scan().unwrap();
+
+        let (ast, errors) = Parser::ast(scanner.tokens).unwrap();
+        assert_eq!(true, errors.is_empty());
+
+        let options = FormatterOptions {
+            max_line_length: 100,
+            indent: 4,
+        };
+
+        let formatted = format_file(&ast, 0, 0, &options);
+
+        let expected = "\
+enum Status
+{
+    case Active;
+    case Inactive;
+}
+"
+        .to_owned();
+
+        assert_eq!(expected, formatted);
+    }
+
+    #[test]
+    fn test_rejects_cases_outside_of_enums() {
+        for code in [
+            "<?php class A { case X; public $a; }",
+            "<?php interface B { case X; }",
+            "<?php trait C { case X = 1; }",
+        ] {
+            let mut scanner = Scanner::new(code);
+            scanner.scan().unwrap();
+
+            let (_, errors) = Parser::ast(scanner.tokens).unwrap();
+            assert_eq!(1, errors.len(), "{}", code);
+            assert!(matches!(
+                &errors[0],
+                Error::UnexpectedTokenError { token } if token.t == TokenType::Case
+            ));
+        }
+
+        // The case is dropped, the rest of the body is kept
+        let mut scanner = Scanner::new("<?php class A { case X; public $a; }");
+        scanner.scan().unwrap();
+
+        let (ast, _) = Parser::ast(scanner.tokens).unwrap();
+        let options = FormatterOptions {
+            max_line_length: 100,
+            indent: 4,
+        };
+
+        assert_eq!(
+            "class A\n{\n    public $a;\n}\n",
+            format_file(&ast, 0, 0, &options)
+        );
+    }
+
+    #[test]
+    fn test_parses_readonly_class_with_promoted_properties() {
+        let mut scanner = Scanner::new(
+            "<?php final readonly class Dto { public function __construct(private Foo $foo, protected readonly ?int $bar) {} }",
+        );
+        scanner.scan().unwrap();
+
+        let (ast, errors) = Parser::ast(scanner.tokens).unwrap();
+        assert_eq!(true, errors.is_empty());
+
+        let options = FormatterOptions {
+            max_line_length: 100,
+            indent: 4,
+        };
+
+        let formatted = format_file(&ast, 0, 0, &options);
+
+        let expected = "\
+final readonly class Dto
+{
+    public function __construct(private Foo $foo, protected readonly ?int $bar)
+    {
+    }
+}
+"
+        .to_owned();
+
+        assert_eq!(expected, formatted);
+    }
 }
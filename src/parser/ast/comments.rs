@@ -1,4 +1,4 @@
-use crate::parser::node::TypeRef;
+use crate::parser::node::{DocType, DocTypeShapeEntry, TypeRef};
 
 use super::super::node::Node;
 use super::super::token::{Token, TokenType};
@@ -34,16 +34,25 @@ impl DocBlockScanner {
     fn token_type(&self, name: &str) -> TokenType {
         match name.to_lowercase().as_ref() {
             "null" => TokenType::Null,
-            "mixed" => TokenType::Mixed,
-            "bool" | "boolean" => TokenType::TypeBool,
-            "int" | "integer" => TokenType::TypeInt,
-            "string" | "binary" => TokenType::TypeString,
+            "mixed" | "scalar" | "numeric" | "array-key" => TokenType::Mixed,
+            "bool" | "boolean" | "true" | "false" => TokenType::TypeBool,
+            "int" | "integer" | "positive-int" | "negative-int" | "non-positive-int"
+            | "non-negative-int" | "non-zero-int" | "int-mask" | "int-mask-of" => {
+                TokenType::TypeInt
+            }
+            "string" | "binary" | "non-empty-string" | "non-falsy-string" | "numeric-string"
+            | "literal-string" | "lowercase-string" | "class-string" | "interface-string"
+            | "trait-string" | "enum-string" | "callable-string" | "key-of" | "value-of" => {
+                TokenType::TypeString
+            }
             "static" => TokenType::Static,
             "self" => TokenType::TypeSelf,
-            "array" => TokenType::TypeArray,
+            "array" | "list" | "iterable" | "non-empty-array" | "non-empty-list" => {
+                TokenType::TypeArray
+            }
             "object" => TokenType::TypeObject,
             "float" | "double" => TokenType::TypeFloat,
-            "void" => TokenType::Void,
+            "void" | "never" | "never-return" | "noreturn" => TokenType::Void,
             "callable" => TokenType::Callable,
             "generator" => TokenType::Generator,
             "resource" => TokenType::Resource,
@@ -58,29 +67,29 @@ impl DocBlockScanner {
         let mut params = Vec::new();
         let mut return_type = Vec::new();
         let mut var_docs = Vec::new();
+        let mut templates = Vec::new();
+        let mut generic_parents = Vec::new();
 
         while let Some(c) = self.advance() {
             match c {
                 '@' => {
-                    let directive = self.collect_identifer(false);
+                    let directive = self.collect_directive();
                     self.skip_blanks();
 
+                    // Generics are also annotated for specific tools only, like @phpstan-template
+                    let generics_directive = directive
+                        .trim_start_matches("phpstan-")
+                        .trim_start_matches("psalm-");
+
                     if directive.eq("param") {
                         // /** @param string|int $param The param is niiiice */
                         self.skip_blanks();
 
-                        let mut type_refs = Vec::new();
-                        while let Some(type_ref) = self.collect_type_ref(false) {
-                            type_refs.push(type_ref);
-
-                            match self.peek() {
-                                Some('|') => {
-                                    self.advance();
-                                    continue;
-                                }
-                                _ => break,
-                            }
-                        }
+                        let doc_type = self.collect_doc_type(false);
+                        let type_refs = doc_type
+                            .as_ref()
+                            .map(DocType::type_refs)
+                            .unwrap_or_default();
 
                         self.skip_blanks();
 
@@ -110,23 +119,17 @@ impl DocBlockScanner {
                                 &param_name,
                             ),
                             types: Some(type_refs),
+                            doc_type,
                             description: param_descr,
                         });
                     } else if directive.eq("return") {
                         self.skip_blanks();
 
-                        let mut type_refs = Vec::new();
-                        while let Some(type_ref) = self.collect_type_ref(true) {
-                            type_refs.push(type_ref);
-
-                            match self.peek() {
-                                Some('|') => {
-                                    self.advance();
-                                    continue;
-                                }
-                                _ => break,
-                            }
-                        }
+                        let doc_type = self.collect_doc_type(true);
+                        let type_refs = doc_type
+                            .as_ref()
+                            .map(DocType::type_refs)
+                            .unwrap_or_default();
 
                         self.skip_blanks();
 
@@ -141,11 +144,13 @@ impl DocBlockScanner {
                         if type_refs.is_empty() {
                             return_type.push(Node::DocCommentReturn {
                                 types: None,
+                                doc_type,
                                 description: return_descr,
                             });
                         } else {
                             return_type.push(Node::DocCommentReturn {
                                 types: Some(type_refs),
+                                doc_type,
                                 description: return_descr,
                             });
                         }
@@ -153,18 +158,11 @@ impl DocBlockScanner {
                         // /** @var User $rofl */
                         self.skip_blanks();
 
-                        let mut type_refs = Vec::new();
-                        while let Some(type_ref) = self.collect_type_ref(false) {
-                            type_refs.push(type_ref);
-
-                            match self.peek() {
-                                Some('|') => {
-                                    self.advance();
-                                    continue;
-                                }
-                                _ => break,
-                            }
-                        }
+                        let doc_type = self.collect_doc_type(false);
+                        let type_refs = doc_type
+                            .as_ref()
+                            .map(DocType::type_refs)
+                            .unwrap_or_default();
 
                         self.skip_blanks();
 
@@ -194,6 +192,7 @@ impl DocBlockScanner {
                                 &param_name,
                             ),
                             types: Some(type_refs),
+                            doc_type,
                             description: param_descr,
                         });
                     } else if directive.eq("deprecated") {
@@ -201,18 +200,11 @@ impl DocBlockScanner {
                     } else if directive.eq("property") {
                         self.skip_blanks();
 
-                        let mut type_refs = Vec::new();
-                        while let Some(type_ref) = self.collect_type_ref(false) {
-                            type_refs.push(type_ref);
-
-                            match self.peek() {
-                                Some('|') => {
-                                    self.advance();
-                                    continue;
-                                }
-                                _ => break,
-                            }
-                        }
+                        let doc_type = self.collect_doc_type(false);
+                        let type_refs = doc_type
+                            .as_ref()
+                            .map(DocType::type_refs)
+                            .unwrap_or_default();
 
                         self.skip_blanks();
 
@@ -248,8 +240,51 @@ impl DocBlockScanner {
                                 &param_name,
                             ),
                             types: type_refs,
+                            doc_type,
                             description: param_descr,
                         })
+                    } else if matches!(
+                        generics_directive,
+                        "template" | "template-covariant" | "template-contravariant"
+                    ) {
+                        // /** @template T of Model */
+                        let name_start = (self.line, self.col);
+                        let name = self.collect_identifer(false);
+                        self.skip_spaces();
+
+                        let bound = match self.collect_identifer(false).as_ref() {
+                            "of" | "as" => {
+                                self.skip_spaces();
+                                self.collect_doc_type(false)
+                                    .and_then(|bound| bound.type_refs().into_iter().next())
+                            }
+                            _ => None,
+                        };
+
+                        self.skip_line();
+
+                        if !name.is_empty() {
+                            templates.push(Node::DocCommentTemplate {
+                                name: Token::named(
+                                    TokenType::Identifier,
+                                    name_start.0,
+                                    name_start.1,
+                                    0,
+                                    &name,
+                                ),
+                                bound,
+                            });
+                        }
+                    } else if matches!(
+                        generics_directive.trim_start_matches("template-"),
+                        "extends" | "implements" | "use"
+                    ) {
+                        // /** @extends Collection<int, User> */
+                        if let Some(parent) = self.collect_doc_type(false) {
+                            generic_parents.extend(parent.type_refs());
+                        }
+
+                        self.skip_line();
                     }
                 }
                 '*' => (),
@@ -265,53 +300,415 @@ impl DocBlockScanner {
             return_type,
             var_docs,
             properties,
+            templates,
+            generic_parents,
         })))
     }
 
-    fn collect_type_ref(&mut self, allow_this: bool) -> Option<TypeRef> {
+    /// Collect a type of the PHPDoc type grammar, like `array{id: int, user: User}|null`
+    fn collect_doc_type(&mut self, allow_this: bool) -> Option<DocType> {
+        let mut members = vec![self.collect_intersection_type(allow_this)?];
+
+        while let Some('|') = self.peek() {
+            self.advance();
+
+            match self.collect_intersection_type(allow_this) {
+                Some(member) => members.push(member),
+                None => break,
+            }
+        }
+
+        if members.len() == 1 {
+            members.pop()
+        } else {
+            Some(DocType::Union(members))
+        }
+    }
+
+    fn collect_intersection_type(&mut self, allow_this: bool) -> Option<DocType> {
+        let mut members = vec![self.collect_prefixed_type(allow_this)?];
+
+        // An & directly followed by a variable marks a parameter passed by reference
+        while let (Some('&'), Some(next)) = (self.peek(), self.peek_next()) {
+            if *next == '$' || next.is_whitespace() {
+                break;
+            }
+
+            self.advance();
+
+            match self.collect_prefixed_type(allow_this) {
+                Some(member) => members.push(member),
+                None => break,
+            }
+        }
+
+        if members.len() == 1 {
+            members.pop()
+        } else {
+            Some(DocType::Intersection(members))
+        }
+    }
+
+    /// Collect a type that may be nullable like ?User or a collection like User[]
+    fn collect_prefixed_type(&mut self, allow_this: bool) -> Option<DocType> {
+        if let Some('?') = self.peek() {
+            let null = Token::named(TokenType::Null, self.line, self.col, 0, "null");
+            self.advance();
+
+            return Some(DocType::Nullable {
+                null,
+                inner: Box::new(self.collect_prefixed_type(allow_this)?),
+            });
+        }
+
+        let mut doc_type = self.collect_primary_type(allow_this)?;
+
+        while let (Some('['), Some(']')) = (self.peek(), self.peek_next()) {
+            self.advance();
+            self.advance();
+
+            doc_type = DocType::Array(Box::new(doc_type));
+        }
+
+        Some(doc_type)
+    }
+
+    fn collect_primary_type(&mut self, allow_this: bool) -> Option<DocType> {
+        let start = (self.line, self.col);
+
+        match self.peek()? {
+            '(' => {
+                self.advance();
+                self.skip_blanks();
+                let doc_type = self.collect_doc_type(allow_this)?;
+                self.skip_blanks();
+
+                if let Some(')') = self.advance() {
+                    Some(doc_type)
+                } else {
+                    None
+                }
+            }
+            '\'' | '"' => {
+                let quote = self.advance()?;
+                let mut literal = String::from(quote);
+
+                while let Some(c) = self.advance() {
+                    literal.push(c);
+
+                    if c == quote {
+                        break;
+                    }
+                }
+
+                Some(DocType::Literal(Token::named(
+                    TokenType::TypeString,
+                    start.0,
+                    start.1,
+                    0,
+                    &literal,
+                )))
+            }
+            c if c.is_ascii_digit() || *c == '-' => {
+                let mut literal = String::new();
+
+                while let Some(&c) = self.peek() {
+                    if c.is_ascii_alphanumeric() || c == '.' || c == '_' || c == '-' {
+                        literal.push(c);
+                        self.advance();
+                    } else {
+                        break;
+                    }
+                }
+
+                let t = if literal.contains('.') {
+                    TokenType::TypeFloat
+                } else {
+                    TokenType::TypeInt
+                };
+
+                Some(DocType::Literal(Token::named(
+                    t, start.0, start.1, 0, &literal,
+                )))
+            }
+            _ => self.collect_named_type(allow_this),
+        }
+    }
+
+    /// Collect a named type along with its type arguments, shape or signature
+    fn collect_named_type(&mut self, allow_this: bool) -> Option<DocType> {
+        let name = self.collect_type_name(allow_this)?;
+        let keyword = name.tip().unwrap_or_default().to_lowercase();
+
+        // Class constants like Status::DRAFT or Status::*
+        if let (Some(':'), Some(':')) = (self.peek(), self.peek_next()) {
+            let start = name.root_token().clone();
+            let mut constant = String::from(&name);
+
+            while let Some(&c) = self.peek() {
+                if c == ':' || c == '*' || c.is_alphanumeric() || c == '_' {
+                    constant.push(c);
+                    self.advance();
+                } else {
+                    break;
+                }
+            }
+
+            return Some(DocType::Literal(Token::named(
+                TokenType::Mixed,
+                start.line,
+                start.col,
+                0,
+                &constant,
+            )));
+        }
+
+        match self.peek() {
+            Some('<') => {
+                self.advance();
+                let arguments = self.collect_type_list('>', allow_this);
+
+                Some(DocType::Named { name, arguments })
+            }
+            Some('{') if matches!(keyword.as_ref(), "array" | "list" | "object") => {
+                self.advance();
+                let entries = self.collect_shape_entries(allow_this);
+
+                Some(DocType::Shape {
+                    keyword: name.kind.into_iter().last()?,
+                    entries,
+                })
+            }
+            Some('(') if matches!(keyword.as_ref(), "callable" | "closure" | "pure-callable") => {
+                self.advance();
+                let parameters = self.collect_type_list(')', allow_this);
+
+                let return_type = if let Some(':') = self.peek() {
+                    self.advance();
+                    self.skip_spaces();
+
+                    self.collect_prefixed_type(allow_this).map(Box::new)
+                } else {
+                    None
+                };
+
+                Some(DocType::Callable {
+                    name,
+                    parameters,
+                    return_type,
+                })
+            }
+            _ => Some(DocType::Named {
+                name,
+                arguments: Vec::new(),
+            }),
+        }
+    }
+
+    /// Collect a name like \Ns\Class or non-empty-string
+    fn collect_type_name(&mut self, allow_this: bool) -> Option<TypeRef> {
         let mut type_ref_parts = Vec::new();
 
         loop {
-            let current_start = (self.line, self.col);
-            let identifier = &self.collect_identifer(allow_this);
-            if !identifier.is_empty() {
-                type_ref_parts.push(Token::named(
-                    self.token_type(identifier),
-                    current_start.0,
-                    current_start.1,
+            let start = (self.line, self.col);
+
+            if let Some('\\') = self.peek() {
+                self.advance();
+                type_ref_parts.push(Token::new(
+                    TokenType::NamespaceSeparator,
+                    start.0,
+                    start.1,
                     0,
-                    identifier,
                 ));
+
+                continue;
             }
 
-            let n = self.peek();
-            match n {
-                Some('\\') => type_ref_parts.push(Token::new(
-                    TokenType::NamespaceSeparator,
-                    current_start.0,
-                    current_start.1,
-                    0,
-                )),
-                Some('[') => {
+            let mut identifier = self.collect_identifer(allow_this);
+            if identifier.is_empty() {
+                break;
+            }
+
+            // Keywords like non-empty-string or class-string
+            while let (Some('-'), Some(next)) = (self.peek(), self.peek_next()) {
+                if !next.is_alphanumeric() {
+                    break;
+                }
+
+                self.advance();
+                identifier.push('-');
+                identifier.push_str(&self.collect_identifer(false));
+            }
+
+            type_ref_parts.push(Token::named(
+                self.token_type(&identifier),
+                start.0,
+                start.1,
+                0,
+                &identifier,
+            ));
+
+            if let Some('\\') = self.peek() {
+                continue;
+            }
+
+            break;
+        }
+
+        if type_ref_parts.is_empty() {
+            None
+        } else {
+            Some(TypeRef::one(type_ref_parts))
+        }
+    }
+
+    /// Collect comma separated types like the arguments of Collection<int, User> or the
+    /// parameters of callable(int $a, string ...$b), up to the closing delimiter
+    fn collect_type_list(&mut self, delimiter: char, allow_this: bool) -> Vec<DocType> {
+        let mut types = Vec::new();
+
+        loop {
+            self.skip_blanks();
+            if let Some(&c) = self.peek() {
+                if c == delimiter {
                     self.advance();
-                    if let Some(']') = self.advance() {
-                        if !type_ref_parts.is_empty() {
-                            return Some(TypeRef::many(type_ref_parts));
-                        } else {
-                            return None;
-                        }
-                    }
+
+                    return types;
                 }
-                _ => {
-                    if !type_ref_parts.is_empty() {
-                        return Some(TypeRef::one(type_ref_parts));
-                    } else {
-                        return None;
-                    }
+            }
+
+            match self.collect_doc_type(allow_this) {
+                Some(doc_type) => types.push(doc_type),
+                None => return types,
+            }
+
+            // Names, default values and variadics of callable parameters
+            while let Some(&c) = self.peek() {
+                if c == ',' || c == delimiter || c == '\n' {
+                    break;
                 }
+
+                self.advance();
+            }
+
+            if let Some(',') = self.peek() {
+                self.advance();
             }
+        }
+    }
+
+    /// Collect the entries of an array shape up to the closing }
+    fn collect_shape_entries(&mut self, allow_this: bool) -> Vec<DocTypeShapeEntry> {
+        let mut entries = Vec::new();
+
+        loop {
+            self.skip_blanks();
+
+            match self.peek() {
+                Some('}') => {
+                    self.advance();
+
+                    return entries;
+                }
+                // Unsealed shapes like array{id: int, ...}
+                Some('.') => {
+                    self.advance();
+                    continue;
+                }
+                _ => (),
+            }
+
+            let (key, optional) = match self.collect_shape_key() {
+                Some((key, optional)) => (Some(key), optional),
+                None => (None, false),
+            };
+
+            self.skip_blanks();
+            match self.collect_doc_type(allow_this) {
+                Some(value) => entries.push(DocTypeShapeEntry {
+                    key,
+                    optional,
+                    value,
+                }),
+                None => return entries,
+            }
+
+            self.skip_blanks();
+            if let Some(',') = self.peek() {
+                self.advance();
+            }
+        }
+    }
+
+    /// Collect the key of a shape entry like `id:` or `'user'?:`. Entries without a key are
+    /// left untouched
+    fn collect_shape_key(&mut self) -> Option<(String, bool)> {
+        let mut key = String::new();
+        let mut rest = self.chars.iter().rev().peekable();
+
+        let quote = match rest.peek() {
+            Some('\'') | Some('"') => rest.next().copied(),
+            _ => None,
+        };
+
+        while let Some(&&c) = rest.peek() {
+            if Some(c) == quote {
+                rest.next();
+                break;
+            } else if quote.is_some() || c.is_alphanumeric() || c == '_' || c == '-' {
+                key.push(c);
+                rest.next();
+            } else {
+                break;
+            }
+        }
+
+        let mut length = key.chars().count() + if quote.is_some() { 2 } else { 0 };
+        while let Some(' ') = rest.peek() {
+            rest.next();
+            length += 1;
+        }
+
+        let optional = if let Some('?') = rest.peek() {
+            rest.next();
+            length += 1;
+
+            true
+        } else {
+            false
+        };
+
+        // A single colon, as two of them are a class constant
+        if key.is_empty() || rest.next() != Some(&':') || rest.next() == Some(&':') {
+            return None;
+        }
+
+        for _ in 0..=length {
+            self.advance();
+        }
+
+        Some((key, optional))
+    }
+
+    /// Collect the name of a directive like param or phpstan-template
+    fn collect_directive(&mut self) -> String {
+        let mut directive = self.collect_identifer(false);
 
+        while let Some('-') = self.peek() {
             self.advance();
+            directive.push('-');
+            directive.push_str(&self.collect_identifer(false));
+        }
+
+        directive
+    }
+
+    /// Skip the rest of the current line, like the description of a directive
+    fn skip_line(&mut self) {
+        while let Some(c) = self.advance() {
+            if c == '\n' {
+                return;
+            }
         }
     }
 
@@ -336,6 +733,11 @@ impl DocBlockScanner {
         self.chars.last()
     }
 
+    /// Return the token after the next one without popping anything off the stream
+    fn peek_next(&self) -> Option<&char> {
+        self.chars.iter().rev().nth(1)
+    }
+
     fn advance(&mut self) -> Option<char> {
         if let Some(c) = self.chars.pop() {
             if c == '\n' || c == '\r' {
@@ -351,6 +753,13 @@ impl DocBlockScanner {
         None
     }
 
+    /// Skip blanks without leaving the current line
+    fn skip_spaces(&mut self) {
+        while let Some(' ') | Some('\t') = self.peek() {
+            self.advance();
+        }
+    }
+
     fn skip_blanks(&mut self) {
         while let Some(c) = self.peek() {
             if c.is_whitespace() {
@@ -422,4 +831,144 @@ mod tests {
             _ => {}
         }
     }
+
+    #[test]
+    fn test_parses_generic_collections_in_vardoc_comments() {
+        let mut scanner = Scanner::new(
+            "<?php
+/** @var array<int, Foo> $list */",
+        );
+        scanner.scan().unwrap();
+
+        let (ast, errors) = Parser::ast(scanner.tokens).unwrap();
+
+        assert_eq!(true, errors.is_empty());
+        match ast.first().unwrap() {
+            Node::DocCommentVar { types, name, .. } => {
+                let type_ref = types.clone().unwrap().first().unwrap().clone();
+
+                assert_eq!("list", name.label.clone().unwrap());
+                assert!(type_ref.is_multiple());
+                assert_eq!("Foo", type_ref.to_fqdn());
+            }
+            _ => panic!("Expected a var doc comment"),
+        }
+    }
+
+    #[test]
+    fn test_parses_templates_and_generic_parents() {
+        let mut scanner = Scanner::new(
+            "<?php
+/**
+ * @template TKey
+ * @phpstan-template-covariant TValue of Model
+ * @extends Base<TKey, Collection<int, TValue>>
+ */
+class Collection extends Base {}",
+        );
+        scanner.scan().unwrap();
+
+        let (ast, errors) = Parser::ast(scanner.tokens).unwrap();
+
+        assert!(errors.is_empty());
+        let doc_comment = match ast.first().unwrap() {
+            Node::ClassStatement(class) => class.doc_comment.clone().unwrap(),
+            _ => panic!("Expected a class"),
+        };
+
+        match doc_comment.as_ref() {
+            Node::DocComment {
+                templates,
+                generic_parents,
+                ..
+            } => {
+                let templates = templates
+                    .iter()
+                    .map(|template| match template {
+                        Node::DocCommentTemplate { name, bound } => (
+                            name.to_string(),
+                            bound.as_ref().map(|bound| bound.to_fqdn()),
+                        ),
+                        _ => panic!("Expected a template"),
+                    })
+                    .collect::<Vec<(String, Option<String>)>>();
+
+                assert_eq!(
+                    vec![
+                        (String::from("TKey"), None),
+                        (String::from("TValue"), Some(String::from("Model")))
+                    ],
+                    templates
+                );
+
+                let parent = generic_parents.first().unwrap();
+                assert_eq!("Base", parent.to_fqdn());
+                assert_eq!(2, parent.arguments().len());
+                assert_eq!("TKey", parent.arguments()[0].to_fqdn());
+                assert_eq!("Collection", parent.arguments()[1].to_fqdn());
+                assert_eq!(2, parent.arguments()[1].arguments().len());
+            }
+            _ => panic!("Expected a doc comment"),
+        }
+    }
+
+    #[test]
+    fn test_parses_structured_doc_types() {
+        let mut scanner = Scanner::new(
+            "<?php
+/**
+ * @var array{id: int, user?: \\App\\User, string} $row
+ * @var list<User>|null $list
+ * @var ?array<string, User[]> $map
+ * @var Countable&Traversable $items
+ * @var callable(int, string): bool $callback
+ * @var class-string<Model> $class
+ */
+class Types {}",
+        );
+        scanner.scan().unwrap();
+
+        let (ast, errors) = Parser::ast(scanner.tokens).unwrap();
+
+        assert!(errors.is_empty());
+        let doc_comment = match ast.first().unwrap() {
+            Node::ClassStatement(class) => class.doc_comment.clone().unwrap(),
+            _ => panic!("Expected a class"),
+        };
+
+        let doc_types = match doc_comment.as_ref() {
+            Node::DocComment { var_docs, .. } => var_docs
+                .iter()
+                .map(|var_doc| match var_doc {
+                    Node::DocCommentVar { doc_type, .. } => doc_type.clone().unwrap(),
+                    _ => panic!("Expected a var doc"),
+                })
+                .collect::<Vec<DocType>>(),
+            _ => panic!("Expected a doc comment"),
+        };
+
+        assert_eq!(
+            vec![
+                "array{id: int, user?: \\App\\User, string}",
+                "list<User>|null",
+                "?array<string, User[]>",
+                "Countable&Traversable",
+                "callable(int, string): bool",
+                "class-string<Model>",
+            ],
+            doc_types
+                .iter()
+                .map(|doc_type| doc_type.to_string())
+                .collect::<Vec<String>>()
+        );
+
+        let row = &doc_types[0];
+        assert_eq!("\\App\\User", row.shape_entry("user").unwrap().to_string());
+        assert_eq!("string", row.shape_entry("0").unwrap().to_string());
+        assert!(row.shape_entry("missing").is_none());
+
+        assert_eq!("User", doc_types[1].value_type().unwrap().to_string());
+        assert_eq!("User[]", doc_types[2].value_type().unwrap().to_string());
+        assert_eq!(2, doc_types[3].type_refs().len());
+    }
 }
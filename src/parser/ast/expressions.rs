@@ -1,7 +1,7 @@
 use super::super::token::TokenType;
 use super::super::{Error, ExpressionResult, Parser};
 use super::{super::node::Node, attributes::attributes_block};
-use super::{arrays, calls, classes, conditionals, functions, keywords, types, variables};
+use super::{arrays, calls, classes, conditionals, functions, keywords, strings, types, variables};
 
 pub(crate) fn expression_statement(parser: &mut Parser) -> ExpressionResult {
     let value = expression(parser, 0)?;
@@ -114,6 +114,7 @@ pub(crate) fn primary(parser: &mut Parser) -> ExpressionResult {
         TokenType::BinaryNumber,
         TokenType::ConstantEncapsedString,
         TokenType::EncapsedAndWhitespaceString,
+        TokenType::EncapsedStringPart,
         TokenType::ShellEscape,
         TokenType::ConstDir,
         TokenType::ConstFile,
@@ -137,12 +138,30 @@ pub(crate) fn primary(parser: &mut Parser) -> ExpressionResult {
         return types::non_empty_type_ref(parser);
     }
 
+    if let Some(start) = parser.consume_or_ignore(TokenType::DoubleQuote) {
+        return Ok(Node::EncapsedString {
+            start,
+            parts: strings::encapsed_parts(parser, TokenType::DoubleQuote)?,
+            end: parser.consume(TokenType::DoubleQuote)?,
+        });
+    }
+
     if parser.next_token_one_of(&[TokenType::HereDocStart]) {
-        parser.consume_or_ff_after(TokenType::HereDocStart, &[TokenType::HereDocStart])?;
-        let string = parser.next().unwrap();
-        parser.consume_or_ff_after(TokenType::HereDocEnd, &[TokenType::HereDocEnd])?;
+        let start = parser.consume(TokenType::HereDocStart)?;
+
+        // Heredocs without interpolation are just one string
+        if parser.next_token_one_of(&[TokenType::EncapsedAndWhitespaceString]) {
+            let string = parser.next().unwrap();
+            parser.consume_or_ff_after(TokenType::HereDocEnd, &[TokenType::HereDocEnd])?;
 
-        return Ok(Node::Literal(string));
+            return Ok(Node::Literal(string));
+        }
+
+        return Ok(Node::EncapsedString {
+            start,
+            parts: strings::encapsed_parts(parser, TokenType::HereDocEnd)?,
+            end: parser.consume(TokenType::HereDocEnd)?,
+        });
     }
 
     if let Some(isset) = parser.consume_or_ignore(TokenType::Isset) {
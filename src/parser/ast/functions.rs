@@ -26,6 +26,28 @@ pub(crate) fn argument_list(
 
     loop {
         let attributes = attributes_block(parser)?;
+
+        // Modifiers of promoted constructor properties
+        let mut visibility = None;
+        let mut is_readonly = None;
+        while parser.next_token_one_of(&[
+            TokenType::Public,
+            TokenType::Private,
+            TokenType::Protected,
+            TokenType::Readonly,
+        ]) {
+            visibility = parser
+                .consume_one_of_or_ignore(&[
+                    TokenType::Public,
+                    TokenType::Private,
+                    TokenType::Protected,
+                ])
+                .or(visibility);
+            is_readonly = parser
+                .consume_or_ignore(TokenType::Readonly)
+                .or(is_readonly);
+        }
+
         let argument_type = argument_type(parser)?;
         let reference = parser.consume_or_ignore(TokenType::BinaryAnd);
         let spread = parser.consume_or_ignore(TokenType::Elipsis);
@@ -41,6 +63,8 @@ pub(crate) fn argument_list(
         let doc_comment = comments::param_comment_for(doc_comment, &name).map(Box::new);
 
         arguments.push(Node::FunctionArgument {
+            visibility,
+            is_readonly,
             argument_type,
             name,
             spread,
@@ -10,5 +10,6 @@ pub mod functions;
 pub mod keywords;
 pub mod loops;
 pub mod namespaces;
+pub mod strings;
 pub mod types;
 pub mod variables;
@@ -0,0 +1,119 @@
+use crate::parser::Error;
+
+use super::super::node::Node;
+use super::super::token::TokenType;
+use super::super::{expressions, ExpressionListResult, ExpressionResult, Parser};
+use super::variables;
+
+/// Parses the literal parts and embedded expressions of a double quoted string or a heredoc
+/// until the `end` token is reached
+pub(crate) fn encapsed_parts(parser: &mut Parser, end: TokenType) -> ExpressionListResult {
+    let mut parts = Vec::new();
+
+    while !parser.next_token_one_of(&[end.clone(), TokenType::Eof]) {
+        if let Some(part) = parser.consume_or_ignore(TokenType::EncapsedStringPart) {
+            parts.push(Node::Literal(part));
+        } else if let Some(oc) = parser
+            .consume_one_of_or_ignore(&[TokenType::OpenCurly, TokenType::DollarOpenCurlyBraces])
+        {
+            parts.push(Node::EncapsedExpression {
+                oc,
+                expression: Box::new(expressions::expression(parser, 0)?),
+                cc: parser.consume(TokenType::CloseCurly)?,
+            });
+        } else {
+            parts.push(simple_interpolation(parser)?);
+        }
+    }
+
+    Ok(parts)
+}
+
+/// Parses the simple interpolation syntax, which only allows a variable followed by either
+/// one array offset or one property
+///
+/// # Details
+/// ```php
+/// "$var $var[0] $var[key] $var[$key] $var->property"
+/// ```
+fn simple_interpolation(parser: &mut Parser) -> ExpressionResult {
+    let variable = variables::variable(parser)?;
+
+    if let Some(ob) = parser.consume_or_ignore(TokenType::OpenBrackets) {
+        let index = match parser.next() {
+            Some(index) if index.t == TokenType::Variable => Node::Variable(index),
+            Some(index) => Node::Literal(index),
+            None => return Err(Error::Eof),
+        };
+
+        return Ok(Node::Field {
+            array: Box::new(variable),
+            ob,
+            index: Some(Box::new(index)),
+            cb: parser.consume(TokenType::CloseBrackets)?,
+        });
+    }
+
+    if let Some(arrow) = parser
+        .consume_one_of_or_ignore(&[TokenType::ObjectOperator, TokenType::NullsafeObjectOperator])
+    {
+        return Ok(Node::Member {
+            object: Box::new(variable),
+            arrow,
+            oc: None,
+            member: Box::new(Node::Literal(parser.consume_identifier()?)),
+            cc: None,
+        });
+    }
+
+    Ok(variable)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::formatter::{format_file, FormatterOptions};
+    use crate::parser::node::Node;
+    use crate::parser::{scanner::Scanner, Parser};
+
+    #[test]
+    fn test_parses_interpolated_string() {
+        let mut scanner = Scanner::new(
+            "<?php echo \"Hi $name[0], $user->name {$user->getName()} ${var} $list[$key]!\";",
+        );
+        scanner.scan().unwrap();
+
+        let (ast, errors) = Parser::ast(scanner.tokens).unwrap();
+        assert!(errors.is_empty());
+
+        let options = FormatterOptions {
+            max_line_length: 100,
+            indent: 4,
+        };
+
+        let formatted = format_file(&ast, 0, 0, &options);
+        let expected =
+            "echo \"Hi $name[0], $user->name {$user->getName()} ${var} $list[$key]!\";\n"
+                .to_owned();
+
+        assert_eq!(expected, formatted);
+    }
+
+    #[test]
+    fn test_parses_interpolated_heredoc() {
+        let mut scanner = Scanner::new("<?php $x = <<<EOT\n  Hello {$name}\nEOT;\n");
+        scanner.scan().unwrap();
+
+        let (ast, errors) = Parser::ast(scanner.tokens).unwrap();
+        assert!(errors.is_empty());
+
+        let parts = match &ast[0] {
+            Node::ExpressionStatement { expression } => match expression.as_ref() {
+                Node::Binary { right, .. } => right.children().len(),
+                _ => 0,
+            },
+            _ => 0,
+        };
+
+        assert_eq!(3, parts);
+    }
+}
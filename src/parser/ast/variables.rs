@@ -93,6 +93,12 @@ pub(crate) fn static_variables(parser: &mut Parser, token: Token) -> ExpressionR
                 assignment: Some(assignment),
                 value: Some(Box::new(expressions::expression(parser, 0)?)),
             });
+        } else {
+            assignments.push(Node::StaticVariable {
+                variable,
+                assignment: None,
+                value: None,
+            });
         }
 
         if parser.consume_or_ignore(TokenType::Comma).is_none() {
@@ -158,6 +164,7 @@ pub(crate) fn lexical_variable(parser: &mut Parser) -> ExpressionResult {
 
 #[cfg(test)]
 mod test {
+    use crate::parser::node::Node;
     use crate::parser::scanner::Scanner;
     use crate::parser::Parser;
 
@@ -182,4 +189,19 @@ mod test {
 
         assert!(!ast_result.1.is_empty());
     }
+
+    #[test]
+    fn test_parses_static_variables_without_value() {
+        let code_semicolon = "<?php static $a, $b = 1;";
+
+        let mut scanner = Scanner::new(code_semicolon);
+        let tokens = scanner.scan().unwrap();
+        let ast_result = Parser::ast(tokens.clone()).unwrap();
+
+        assert!(ast_result.1.is_empty());
+        assert!(matches!(
+            &ast_result.0[0],
+            Node::StaticVariablesStatement { assignments, .. } if assignments.len() == 2
+        ));
+    }
 }
@@ -394,11 +394,15 @@ impl Parser {
                 TokenType::Goto => return keywords::goto_statement(self),
                 TokenType::Return => return functions::return_statement(self),
                 TokenType::Throw => return exception_handling::throw_statement(self),
-                TokenType::Class => return classes::class_statement(self, None, None, attributes),
+                TokenType::Class => {
+                    return classes::class_statement(self, None, None, None, attributes)
+                }
                 TokenType::Trait => return classes::trait_statement(self),
                 TokenType::Abstract => return classes::abstract_class_statement(self, attributes),
                 TokenType::Final => return classes::final_class_statement(self, attributes),
+                TokenType::Readonly => return classes::readonly_class_statement(self, attributes),
                 TokenType::Interface => return classes::interface(self),
+                TokenType::Enum => return classes::enum_statement(self, attributes),
                 TokenType::While => return loops::while_statement(self),
                 TokenType::Do => return loops::do_while_statement(self),
                 TokenType::For => return loops::for_statement(self),
@@ -1,23 +1,30 @@
 use lsp_types::Range;
+use serde::{Deserialize, Serialize};
 
 use super::token::{Token, TokenType};
 use std::{iter::Skip, ops::Deref, slice::Iter};
 
-#[derive(Debug, PartialEq, Clone, Default)]
+#[derive(Debug, PartialEq, Clone, Default, Serialize, Deserialize)]
 pub struct TypeRef {
     pub(crate) kind: Vec<Token>,
 
     /// Indicator that we are dealing with an array of that type
     /// This is used when parsing phpDoc comments a la Class[] or Array<Class>
     multiple: bool,
+
+    /// Type arguments of a generic type from a phpDoc comment, like `int` and `User` in
+    /// Collection<int, User>
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    arguments: Vec<TypeRef>,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub struct ClassStatement {
     pub(crate) token: Token,
     pub(crate) name: Token,
     pub(crate) is_abstract: Option<Token>,
     pub(crate) is_final: Option<Token>,
+    pub(crate) is_readonly: Option<Token>,
     pub(crate) implements: Option<Vec<Node>>,
     pub(crate) extends: Option<Box<Node>>,
     pub(crate) body: Box<Node>,
@@ -25,7 +32,7 @@ pub struct ClassStatement {
     pub(crate) attributes: Vec<Node>,
 }
 
-#[derive(Debug, PartialEq, Hash, Eq, Clone, Copy)]
+#[derive(Debug, PartialEq, Hash, Eq, Clone, Copy, Serialize, Deserialize)]
 pub struct NodeRange {
     /// Start column
     pub(crate) start_col: u32,
@@ -156,6 +163,7 @@ impl TypeRef {
         Self {
             kind,
             multiple: false,
+            arguments: Vec::new(),
         }
     }
 
@@ -163,6 +171,16 @@ impl TypeRef {
         Self {
             kind,
             multiple: true,
+            arguments: Vec::new(),
+        }
+    }
+
+    /// Generic type like Collection<int, User>
+    pub fn generic(kind: Vec<Token>, arguments: Vec<TypeRef>) -> Self {
+        Self {
+            kind,
+            multiple: false,
+            arguments,
         }
     }
 
@@ -170,6 +188,7 @@ impl TypeRef {
         Self {
             kind: self.kind.clone(),
             multiple: false,
+            arguments: Vec::new(),
         }
     }
 
@@ -182,7 +201,8 @@ impl TypeRef {
             .collect();
         Self {
             kind: combined,
-            ..*orig
+            multiple: orig.multiple,
+            arguments: orig.arguments.clone(),
         }
     }
 
@@ -190,6 +210,11 @@ impl TypeRef {
         self.multiple
     }
 
+    /// The type arguments of a generic type
+    pub fn arguments(&self) -> &[TypeRef] {
+        &self.arguments
+    }
+
     pub fn range(&self) -> NodeRange {
         NodeRange::from_range(
             &self.kind.first().unwrap().range(),
@@ -316,6 +341,7 @@ impl From<Vec<Token>> for TypeRef {
         TypeRef {
             kind: tokens,
             multiple: false,
+            arguments: Vec::new(),
         }
     }
 }
@@ -325,11 +351,232 @@ impl From<[Token; 0]> for TypeRef {
         TypeRef {
             kind: tokens.into(),
             multiple: false,
+            arguments: Vec::new(),
+        }
+    }
+}
+
+/// A type of the PHPDoc type grammar, like `array{id: int, user: User}`, `list<Foo>` or
+/// `callable(int): string`
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub enum DocType {
+    /// A class, keyword or template with optional type arguments, like Collection<int, User>
+    Named {
+        name: TypeRef,
+        arguments: Vec<DocType>,
+    },
+    /// An array or object shape like array{id: int, user?: User}
+    Shape {
+        keyword: Token,
+        entries: Vec<DocTypeShapeEntry>,
+    },
+    /// A collection of a type, like User[]
+    Array(Box<DocType>),
+    /// A type that may be null, like ?User
+    Nullable {
+        null: Token,
+        inner: Box<DocType>,
+    },
+    Union(Vec<DocType>),
+    Intersection(Vec<DocType>),
+    /// A callable with a signature, like callable(int): string or Closure(User): void
+    Callable {
+        name: TypeRef,
+        parameters: Vec<DocType>,
+        return_type: Option<Box<DocType>>,
+    },
+    /// A constant value like 'draft' or 42
+    Literal(Token),
+}
+
+/// An entry of an array shape. Entries of list shapes like array{int, string} have no key
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
+pub struct DocTypeShapeEntry {
+    pub key: Option<String>,
+    pub optional: bool,
+    pub value: DocType,
+}
+
+impl DocType {
+    /// Flatten the type into the types it may have at runtime. Collections become multiple
+    /// type refs of their value type, shapes become arrays
+    pub fn type_refs(&self) -> Vec<TypeRef> {
+        match self {
+            DocType::Named { name, arguments } => {
+                if let Some(value) = self.collection_value() {
+                    return value
+                        .type_refs()
+                        .into_iter()
+                        .map(|type_ref| TypeRef::many(type_ref.kind))
+                        .collect();
+                }
+
+                // Arguments are positional, so each of them is kept even if it has no type
+                let arguments = arguments
+                    .iter()
+                    .map(|argument| argument.type_refs().into_iter().next().unwrap_or_default())
+                    .collect();
+
+                vec![TypeRef::generic(name.kind.clone(), arguments)]
+            }
+            DocType::Array(value) => value
+                .type_refs()
+                .into_iter()
+                .map(|type_ref| TypeRef::many(type_ref.kind))
+                .collect(),
+            DocType::Shape { keyword, .. } => vec![TypeRef::one(vec![keyword.clone()])],
+            DocType::Nullable { null, inner } => {
+                let mut type_refs = inner.type_refs();
+                type_refs.push(TypeRef::one(vec![null.clone()]));
+
+                type_refs
+            }
+            DocType::Union(members) | DocType::Intersection(members) => {
+                members.iter().flat_map(DocType::type_refs).collect()
+            }
+            DocType::Callable { name, .. } => vec![name.clone()],
+            DocType::Literal(token) => vec![TypeRef::one(vec![token.clone()])],
+        }
+    }
+
+    /// The type of the items of a collection, like User in list<User> or User[]
+    pub fn value_type(&self) -> Option<&DocType> {
+        match self {
+            DocType::Array(value) => Some(value),
+            DocType::Named { .. } => self.collection_value(),
+            DocType::Nullable { inner, .. } => inner.value_type(),
+            DocType::Union(members) => members.iter().find_map(DocType::value_type),
+            _ => None,
+        }
+    }
+
+    /// The type of an entry of an array shape by its key. Entries without a key are numbered
+    pub fn shape_entry(&self, key: &str) -> Option<&DocType> {
+        match self {
+            DocType::Shape { entries, .. } => entries
+                .iter()
+                .find(|entry| entry.key.as_deref() == Some(key))
+                .or_else(|| {
+                    entries
+                        .iter()
+                        .filter(|entry| entry.key.is_none())
+                        .nth(key.parse().ok()?)
+                })
+                .map(|entry| &entry.value),
+            DocType::Nullable { inner, .. } => inner.shape_entry(key),
+            DocType::Union(members) => members.iter().find_map(|member| member.shape_entry(key)),
+            _ => None,
+        }
+    }
+
+    /// The value type of generic collections like array<int, User>, list<User> or iterable<User>
+    fn collection_value(&self) -> Option<&DocType> {
+        if let DocType::Named { name, arguments } = self {
+            let is_collection = matches!(
+                name.tip()?.to_lowercase().as_ref(),
+                "array" | "list" | "iterable" | "non-empty-array" | "non-empty-list"
+            );
+
+            if is_collection {
+                return arguments.last();
+            }
+        }
+
+        None
+    }
+}
+
+impl std::fmt::Display for DocType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DocType::Named { name, arguments } => {
+                write_type_ref(f, name)?;
+
+                if !arguments.is_empty() {
+                    f.write_str("<")?;
+                    write_joined(f, arguments, ", ")?;
+                    f.write_str(">")?;
+                }
+
+                Ok(())
+            }
+            DocType::Shape { keyword, entries } => {
+                write!(f, "{}{{", label_of(keyword))?;
+
+                for (i, entry) in entries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+
+                    if let Some(key) = entry.key.as_ref() {
+                        write!(f, "{}{}: ", key, if entry.optional { "?" } else { "" })?;
+                    }
+
+                    write!(f, "{}", entry.value)?;
+                }
+
+                f.write_str("}")
+            }
+            DocType::Array(value) => match value.as_ref() {
+                DocType::Union(_) | DocType::Intersection(_) | DocType::Callable { .. } => {
+                    write!(f, "({})[]", value)
+                }
+                _ => write!(f, "{}[]", value),
+            },
+            DocType::Nullable { inner, .. } => write!(f, "?{}", inner),
+            DocType::Union(members) => write_joined(f, members, "|"),
+            DocType::Intersection(members) => write_joined(f, members, "&"),
+            DocType::Callable {
+                name,
+                parameters,
+                return_type,
+            } => {
+                write_type_ref(f, name)?;
+                f.write_str("(")?;
+                write_joined(f, parameters, ", ")?;
+                f.write_str(")")?;
+
+                if let Some(return_type) = return_type {
+                    write!(f, ": {}", return_type)?;
+                }
+
+                Ok(())
+            }
+            DocType::Literal(token) => f.write_str(&label_of(token)),
+        }
+    }
+}
+
+/// Doc comment tokens keep their text as label, even if they represent built in types
+fn label_of(token: &Token) -> String {
+    token.label.clone().unwrap_or_else(|| token.t.to_string())
+}
+
+fn write_type_ref(f: &mut std::fmt::Formatter<'_>, type_ref: &TypeRef) -> std::fmt::Result {
+    for token in &type_ref.kind {
+        f.write_str(&label_of(token))?;
+    }
+
+    Ok(())
+}
+
+fn write_joined(
+    f: &mut std::fmt::Formatter<'_>,
+    types: &[DocType],
+    separator: &str,
+) -> std::fmt::Result {
+    for (i, doc_type) in types.iter().enumerate() {
+        if i > 0 {
+            f.write_str(separator)?;
         }
+
+        write!(f, "{}", doc_type)?;
     }
+
+    Ok(())
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 /// Represents a node in the AST
 pub enum Node {
     /// Represents a doc comment
@@ -341,29 +588,41 @@ pub enum Node {
         params: Vec<Node>,
         var_docs: Vec<Node>,
         properties: Vec<Node>,
+        templates: Vec<Node>,
+        /// Parents with type arguments from @extends, @implements and @use
+        generic_parents: Vec<TypeRef>,
+    },
+    /// Represents a @template inside of a doc comment
+    DocCommentTemplate {
+        name: Token,
+        bound: Option<TypeRef>,
     },
     /// Represents a @param inside of a doc comment
     DocCommentParam {
         name: Token,
 
         types: Option<Vec<TypeRef>>,
+        doc_type: Option<DocType>,
 
         description: String,
     },
     DocCommentProperty {
         name: Token,
         types: Option<Vec<TypeRef>>,
+        doc_type: Option<DocType>,
         description: String,
     },
     DocCommentVar {
         name: Token,
 
         types: Option<Vec<TypeRef>>,
+        doc_type: Option<DocType>,
 
         description: String,
     },
     DocCommentReturn {
         types: Option<Vec<TypeRef>>,
+        doc_type: Option<DocType>,
 
         description: String,
     },
@@ -396,6 +655,16 @@ pub enum Node {
     /// Used as a placeholder for missing nodes. The token acts as an anchor
     Missing(Token),
     Literal(Token),
+    EncapsedString {
+        start: Token,
+        parts: Vec<Node>,
+        end: Token,
+    },
+    EncapsedExpression {
+        oc: Token,
+        expression: Box<Node>,
+        cc: Token,
+    },
     Variable(Token),
     LexicalVariable {
         reference: Option<Token>,
@@ -556,6 +825,8 @@ pub enum Node {
         expr: Box<Node>,
     },
     FunctionArgument {
+        visibility: Option<Token>,
+        is_readonly: Option<Token>,
         argument_type: Option<Box<Node>>,
         name: Token,
         has_default: Option<Token>,
@@ -745,6 +1016,22 @@ pub enum Node {
         body: Box<Node>,
         doc_comment: Option<Box<Node>>,
     },
+    EnumStatement {
+        token: Token,
+        name: Token,
+        backed_type: Option<Box<Node>>,
+        implements: Option<Vec<Node>>,
+        body: Box<Node>,
+        doc_comment: Option<Box<Node>>,
+        attributes: Vec<Node>,
+    },
+    EnumCase {
+        token: Token,
+        name: Token,
+        value: Option<Box<Node>>,
+        doc_comment: Option<Box<Node>>,
+        attributes: Vec<Node>,
+    },
     ClassConstantDefinitionStatement {
         token: Token,
         consts: Vec<Node>,
@@ -769,6 +1056,7 @@ pub enum Node {
         data_type: Option<Box<Node>>,
         is_static: Option<Token>,
         is_abstract: Option<Token>,
+        is_readonly: Option<Token>,
         attributes: Vec<Node>,
     },
     /// Method definition inside a class, interface or trait
@@ -934,6 +1222,9 @@ impl Node {
             | Node::PostUnary { expr, .. }
             | Node::AliasedVariable { expr, .. }
             | Node::DynamicVariable { expr, .. }
+            | Node::EncapsedExpression {
+                expression: expr, ..
+            }
             | Node::NamedParameter { expr, .. }
             | Node::YieldFrom { expr, .. } => vec![expr],
             Node::Ternary {
@@ -962,7 +1253,8 @@ impl Node {
             | Node::ConstStatement {
                 constants: expr, ..
             }
-            | Node::Static { expr, .. } => (*expr).iter().collect(),
+            | Node::Static { expr, .. }
+            | Node::EncapsedString { parts: expr, .. } => (*expr).iter().collect(),
             Node::ArrayElement { key, value, .. } => {
                 if let Some(key) = key {
                     vec![key, value]
@@ -1151,6 +1443,42 @@ impl Node {
                 children
             }
             Node::TraitStatement { body, .. } => vec![body],
+            Node::EnumStatement {
+                doc_comment,
+                backed_type,
+                implements,
+                body,
+                ..
+            } => {
+                let mut children: Vec<&Node> = Vec::new();
+
+                if let Some(doc_comment) = doc_comment {
+                    children.push(doc_comment);
+                }
+
+                if let Some(backed_type) = backed_type {
+                    children.push(backed_type);
+                }
+
+                if let Some(implements) = implements {
+                    children.extend((*implements).iter().collect::<Vec<&Node>>());
+                }
+
+                children.push(body);
+
+                children
+            }
+            Node::EnumCase {
+                value, attributes, ..
+            } => {
+                let mut children: Vec<&Node> = attributes.iter().collect();
+
+                if let Some(value) = value {
+                    children.push(value);
+                }
+
+                children
+            }
             Node::ClassConstantDefinitionStatement {
                 attributes, consts, ..
             } => consts.iter().chain(attributes.iter()).collect(),
@@ -1405,6 +1733,8 @@ impl Node {
             }
             Node::AliasedVariable { variable, expr } => (variable, expr.as_ref()).into(),
             Node::DynamicVariable { variable, cc, .. } => (variable, cc).into(),
+            Node::EncapsedString { start, end, .. } => (start, end).into(),
+            Node::EncapsedExpression { oc, cc, .. } => (oc, cc).into(),
             Node::StaticVariable {
                 variable, value, ..
             } => {
@@ -1496,6 +1826,8 @@ impl Node {
                 }
             }
             Node::FunctionArgument {
+                visibility,
+                is_readonly,
                 argument_type,
                 name,
                 spread,
@@ -1503,7 +1835,11 @@ impl Node {
                 reference,
                 ..
             } => {
-                let start: NodeRange = if let Some(reference) = reference {
+                let start: NodeRange = if let Some(visibility) = visibility {
+                    visibility.into()
+                } else if let Some(is_readonly) = is_readonly {
+                    is_readonly.into()
+                } else if let Some(reference) = reference {
                     reference.into()
                 } else if let Some(argument_type) = argument_type {
                     argument_type.as_ref().range()
@@ -1673,12 +2009,35 @@ impl Node {
                     (is_abstract, stmt.body.as_ref()).into()
                 } else if let Some(is_final) = stmt.is_final.as_ref() {
                     (is_final, stmt.body.as_ref()).into()
+                } else if let Some(is_readonly) = stmt.is_readonly.as_ref() {
+                    (is_readonly, stmt.body.as_ref()).into()
                 } else {
                     (&stmt.token, stmt.body.as_ref()).into()
                 }
             }
             Node::TraitStatement { token, body, .. } => (token, body.as_ref()).into(),
             Node::Interface { token, body, .. } => (token, body.as_ref()).into(),
+            Node::EnumStatement {
+                token,
+                body,
+                doc_comment,
+                ..
+            } => {
+                if let Some(doc_comment) = doc_comment {
+                    (doc_comment.as_ref(), body.as_ref()).into()
+                } else {
+                    (token, body.as_ref()).into()
+                }
+            }
+            Node::EnumCase {
+                token, name, value, ..
+            } => {
+                if let Some(value) = value {
+                    (token, value.as_ref()).into()
+                } else {
+                    (token, name).into()
+                }
+            }
             Node::ClassConstant { name, value, .. } => (name, value.as_ref()).into(),
             Node::ClassConstantDefinitionStatement { consts, .. } => {
                 (consts.first().unwrap(), consts.last().unwrap()).into()
@@ -1804,6 +2163,13 @@ impl Node {
             Node::Missing(token) => token.range(),
             Node::DefineStatement { token, cp, .. } => (token, cp).into(),
             Node::DocComment { comment, .. } => comment.range(),
+            Node::DocCommentTemplate { name, bound } => {
+                if let Some(bound) = bound {
+                    (name, bound).into()
+                } else {
+                    name.into()
+                }
+            }
             Node::DocCommentProperty { name, types, .. } => {
                 if let Some(types) = types {
                     (name, types.last().unwrap()).into()
@@ -408,7 +408,6 @@ impl Scanner {
                         self.push_token(TokenType::AttributeStart);
                     }
                     _ => {
-                        self.advance();
                         self.consume_line_comment();
                     }
                 },
@@ -594,9 +593,18 @@ impl Scanner {
                     self.push_named_token(TokenType::ShellEscape, &string);
                 }
                 '"' => {
+                    let content_start = (self.line, self.col);
                     let string = self.collect_encapsed_and_whitespace_string();
 
-                    self.push_named_token(TokenType::EncapsedAndWhitespaceString, &string);
+                    if let Some(parts) = Scanner::interpolate(&string, content_start) {
+                        self.push_token(TokenType::DoubleQuote);
+                        self.push_tokens(parts);
+
+                        self.start_of_token = (self.line, self.col - 1);
+                        self.push_token(TokenType::DoubleQuote);
+                    } else {
+                        self.push_named_token(TokenType::EncapsedAndWhitespaceString, &string);
+                    }
                 }
                 _ => {
                     if c.is_alphanumeric() || c == '_' {
@@ -619,6 +627,8 @@ impl Scanner {
                             } else {
                                 self.push_named_token(TokenType::Identifier, &name);
                             }
+                        } else if name.to_lowercase() == "enum" && self.next_is_enum_name() {
+                            self.push_token(TokenType::Enum);
                         } else {
                             self.push_named_token(TokenType::Identifier, &name);
                         }
@@ -640,6 +650,9 @@ impl Scanner {
             self.advance();
         }
 
+        // Nowdocs are the single quoted counterpart of heredocs, so nothing is interpolated
+        let is_nowdoc = self.peek() == Some(&'\'');
+
         let marker = match self.peek() {
             // TODO: Do not collect escaped!
             Some('"') => {
@@ -655,6 +668,8 @@ impl Scanner {
 
         self.push_named_token(TokenType::HereDocStart, &marker);
 
+        let content_start = (self.line, self.col);
+
         let mut heredoc = String::new();
         let mut line = String::new();
         let mut potential_end = false;
@@ -711,13 +726,254 @@ impl Scanner {
             return Err(String::from("Unterminated heredoc!"));
         }
 
-        self.push_named_token(TokenType::EncapsedAndWhitespaceString, &heredoc);
+        match Scanner::interpolate(&heredoc, content_start) {
+            Some(parts) if !is_nowdoc => self.push_tokens(parts),
+            _ => self.push_named_token(TokenType::EncapsedAndWhitespaceString, &heredoc),
+        }
+
+        // The end marker is on a line of its own, not where the heredoc started
+        self.start_of_token = (self.line, self.col - line.chars().count() as u32);
         self.push_named_token(TokenType::HereDocEnd, &line);
 
         Ok(())
     }
 
-    /// TODO: Make it support variables inside of it
+    /// Splits the content of a double quoted string or heredoc into literal parts and the tokens of
+    /// the embedded variables and expressions. Returns `None` if nothing is interpolated, so the
+    /// content can remain a single string token.
+    ///
+    /// # Details
+    /// ```php
+    /// "Hello $name, $user->name, $users[0], {$user->getName()} and ${name}"
+    /// ```
+    fn interpolate(content: &str, start: (u32, u32)) -> Option<Vec<Token>> {
+        let chars = content.chars().collect::<Vec<char>>();
+
+        // Position of every char, plus the position right after the content
+        let mut positions = Vec::with_capacity(chars.len() + 1);
+        let (mut line, mut col) = start;
+        for c in chars.iter() {
+            positions.push((line, col));
+
+            if *c == '\n' {
+                line += 1;
+                col = 0;
+            } else {
+                col += 1;
+            }
+        }
+        positions.push((line, col));
+
+        let is_name_start =
+            |i: usize| matches!(chars.get(i), Some(c) if c.is_alphabetic() || *c == '_');
+        let name_end = |i: usize| {
+            let mut end = i;
+            while matches!(chars.get(end), Some(c) if c.is_alphanumeric() || *c == '_') {
+                end += 1;
+            }
+            end
+        };
+        let named = |t: TokenType, from: usize, to: usize| {
+            let (line, col) = positions[from];
+            Token::named(t, line, col, 0, &chars[from..to].iter().collect::<String>())
+        };
+        // Variables start at the $ but are labeled with the name only
+        let variable = |dollar: usize, to: usize| {
+            let mut token = named(TokenType::Variable, dollar + 1, to);
+            token.col = positions[dollar].1;
+
+            token
+        };
+        let unnamed = |t: TokenType, at: usize| Token::new(t, positions[at].0, positions[at].1, 0);
+
+        let mut tokens = Vec::new();
+        let mut literal_start = 0;
+        let mut interpolated = false;
+        let mut i = 0;
+
+        while i < chars.len() {
+            let embedded_start = i;
+            let mut embedded = Vec::new();
+
+            if chars[i] == '\\' {
+                i += 2;
+
+                continue;
+            } else if chars[i] == '$' && is_name_start(i + 1) {
+                // Simple syntax: $var, $var[offset] and $var->property
+                let end = name_end(i + 1);
+                embedded.push(variable(i, end));
+                i = end;
+
+                if chars.get(i) == Some(&'[') {
+                    let offset_start = i + 1;
+                    let (offset, offset_end) = if chars.get(offset_start) == Some(&'$')
+                        && is_name_start(offset_start + 1)
+                    {
+                        let end = name_end(offset_start + 1);
+                        (variable(offset_start, end), end)
+                    } else if matches!(chars.get(offset_start), Some(c) if c.is_ascii_digit() || *c == '-')
+                    {
+                        let mut end = offset_start + 1;
+                        while matches!(chars.get(end), Some(c) if c.is_ascii_digit()) {
+                            end += 1;
+                        }
+                        (named(TokenType::LongNumber, offset_start, end), end)
+                    } else {
+                        let end = name_end(offset_start);
+                        (named(TokenType::EncapsedStringPart, offset_start, end), end)
+                    };
+
+                    if offset_end > offset_start && chars.get(offset_end) == Some(&']') {
+                        embedded.push(unnamed(TokenType::OpenBrackets, i));
+                        embedded.push(offset);
+                        embedded.push(unnamed(TokenType::CloseBrackets, offset_end));
+                        i = offset_end + 1;
+                    }
+                } else if chars.get(i) == Some(&'-')
+                    && chars.get(i + 1) == Some(&'>')
+                    && is_name_start(i + 2)
+                {
+                    let end = name_end(i + 2);
+                    embedded.push(unnamed(TokenType::ObjectOperator, i));
+                    embedded.push(named(TokenType::Identifier, i + 2, end));
+                    i = end;
+                } else if chars.get(i) == Some(&'?')
+                    && chars.get(i + 1) == Some(&'-')
+                    && chars.get(i + 2) == Some(&'>')
+                    && is_name_start(i + 3)
+                {
+                    let end = name_end(i + 3);
+                    embedded.push(unnamed(TokenType::NullsafeObjectOperator, i));
+                    embedded.push(named(TokenType::Identifier, i + 3, end));
+                    i = end;
+                }
+            } else if chars[i] == '{' && chars.get(i + 1) == Some(&'$') {
+                // Complex syntax: {$expression}
+                if let Some(close) = Scanner::matching_curly(&chars, i) {
+                    let code = chars[i + 1..close].iter().collect::<String>();
+
+                    if let Some(expression) = Scanner::scan_embedded(&code, positions[i + 1]) {
+                        embedded.push(unnamed(TokenType::OpenCurly, i));
+                        embedded.extend(expression);
+                        embedded.push(unnamed(TokenType::CloseCurly, close));
+                        i = close + 1;
+                    }
+                }
+            } else if chars[i] == '$' && chars.get(i + 1) == Some(&'{') {
+                // ${name} or the variable variable ${expression}
+                if let Some(close) = Scanner::matching_curly(&chars, i + 1) {
+                    if name_end(i + 2) == close && is_name_start(i + 2) {
+                        embedded.push(unnamed(TokenType::DollarOpenCurlyBraces, i));
+                        embedded.push(named(TokenType::Variable, i + 2, close));
+                        embedded.push(unnamed(TokenType::CloseCurly, close));
+                        i = close + 1;
+                    } else {
+                        let code = chars[i..=close].iter().collect::<String>();
+
+                        if let Some(expression) = Scanner::scan_embedded(&code, positions[i]) {
+                            embedded.extend(expression);
+                            i = close + 1;
+                        }
+                    }
+                }
+            }
+
+            if embedded.is_empty() {
+                i = embedded_start + 1;
+
+                continue;
+            }
+
+            if literal_start < embedded_start {
+                tokens.push(named(
+                    TokenType::EncapsedStringPart,
+                    literal_start,
+                    embedded_start,
+                ));
+            }
+
+            tokens.extend(embedded);
+            literal_start = i;
+            interpolated = true;
+        }
+
+        if !interpolated {
+            return None;
+        }
+
+        if literal_start < chars.len() {
+            tokens.push(named(
+                TokenType::EncapsedStringPart,
+                literal_start,
+                chars.len(),
+            ));
+        }
+
+        Some(tokens)
+    }
+
+    /// Returns the index of the curly brace closing the one at `open`, skipping
+    /// nested braces and quoted strings
+    fn matching_curly(chars: &[char], open: usize) -> Option<usize> {
+        let mut depth = 0;
+        let mut quote = None;
+        let mut escaped = false;
+
+        for (i, c) in chars.iter().enumerate().skip(open) {
+            if let Some(q) = quote {
+                if escaped {
+                    escaped = false;
+                } else if *c == '\\' {
+                    escaped = true;
+                } else if *c == q {
+                    quote = None;
+                }
+
+                continue;
+            }
+
+            match c {
+                '\'' | '"' => quote = Some(*c),
+                '{' => depth += 1,
+                '}' => {
+                    depth -= 1;
+
+                    if depth == 0 {
+                        return Some(i);
+                    }
+                }
+                _ => (),
+            }
+        }
+
+        None
+    }
+
+    /// Scans an expression embedded in a string and moves its tokens to where the
+    /// expression starts within the file
+    fn scan_embedded(code: &str, start: (u32, u32)) -> Option<Vec<Token>> {
+        let mut scanner = Scanner::new(code);
+        scanner.context = Context::InScript;
+        scanner.scan().ok()?;
+
+        Some(
+            scanner
+                .tokens
+                .drain(..)
+                .filter(|token| token.t != TokenType::Eof)
+                .map(|mut token| {
+                    if token.line == 0 {
+                        token.col += start.1;
+                    }
+                    token.line += start.0;
+
+                    token
+                })
+                .collect(),
+        )
+    }
+
     fn collect_encapsed_and_whitespace_string(&mut self) -> String {
         let mut name = String::new();
         let mut escaped = false;
@@ -917,6 +1173,16 @@ impl Scanner {
         ));
     }
 
+    /// Pushes tokens that were created outside of the regular token stream, like the parts of
+    /// a string, and updates their offsets
+    fn push_tokens(&mut self, tokens: Vec<Token>) {
+        for mut token in tokens {
+            token.offset = Some(self.tokens.len());
+
+            self.tokens.push(token);
+        }
+    }
+
     fn push_named_token(&mut self, t: TokenType, name: &str) {
         self.tokens.push(Token::named(
             t,
@@ -940,6 +1206,28 @@ impl Scanner {
         }
     }
 
+    /// Checks if the upcoming chars are the name of an enum declaration. "enum" is not a reserved
+    /// word, so it is only treated as a keyword if it is followed by whitespace and a name that is
+    /// not "extends" or "implements", like in `enum Suit {}`
+    fn next_is_enum_name(&self) -> bool {
+        let mut upcoming = self.chars.iter().rev().peekable();
+
+        if !matches!(upcoming.peek(), Some(c) if c.is_whitespace()) {
+            return false;
+        }
+
+        let name = upcoming
+            .skip_while(|c| c.is_whitespace())
+            .take_while(|c| c.is_alphanumeric() || **c == '_')
+            .collect::<String>()
+            .to_lowercase();
+
+        !name.is_empty()
+            && !name.starts_with(|c: char| c.is_numeric())
+            && name != "extends"
+            && name != "implements"
+    }
+
     /// Returns the correct TokenType for a registered keyword
     fn map_keyword(&self, ident: &str) -> Option<TokenType> {
         match ident {
@@ -999,6 +1287,7 @@ impl Scanner {
             "private" => TokenType::Private,
             "protected" => TokenType::Protected,
             "public" => TokenType::Public,
+            "readonly" => TokenType::Readonly,
             "var" => TokenType::Var,
             "unset" => TokenType::Unset,
             "isset" => TokenType::Isset,
@@ -1182,6 +1471,23 @@ $object->{'\u{6771}\u{4eac}'} = 2020;
             "<?php echo 'blubb' ; /** some multiline comment */ echo 'blabb' ; ?>"
         );
     }
+    #[test]
+    fn test_keeps_the_text_of_line_comments() {
+        let mut scanner = Scanner::new("<?php\n#region A\n// endregion\n");
+
+        scanner.scan().unwrap();
+
+        assert_eq!(
+            vec![Some("region A"), Some(" endregion")],
+            scanner
+                .tokens
+                .iter()
+                .filter(|t| t.t == TokenType::LineComment)
+                .map(|t| t.label.as_deref())
+                .collect::<Vec<Option<&str>>>()
+        );
+    }
+
     #[test]
     fn test_parses_line_comments_and_respects_end_of_script() {
         let mut scanner = Scanner::new("<?php // Line comment ?> <h1> test </h1>");
@@ -1522,4 +1828,62 @@ $object->{'\u{6771}\u{4eac}'} = 2020;
             "<?php $a = match ( $x ) { 2 , 1 => 1 , default => 0 } ; ?>"
         );
     }
+
+    #[test]
+    fn test_scans_enum_only_in_declarations() {
+        let mut scanner =
+            Scanner::new("<?php enum Suit: string {} Enum::from(1); enum implements; ?>");
+
+        scanner.scan().unwrap();
+
+        let enum_tokens = scanner
+            .tokens
+            .iter()
+            .filter(|t| t.t == TokenType::Enum)
+            .count();
+
+        assert_eq!(1, enum_tokens);
+        assert_eq!(
+            token_list!(scanner.tokens),
+            "<?php enum Suit : string { } Enum :: from ( 1 ) ; enum implements ; ?>"
+        );
+    }
+
+    #[test]
+    fn test_scans_variables_interpolated_in_strings() {
+        let mut scanner = Scanner::new(
+            "<?php \"Hi $name, {$user->getName()} $items[0] ${var} \\$no\"; \"plain\";",
+        );
+
+        scanner.scan().unwrap();
+
+        assert_eq!(
+            token_list!(scanner.tokens),
+            "<?php \" Hi  $name ,  { $user -> getName ( ) }   $items [ 0 ]   ${ $var }  \\$no \" ; \"plain\" ; "
+        );
+
+        let user = scanner
+            .tokens
+            .iter()
+            .find(|t| t.label == Some("user".to_owned()))
+            .unwrap();
+        assert_eq!((0, 18), (user.line, user.col));
+    }
+
+    #[test]
+    fn test_scans_variables_interpolated_in_heredocs_only() {
+        let mut scanner =
+            Scanner::new("<?php $a = <<<EOT\n$name\nEOT;\n$b = <<<'EOT'\n$name\nEOT;\n");
+
+        scanner.scan().unwrap();
+
+        let variables = scanner
+            .tokens
+            .iter()
+            .filter(|t| t.label == Some("name".to_owned()))
+            .collect::<Vec<&Token>>();
+
+        assert_eq!(1, variables.len());
+        assert_eq!((1, 0), (variables[0].line, variables[0].col));
+    }
 }
@@ -1,17 +1,18 @@
 use std::fmt::{Display, Formatter, Result};
 
+use serde::{Deserialize, Serialize};
 use tower_lsp::lsp_types::{CompletionItem, CompletionItemKind};
 
 use super::node::NodeRange;
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ScriptStartType {
     Regular,
     Short,
     Echo,
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum TokenType {
     Eof,
 
@@ -107,6 +108,11 @@ pub enum TokenType {
     BinaryNumber,
     ConstantEncapsedString,
     EncapsedAndWhitespaceString,
+    // A literal part of a string that contains interpolated variables or expressions
+    EncapsedStringPart,
+    // The quotes around a string that contains interpolated variables or expressions
+    DoubleQuote,
+    DollarOpenCurlyBraces,
     ShellEscape,
     HereDocStart,
     HereDocEnd,
@@ -170,6 +176,7 @@ pub enum TokenType {
     Private,
     Protected,
     Public,
+    Readonly,
     Var,
     Unset,
     Isset,
@@ -179,6 +186,7 @@ pub enum TokenType {
     Class,
     Trait,
     Interface,
+    Enum,
     Extends,
     Implements,
     List,
@@ -316,7 +324,7 @@ impl TokenType {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Eq)]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Token {
     pub col: u32,
     pub line: u32,
@@ -404,6 +412,7 @@ impl Token {
             | TokenType::Private
             | TokenType::Protected
             | TokenType::Public
+            | TokenType::Readonly
             | TokenType::Var
             | TokenType::Unset
             | TokenType::Isset
@@ -413,6 +422,7 @@ impl Token {
             | TokenType::Class
             | TokenType::Trait
             | TokenType::Interface
+            | TokenType::Enum
             | TokenType::Extends
             | TokenType::Implements
             | TokenType::List
@@ -511,6 +521,27 @@ impl Token {
         )
     }
 
+    /// True for `=` and all compound assignments like `.=`, `+=` or `??=`
+    pub fn is_assignment(&self) -> bool {
+        matches!(
+            self.t,
+            TokenType::Assignment
+                | TokenType::BinaryAndAssignment
+                | TokenType::BinaryOrAssignment
+                | TokenType::ModuloAssignment
+                | TokenType::ConcatAssignment
+                | TokenType::XorAssignment
+                | TokenType::RightShiftAssignment
+                | TokenType::LeftShiftAssignment
+                | TokenType::CoalesceAssignment
+                | TokenType::PowerAssignment
+                | TokenType::PlusAssign
+                | TokenType::MinusAssign
+                | TokenType::MulAssign
+                | TokenType::DivAssign
+        )
+    }
+
     pub fn gap_to(&self, next: &Token) -> (u32, u32) {
         if self.line == next.line {
             (next.start().1 - self.end().1, 0)
@@ -693,6 +724,10 @@ impl Display for TokenType {
             TokenType::Variable => "$".to_owned(),
             TokenType::Linebreak => "".to_owned(),
             TokenType::Match => "match".to_owned(),
+            TokenType::Enum => "enum".to_owned(),
+            TokenType::Readonly => "readonly".to_owned(),
+            TokenType::DoubleQuote => "\"".to_owned(),
+            TokenType::DollarOpenCurlyBraces => "${".to_owned(),
             _ => unreachable!("Should have never been called with {:?}", self),
         };
 
@@ -725,6 +760,7 @@ impl Display for Token {
             TokenType::EncapsedAndWhitespaceString => {
                 format!("\"{}\"", self.label.as_ref().unwrap())
             }
+            TokenType::EncapsedStringPart => self.label.as_ref().unwrap().to_string(),
             TokenType::ShellEscape => format!("`{}`", self.label.as_ref().unwrap()),
             TokenType::HereDocStart => format!("<<<{}", self.label.as_ref().unwrap()),
             TokenType::HereDocEnd => self.label.as_ref().unwrap().to_string(),
@@ -1,5 +1,5 @@
 use crate::{
-    backend::FileReferenceMap,
+    backend::{FileReferenceMap, FileTypeMap},
     environment::{
         symbol::{PhpSymbolKind, Symbol, SymbolAlias, Visibility},
         visitor::name_resolver::NameResolver,
@@ -99,7 +99,7 @@ impl Suggestion {
 /// Find the node at the current cursor position, if any, and return it
 /// along with the path to it, as the nodes themselve do not have links to
 /// their parents
-fn find<'a>(
+pub(crate) fn find<'a>(
     n: &'a AstNode,
     position: &Position,
     mut ancestors: Vec<&'a AstNode>,
@@ -210,10 +210,11 @@ fn suggest_variables_of_scope(scope: NodeId, arena: &Arena<Symbol>) -> Vec<Sugge
         })
         .collect();
 
-    if let Some(parent_class) = scope
-        .ancestors(arena)
-        .find(|node| arena[*node].get().kind == PhpSymbolKind::Class)
-    {
+    if let Some(parent_class) = scope.ancestors(arena).find(|node| {
+        let kind = arena[*node].get().kind;
+
+        kind == PhpSymbolKind::Class || kind == PhpSymbolKind::Enum
+    }) {
         suggestions.push(Suggestion::this(parent_class, SuggestionContext::Reference));
     }
 
@@ -229,6 +230,7 @@ fn suggest_members_of_symbol(
     global_symbols: &HashMap<String, NodeId>,
     symbol_under_cursor: NodeId,
     references: &FileReferenceMap,
+    inferred_types: Option<&FileTypeMap>,
 ) -> Vec<Suggestion> {
     let mut no_magic_const = false;
 
@@ -245,7 +247,15 @@ fn suggest_members_of_symbol(
     // If a $this is encountered it we already found the parent
     let mut resolved_parent = None;
     if let AstNode::Member { object, .. } = node {
-        parent_range = object.range();
+        // The type of a chain like $object->method()-> is the one of its last link
+        parent_range = match object.as_ref() {
+            AstNode::Call { callee, .. } => match callee.as_ref() {
+                AstNode::Member { member, .. } => member.range(),
+                _ => object.range(),
+            },
+            AstNode::Member { member, .. } => member.range(),
+            _ => object.range(),
+        };
         no_magic_const = true;
     } else if let AstNode::Member { object, .. } = parent {
         no_magic_const = true;
@@ -285,7 +295,7 @@ fn suggest_members_of_symbol(
     let current_class = symbol_under_cursor.ancestors(arena).find(|n| {
         let s = arena[*n].get();
 
-        s.kind == PhpSymbolKind::Class
+        s.kind == PhpSymbolKind::Class || s.kind == PhpSymbolKind::Enum
     });
 
     // Collect a list of all accessible members of this class and its parents
@@ -314,62 +324,80 @@ fn suggest_members_of_symbol(
 
     let mut resolver = NameResolver::new(global_symbols, symbol_under_cursor);
 
+    // Prefer the types inferred at this point of the control flow over all types the
+    // variable ever had
+    let mut types = if let Some(types) = inferred_types.and_then(|inferred_types| {
+        inferred_types
+            .iter()
+            .find(|(range, _)| in_range(&pos, &get_range(**range)))
+            .map(|(_, types)| types.clone())
+    }) {
+        types
+    } else {
+        arena[*resolved_parent]
+            .get()
+            .data_types
+            .iter()
+            .filter_map(|dt_reference| {
+                if dt_reference.node.is_some() {
+                    return dt_reference.node;
+                }
+
+                if "$this" == dt_reference.type_ref.root().unwrap() {
+                    return arena[*resolved_parent].parent();
+                }
+
+                resolver.resolve_type_ref(
+                    &dt_reference.type_ref,
+                    arena,
+                    &symbol_under_cursor,
+                    false,
+                )
+            })
+            .collect::<Vec<NodeId>>()
+    };
+
     let mut suggestions: Vec<Suggestion> = Vec::new();
     let static_only = Some(':') == trigger;
-    arena[*resolved_parent]
-        .get()
-        .data_types
-        .iter()
-        .filter_map(|dt_reference| {
-            if dt_reference.node.is_some() {
-                return dt_reference.node;
-            }
+    types.drain(..).for_each(|node| {
+        let mut resolver = NameResolver::new(global_symbols, symbol_under_cursor);
+        suggestions.extend(
+            arena[node]
+                .get()
+                .get_all_symbols(node, &mut resolver, arena)
+                .iter()
+                .filter_map(|(_, n)| {
+                    if !n.alias.starts_with(&prefix) {
+                        return None;
+                    }
 
-            if "$this" == dt_reference.type_ref.root().unwrap() {
-                return arena[*resolved_parent].parent();
-            }
+                    let s = arena[n.symbol].get();
 
-            resolver.resolve_type_ref(&dt_reference.type_ref, arena, &symbol_under_cursor, false)
-        })
-        .for_each(|node| {
-            let mut resolver = NameResolver::new(global_symbols, symbol_under_cursor);
-            suggestions.extend(
-                arena[node]
-                    .get()
-                    .get_all_symbols(node, &mut resolver, arena)
-                    .iter()
-                    .filter_map(|(_, n)| {
-                        if !n.alias.starts_with(&prefix) {
-                            return None;
-                        }
-
-                        let s = arena[n.symbol].get();
-
-                        if static_only && !s.is_static
-                            || no_magic_const && s.kind == PhpSymbolKind::MagicConst
-                        {
-                            return None;
-                        }
-
-                        // Either the element is accessible from this scope anyway or its public ...
-                        if n.visibility >= Visibility::Public
-                            || accessible_members.contains_key(&s.normalized_name())
-                        {
-                            Some(Suggestion::aliased(
-                                n.symbol,
-                                n.alias,
-                                SuggestionContext::Call,
-                                None,
-                            ))
-                        } else {
-                            None
-                        }
-
-                        // ... or we ignore it
-                    })
-                    .collect::<Vec<Suggestion>>(),
-            );
-        });
+                    if static_only && !s.is_static
+                        || no_magic_const && s.kind == PhpSymbolKind::MagicConst
+                    {
+                        return None;
+                    }
+
+                    // Either the element is accessible from this scope anyway or its public ...
+                    if n.visibility >= Visibility::Public
+                        || accessible_members.contains_key(&s.normalized_name())
+                    {
+                        Some(Suggestion::aliased(
+                            n.symbol,
+                            n.alias,
+                            SuggestionContext::Call,
+                            None,
+                        ))
+                    } else {
+                        None
+                    }
+
+                    // ... or we ignore it
+                })
+                .collect::<Vec<Suggestion>>(),
+        );
+    });
 
     suggestions
 }
@@ -400,6 +428,7 @@ fn suggest_keywords(arena: &Arena<Symbol>, symbol_under_cursor: NodeId) -> Vec<S
 /// * arena: A reference to the memory arena storing all the symbols
 /// * global_symbols: A lookup table to globally available symbols
 /// * references: Collected references in the current document
+/// * inferred_types: Inferred types of the variables in the current document
 pub fn get_suggestions_at(
     trigger: Option<char>,
     pos: Position,
@@ -408,6 +437,7 @@ pub fn get_suggestions_at(
     arena: &Arena<Symbol>,
     global_symbols: &HashMap<String, NodeId>,
     references: &FileReferenceMap,
+    inferred_types: Option<&FileTypeMap>,
 ) -> Vec<Suggestion> {
     let (node, mut ancestors) =
         if let Some((node, mut ancestors)) = ast.iter().find_map(|n| find(n, &pos, Vec::new())) {
@@ -444,6 +474,7 @@ pub fn get_suggestions_at(
                 global_symbols,
                 symbol_under_cursor,
                 references,
+                inferred_types,
             );
         }
         _ => (),
@@ -503,6 +534,10 @@ mod tests {
         let pos = Position { line, character };
 
         let references = state.symbol_references.get(sources[file].0).unwrap();
+        let inferred_types = state
+            .inferred_types
+            .get(sources[file].0)
+            .map(|types| &**types);
         let suc = state.files.get(sources[file].0).unwrap();
         let file = state.arena[*suc].get();
         let mut suggestions = super::get_suggestions_at(
@@ -513,6 +548,7 @@ mod tests {
             &state.arena,
             &state.global_symbols,
             &references,
+            inferred_types,
         );
 
         suggestions
@@ -564,6 +600,44 @@ mod tests {
         assert!(actual.contains(&&"getPulse".to_string()));
     }
 
+    #[tokio::test]
+    async fn test_suggests_members_of_inferred_type() {
+        let sources = vec![
+            (
+                "index.php",
+                "<?php class A { function a() {} } class B { function b() {} } $x = new A(); $x = new B(); $x->",
+            ),
+            (
+                "index2.php",
+                "<?php class A { function a() {} } class B { function b() {} } if ($y instanceof B) { $y-> }",
+            ),
+        ];
+
+        let actual = suggestions(&sources, 0, 0, 94, Some('>'));
+        assert_eq!(vec!["b".to_string()], actual);
+
+        let actual = suggestions(&sources, 1, 0, 89, Some('>'));
+        assert_eq!(vec!["b".to_string()], actual);
+    }
+
+    #[tokio::test]
+    async fn test_suggests_members_of_template_types() {
+        let sources = vec![
+            ("user.php", "<?php class User { function name() {} }"),
+            (
+                "collection.php",
+                "<?php /** @template T */ class Collection { /** @return T|null */ function first() {} }",
+            ),
+            (
+                "index.php",
+                "<?php /** @var Collection<User> $users */ $users->first()->",
+            ),
+        ];
+
+        let actual = suggestions(&sources, 2, 0, 59, Some('>'));
+        assert_eq!(vec!["name".to_string()], actual);
+    }
+
     #[tokio::test]
     async fn test_suggests_variables() {
         let sources = vec![("index.php", "<?php $cat = 'Marci'; $")];